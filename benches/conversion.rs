@@ -0,0 +1,63 @@
+//! Conversion benchmarks on synthetic images
+//!
+//! Run with: cargo bench
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use pdf_converter::PdfConverter;
+use std::path::{Path, PathBuf};
+
+/// Image sizes in pixels used by every benchmark
+const SIZES: [(u32, u32); 3] = [(640, 480), (1920, 1080), (2480, 3508)];
+
+/// Write a gradient test image and return its path
+fn write_image(dir: &Path, name: &str, width: u32, height: u32) -> PathBuf {
+    let img = image::RgbImage::from_fn(width, height, |x, y| {
+        image::Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+    });
+    let path = dir.join(name);
+    img.save(&path).expect("failed to write benchmark image");
+    path
+}
+
+fn bench_single_image(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let converter = PdfConverter::new();
+    let mut group = c.benchmark_group("single_image");
+    group.sample_size(10);
+
+    for (width, height) in SIZES {
+        let image = write_image(dir.path(), &format!("{}x{}.png", width, height), width, height);
+        let output = dir.path().join("single.pdf");
+        group.bench_with_input(BenchmarkId::from_parameter(format!("{}x{}", width, height)), &image, |b, image| {
+            b.iter(|| converter.convert_image_to_pdf(image, &output).unwrap());
+        });
+    }
+    group.finish();
+}
+
+fn bench_folder(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let images = dir.path().join("images");
+    std::fs::create_dir(&images).unwrap();
+    for (i, (width, height)) in SIZES.iter().enumerate() {
+        write_image(&images, &format!("{:02}.png", i), *width, *height);
+    }
+
+    let converter = PdfConverter::new();
+    let output = dir.path().join("folder.pdf");
+    let mut group = c.benchmark_group("folder");
+    group.sample_size(10);
+
+    // 解码和几何计算
+    group.bench_function("decode", |b| {
+        b.iter(|| converter.for_each_image(&images, |_, _, _| {}).unwrap());
+    });
+    // 解码、组装并保存PDF
+    group.bench_function("decode_and_assemble", |b| {
+        b.iter(|| converter.convert_folder_to_pdf(&images, &output).unwrap());
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_single_image, bench_folder);
+criterion_main!(benches);
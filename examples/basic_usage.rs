@@ -23,11 +23,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     match std::fs::read_dir(test_folder) {
         Ok(entries) => {
             let mut count = 0;
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    println!("  Found file: {}", entry.file_name().to_string_lossy());
-                    count += 1;
-                }
+            for entry in entries.flatten() {
+                println!("  Found file: {}", entry.file_name().to_string_lossy());
+                count += 1;
             }
             println!("  Total files found: {}", count);
             
@@ -69,6 +67,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         margin_mm: 10.0,  // Smaller margins
         dpi: 150.0,       // Lower DPI for smaller file size
         title: "Custom PDF Document".to_string(),
+        ..PdfConfig::default()
     };
     
     let custom_converter = PdfConverter::with_config(custom_config);
@@ -91,23 +90,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Example 3: Convert single image
     println!("\n🔄 Example 3: Convert single image");
     if let Ok(entries) = std::fs::read_dir(test_folder) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.is_file() {
-                    if let Some(extension) = path.extension() {
-                        if let Some(ext_str) = extension.to_str() {
-                            let ext_lower = ext_str.to_lowercase();                            if ["jpg", "jpeg", "png", "gif", "bmp", "webp"].contains(&ext_lower.as_str()) {
-                                let single_output = "single_image.pdf";
-                                match converter.convert_image_to_pdf(path.to_str().unwrap(), single_output) {
-                                    Ok(()) => {
-                                        println!("✅ Single image PDF generated: {} -> {}", 
-                                            path.display(), single_output);
-                                        break;
-                                    },
-                                    Err(e) => {
-                                        println!("❌ Single image conversion failed: {}", e);
-                                    }
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_file() {
+                if let Some(extension) = path.extension() {
+                    if let Some(ext_str) = extension.to_str() {
+                        let ext_lower = ext_str.to_lowercase();                            if ["jpg", "jpeg", "png", "gif", "bmp", "webp"].contains(&ext_lower.as_str()) {
+                            let single_output = "single_image.pdf";
+                            match converter.convert_image_to_pdf(path.to_str().unwrap(), single_output) {
+                                Ok(()) => {
+                                    println!("✅ Single image PDF generated: {} -> {}", 
+                                        path.display(), single_output);
+                                    break;
+                                },
+                                Err(e) => {
+                                    println!("❌ Single image conversion failed: {}", e);
                                 }
                             }
                         }
@@ -72,6 +72,7 @@ fn test_custom_config_conversion() -> Result<(), Box<dyn std::error::Error>> {
         margin_mm: 15.0,
         dpi: 300.0,
         title: "高质量PDF文档".to_string(),
+        ..PdfConfig::default()
     };
     
     // 快速配置（低DPI）
@@ -81,6 +82,7 @@ fn test_custom_config_conversion() -> Result<(), Box<dyn std::error::Error>> {
         margin_mm: 20.0,
         dpi: 150.0,
         title: "快速生成PDF文档".to_string(),
+        ..PdfConfig::default()
     };
     
     let configs = [
@@ -93,12 +95,12 @@ fn test_custom_config_conversion() -> Result<(), Box<dyn std::error::Error>> {
         
         if Path::new("test_images/1.jpg").exists() {
             let start_time = Instant::now();
-            match converter.convert_image_to_pdf("test_images/1.jpg", &output) {
+            match converter.convert_image_to_pdf("test_images/1.jpg", output) {
                 Ok(()) => {
                     let duration = start_time.elapsed();
                     println!("  ✅ {} 配置: {} (用时: {:?})", description, output, duration);
                     
-                    if let Ok(metadata) = std::fs::metadata(&output) {
+                    if let Ok(metadata) = std::fs::metadata(output) {
                         println!("     📄 文件大小: {} KB", metadata.len() / 1024);
                     }
                 }
@@ -199,6 +201,7 @@ fn test_performance() -> Result<(), Box<dyn std::error::Error>> {
             margin_mm: 20.0,
             dpi: *dpi,
             title: format!("{}测试", description),
+            ..PdfConfig::default()
         };
           let converter = PdfConverter::with_config(config);
         let output = format!("comprehensive_perf_{}.pdf", *dpi as i32);
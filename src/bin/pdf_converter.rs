@@ -0,0 +1,106 @@
+//! Command line front end for pdf-converter
+//!
+//! Usage:
+//!
+//! ```text
+//! pdf-converter <folder|archive.zip> -o out.pdf
+//! pdf-converter --stdin -o out.pdf
+//! ```
+//!
+//! With `--stdin`, images are read from standard input as length-prefixed
+//! blobs (a 4-byte big-endian length followed by the file contents), so a
+//! producer can pipe images in without temporary files. Settings come from
+//! the `PDF_*` environment variables read by `PdfConfig::from_env`.
+//!
+//! With `--progress=json`, one JSON object per converted image is written to
+//! standard error, e.g.
+//! `{"index":0,"total":12,"filename":"scan_001.jpg","elapsed":0.41}` with
+//! `total` `null` when unknown and `elapsed` in seconds since the start.
+
+use pdf_converter::{PdfConfig, PdfConverter};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: pdf-converter (<folder|archive.zip> | --stdin) -o <output.pdf> [--progress=json]";
+
+/// Where the images come from
+enum Input {
+    Path(PathBuf),
+    Stdin,
+}
+
+/// Parsed command line
+struct Args {
+    input: Input,
+    output: PathBuf,
+    json_progress: bool,
+}
+
+fn main() -> ExitCode {
+    let Args { input, output, json_progress } = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        // 空消息表示请求了帮助
+        Err(message) if message.is_empty() => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("{message}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = PdfConfig::from_env().and_then(|config| {
+        let mut converter = PdfConverter::with_config(config);
+        if json_progress {
+            converter = converter.with_progress(|progress| {
+                let line = serde_json::json!({
+                    "index": progress.index,
+                    "total": progress.total,
+                    "filename": progress.image.path.file_name().unwrap_or_default().to_string_lossy(),
+                    "elapsed": progress.elapsed.as_secs_f64(),
+                });
+                eprintln!("{line}");
+            });
+        }
+        match input {
+            Input::Stdin => converter.convert_image_stream_to_pdf(std::io::stdin().lock(), &output),
+            Input::Path(path) if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip")) => {
+                converter.convert_zip_to_pdf(&path, &output)
+            }
+            Input::Path(path) => converter.convert_folder_to_pdf(&path, &output),
+        }
+    });
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("❌ {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Input, output path and options from the command line arguments
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Args, String> {
+    let mut input = None;
+    let mut output = None;
+    let mut json_progress = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--stdin" => input = Some(Input::Stdin),
+            "-o" | "--output" => output = Some(PathBuf::from(args.next().ok_or("missing value for -o")?)),
+            "--progress=json" => json_progress = true,
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+            _ => input = Some(Input::Path(PathBuf::from(arg))),
+        }
+    }
+
+    match (input, output) {
+        (Some(input), Some(output)) => Ok(Args { input, output, json_progress }),
+        (None, _) => Err("missing input".to_string()),
+        (_, None) => Err("missing output".to_string()),
+    }
+}
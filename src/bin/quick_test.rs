@@ -1,7 +1,7 @@
 //! Quick test for pdf-converter functionality
 //! This is a minimal test to verify basic functionality
 
-use pdf_converter::PdfConverter;
+use pdf_converter::{PdfConfig, PdfConverter};
 use std::path::Path;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -37,7 +37,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 fn test_folder_conversion(folder: &str) -> Result<(), Box<dyn std::error::Error>> {
     println!("🔄 Testing folder conversion: {}", folder);
     
-    let converter = PdfConverter::new();
+    let converter = quick_test_converter();
     let output = "quick_test_folder.pdf";
     
     match converter.convert_folder_to_pdf(folder, output) {
@@ -62,7 +62,7 @@ fn test_folder_conversion(folder: &str) -> Result<(), Box<dyn std::error::Error>
 fn test_single_image(image_path: &str) -> Result<(), Box<dyn std::error::Error>> {
     println!("🔄 Testing single image conversion: {}", image_path);
     
-    let converter = PdfConverter::new();
+    let converter = quick_test_converter();
     let output = "quick_test_single.pdf";
     
     match converter.convert_image_to_pdf(image_path, output) {
@@ -83,22 +83,23 @@ fn test_single_image(image_path: &str) -> Result<(), Box<dyn std::error::Error>>
     Ok(())
 }
 
+/// Converter for the quick test, opening the results when built with the `open` feature
+fn quick_test_converter() -> PdfConverter {
+    #[allow(unused_mut)]
+    let mut config = PdfConfig::default();
+    #[cfg(feature = "open")]
+    {
+        config.open_after = true;
+    }
+    PdfConverter::with_config(config)
+}
+
 fn find_any_image(dir: &str) -> Option<String> {
-    let image_extensions = ["jpg", "jpeg", "png", "gif", "bmp", "webp"];
-    
     if let Ok(entries) = std::fs::read_dir(dir) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.is_file() {
-                    if let Some(extension) = path.extension() {
-                        if let Some(ext_str) = extension.to_str() {
-                            if image_extensions.contains(&ext_str.to_lowercase().as_str()) {
-                                return Some(path.to_string_lossy().to_string());
-                            }
-                        }
-                    }
-                }
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_file() && PdfConverter::is_supported_image(&path) {
+                return Some(path.to_string_lossy().to_string());
             }
         }
     }
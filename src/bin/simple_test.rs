@@ -38,28 +38,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Test 2: Find and convert a single image
     println!("\n🔄 Testing single image conversion...");
     if let Ok(entries) = std::fs::read_dir(test_folder) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.is_file() {
-                    if let Some(extension) = path.extension() {
-                        if let Some(ext_str) = extension.to_str() {
-                            let ext_lower = ext_str.to_lowercase();
-                            if ["jpg", "jpeg", "png"].contains(&ext_lower.as_str()) {
-                                let single_output = "simple_single_test.pdf";
-                                match converter.convert_image_to_pdf(path.as_path(), std::path::Path::new(single_output)) {
-                                    Ok(()) => {
-                                        println!("✅ Single image conversion successful: {} -> {}", 
-                                            path.display(), single_output);
-                                        if Path::new(single_output).exists() {
-                                            let metadata = std::fs::metadata(single_output)?;
-                                            println!("📄 PDF size: {} KB", metadata.len() / 1024);
-                                        }
-                                        break;
-                                    }
-                                    Err(e) => {
-                                        println!("❌ Single image conversion failed: {}", e);
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_file() {
+                if let Some(extension) = path.extension() {
+                    if let Some(ext_str) = extension.to_str() {
+                        let ext_lower = ext_str.to_lowercase();
+                        if ["jpg", "jpeg", "png"].contains(&ext_lower.as_str()) {
+                            let single_output = "simple_single_test.pdf";
+                            match converter.convert_image_to_pdf(path.as_path(), std::path::Path::new(single_output)) {
+                                Ok(()) => {
+                                    println!("✅ Single image conversion successful: {} -> {}", 
+                                        path.display(), single_output);
+                                    if Path::new(single_output).exists() {
+                                        let metadata = std::fs::metadata(single_output)?;
+                                        println!("📄 PDF size: {} KB", metadata.len() / 1024);
                                     }
+                                    break;
+                                }
+                                Err(e) => {
+                                    println!("❌ Single image conversion failed: {}", e);
                                 }
                             }
                         }
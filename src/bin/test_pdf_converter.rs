@@ -27,19 +27,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut image_count = 0;
     match std::fs::read_dir(test_folder) {
         Ok(entries) => {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.is_file() {
-                        if let Some(extension) = path.extension() {
-                            if let Some(ext_str) = extension.to_str() {
-                                let ext_lower = ext_str.to_lowercase();
-                                if ["jpg", "jpeg", "png", "gif", "bmp", "webp"].contains(&ext_lower.as_str()) {
-                                    println!("  📷 {} ({})", 
-                                        entry.file_name().to_string_lossy(), 
-                                        ext_str.to_uppercase());
-                                    image_count += 1;
-                                }
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_file() {
+                    if let Some(extension) = path.extension() {
+                        if let Some(ext_str) = extension.to_str() {
+                            let ext_lower = ext_str.to_lowercase();
+                            if ["jpg", "jpeg", "png", "gif", "bmp", "webp"].contains(&ext_lower.as_str()) {
+                                println!("  📷 {} ({})", 
+                                    entry.file_name().to_string_lossy(), 
+                                    ext_str.to_uppercase());
+                                image_count += 1;
                             }
                         }
                     }
@@ -117,6 +115,7 @@ fn test_folder_conversion_custom(test_folder: &str) -> Result<(), Box<dyn std::e
         margin_mm: 10.0,  // Smaller margins
         dpi: 150.0,       // Lower DPI for faster processing
         title: "Custom Settings Test PDF".to_string(),
+        ..PdfConfig::default()
     };
     
     let converter = PdfConverter::with_config(custom_config);
@@ -148,33 +147,31 @@ fn test_single_image_conversion(test_folder: &str) -> Result<(), Box<dyn std::er
     // Find the first image file
     match std::fs::read_dir(test_folder) {
         Ok(entries) => {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.is_file() {
-                        if let Some(extension) = path.extension() {
-                            if let Some(ext_str) = extension.to_str() {
-                                let ext_lower = ext_str.to_lowercase();
-                                if ["jpg", "jpeg", "png", "gif", "bmp", "webp"].contains(&ext_lower.as_str()) {
-                                    let output_path = "test_single_image.pdf";
-                                    
-                                    let start_time = Instant::now();
-                                    match converter.convert_image_to_pdf(path.clone(), output_path.into()) {
-                                        Ok(()) => {
-                                            let duration = start_time.elapsed();
-                                            println!("  ✅ Success: {} -> {} (took {:?})", 
-                                                path.display(), output_path, duration);
-                                            
-                                            if Path::new(output_path).exists() {
-                                                let metadata = std::fs::metadata(output_path)?;
-                                                println!("     📄 File size: {} KB", metadata.len() / 1024);
-                                            }
-                                            return Ok(());
-                                        }
-                                        Err(e) => {
-                                            println!("  ❌ Failed: {}", e);
-                                            return Err(e.into());
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_file() {
+                    if let Some(extension) = path.extension() {
+                        if let Some(ext_str) = extension.to_str() {
+                            let ext_lower = ext_str.to_lowercase();
+                            if ["jpg", "jpeg", "png", "gif", "bmp", "webp"].contains(&ext_lower.as_str()) {
+                                let output_path = "test_single_image.pdf";
+                                
+                                let start_time = Instant::now();
+                                match converter.convert_image_to_pdf(&path, output_path) {
+                                    Ok(()) => {
+                                        let duration = start_time.elapsed();
+                                        println!("  ✅ Success: {} -> {} (took {:?})", 
+                                            path.display(), output_path, duration);
+                                        
+                                        if Path::new(output_path).exists() {
+                                            let metadata = std::fs::metadata(output_path)?;
+                                            println!("     📄 File size: {} KB", metadata.len() / 1024);
                                         }
+                                        return Ok(());
+                                    }
+                                    Err(e) => {
+                                        println!("  ❌ Failed: {}", e);
+                                        return Err(e.into());
                                     }
                                 }
                             }
@@ -201,6 +198,7 @@ fn test_performance(test_folder: &str) -> Result<(), Box<dyn std::error::Error>>
         margin_mm: 15.0,
         dpi: 300.0,  // High DPI
         title: "High Quality Performance Test".to_string(),
+        ..PdfConfig::default()
     };
     
     // Test with fast settings
@@ -210,6 +208,7 @@ fn test_performance(test_folder: &str) -> Result<(), Box<dyn std::error::Error>>
         margin_mm: 15.0,
         dpi: 100.0,  // Low DPI for speed
         title: "Fast Performance Test".to_string(),
+        ..PdfConfig::default()
     };
     
     // High quality test
@@ -261,35 +260,33 @@ fn test_different_formats(test_folder: &str) -> Result<(), Box<dyn std::error::E
     
     match std::fs::read_dir(test_folder) {
         Ok(entries) => {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.is_file() {
-                        if let Some(extension) = path.extension() {
-                            if let Some(ext_str) = extension.to_str() {
-                                let ext_lower = ext_str.to_lowercase();
-                                if format_extensions.contains(&ext_lower.as_str()) {
-                                    let output_path = format!("test_format_{}.pdf", ext_lower);
-                                    
-                                    let start_time = Instant::now();
-                                    match converter.convert_image_to_pdf(path.clone(), output_path.as_str().into()) {
-                                        Ok(()) => {
-                                            let duration = start_time.elapsed();
-                                            println!("  ✅ {}: {} -> {} (took {:?})", 
-                                                ext_str.to_uppercase(),
-                                                path.file_name().unwrap().to_string_lossy(),
-                                                output_path, 
-                                                duration);
-                                            
-                                            if Path::new(&output_path).exists() {
-                                                let metadata = std::fs::metadata(&output_path)?;
-                                                println!("       📄 Size: {} KB", metadata.len() / 1024);
-                                            }
-                                        }
-                                        Err(e) => {
-                                            println!("  ❌ {} failed: {}", ext_str.to_uppercase(), e);
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_file() {
+                    if let Some(extension) = path.extension() {
+                        if let Some(ext_str) = extension.to_str() {
+                            let ext_lower = ext_str.to_lowercase();
+                            if format_extensions.contains(&ext_lower.as_str()) {
+                                let output_path = format!("test_format_{}.pdf", ext_lower);
+                                
+                                let start_time = Instant::now();
+                                match converter.convert_image_to_pdf(&path, &output_path) {
+                                    Ok(()) => {
+                                        let duration = start_time.elapsed();
+                                        println!("  ✅ {}: {} -> {} (took {:?})", 
+                                            ext_str.to_uppercase(),
+                                            path.file_name().unwrap().to_string_lossy(),
+                                            output_path, 
+                                            duration);
+                                        
+                                        if Path::new(&output_path).exists() {
+                                            let metadata = std::fs::metadata(&output_path)?;
+                                            println!("       📄 Size: {} KB", metadata.len() / 1024);
                                         }
                                     }
+                                    Err(e) => {
+                                        println!("  ❌ {} failed: {}", ext_str.to_uppercase(), e);
+                                    }
                                 }
                             }
                         }
@@ -0,0 +1,50 @@
+//! Bleed, trim and art boxes of individual pages
+//!
+//! The boxes are set on the finished document, after PDF files have been
+//! inserted, so page numbers refer to the pages as they appear in the output.
+//! Each box is given as distances from the edges of the page's media box.
+
+use std::collections::BTreeMap;
+use printpdf::lopdf::{Document, Object};
+use crate::converter::{CropBox, PageBoxes};
+use crate::error::{PdfError, Result};
+use crate::text::PT_PER_MM;
+
+/// Set the boxes of every listed page, keyed by page number starting at 1
+pub(crate) fn apply(document: &mut Document, boxes: &BTreeMap<usize, PageBoxes>) -> Result<()> {
+    let pages = document.get_pages();
+    for (&number, page_boxes) in boxes {
+        let page_id = u32::try_from(number).ok().and_then(|number| pages.get(&number)).ok_or_else(|| PdfError::Config {
+            name: "page_boxes".to_string(),
+            value: format!("page {} of {}", number, pages.len()),
+        })?;
+
+        let page = document.get_object_mut(*page_id)?.as_dict_mut()?;
+        let media_box = page
+            .get(b"MediaBox")?
+            .as_array()?
+            .iter()
+            .map(Object::as_float)
+            .collect::<std::result::Result<Vec<f32>, _>>()?;
+        let [x0, y0, x1, y1] = media_box[..] else {
+            return Err(PdfError::Custom(format!("page {} has an invalid media box", number)));
+        };
+
+        for (key, inset) in [("BleedBox", page_boxes.bleed), ("TrimBox", page_boxes.trim), ("ArtBox", page_boxes.art)] {
+            if let Some(inset) = inset {
+                page.set(key, inset_rect([x0, y0, x1, y1], inset));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rectangle inset from `media_box` (in points), kept inside it
+fn inset_rect(media_box: [f32; 4], inset: CropBox) -> Vec<Object> {
+    let [x0, y0, x1, y1] = media_box;
+    let left = (x0 + inset.left_mm * PT_PER_MM).clamp(x0, x1);
+    let bottom = (y0 + inset.bottom_mm * PT_PER_MM).clamp(y0, y1);
+    let right = (x1 - inset.right_mm * PT_PER_MM).clamp(left, x1);
+    let top = (y1 - inset.top_mm * PT_PER_MM).clamp(bottom, y1);
+    [left, bottom, right, top].into_iter().map(Object::Real).collect()
+}
@@ -1,25 +1,1035 @@
 //! PDF converter implementation
 
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
-use std::io::BufWriter;
+use std::io::{BufWriter, Read, Write};
+use std::time::{Duration, Instant};
 use printpdf::*;
 use ::image::GenericImageView;
+use regex::Regex;
+use crate::boxes;
+use crate::encryption::{self, Encryption};
 use crate::error::{PdfError, Result};
-use crate::{A4_WIDTH_MM, A4_HEIGHT_MM, DEFAULT_MARGIN_MM, DEFAULT_DPI};
+use crate::gif;
+use crate::jpeg;
+use crate::lossless;
+use crate::merge::{self, PdfInsert};
+use crate::outline::{self, OutlineNode};
+use crate::pdfx::{self, PdfX};
+use crate::preview;
+use crate::processing;
+#[cfg(feature = "serde")]
+use crate::project;
+#[cfg(feature = "psd")]
+use crate::psd;
+use crate::sidecar;
+use crate::smask::{self, SoftMask};
+use crate::report::{self, ConversionReport, ImageReport, Progress};
+use crate::text::{self, TextFont};
+use crate::toc::{self, TocLink};
+use crate::xmp;
+use crate::{A4_WIDTH_MM, A4_HEIGHT_MM, DEFAULT_MARGIN_MM, DEFAULT_DPI, DEFAULT_TRIM_TOLERANCE, DEFAULT_DESKEW_MAX_ANGLE, DEFAULT_NOTES_FONT_SIZE, DEFAULT_HEADER_FOOTER_FONT_SIZE, MAX_PAGE_SIDE_MM};
+
+/// Lowercase file extensions of the images picked up from folders and archives
+#[cfg(not(feature = "psd"))]
+const SUPPORTED_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "bmp", "webp"];
+#[cfg(feature = "psd")]
+const SUPPORTED_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "bmp", "webp", "psd"];
+
+/// Smallest width and height in millimeters left for an image when the margins leave no room
+const MIN_AVAILABLE_MM: f32 = 1.0;
+
+/// Bytes read from the start of a JPEG file to find its EXIF orientation without reading the whole file
+const EXIF_HEAD_LEN: u64 = 128 * 1024;
 
 /// Configuration for PDF conversion
+///
+/// With the `serde` feature, configurations can be stored as presets, e.g.
+/// in JSON or TOML. Fields missing from a preset keep their defaults.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct PdfConfig {
     /// Page width in millimeters
     pub page_width_mm: f32,
     /// Page height in millimeters
     pub page_height_mm: f32,
-    /// Page margins in millimeters
+    /// Page margins in millimeters, unless `margin_mode` scales them with the page
     pub margin_mm: f32,
+    /// Whether margins are `margin_mm` or scale with the page size
+    pub margin_mode: MarginMode,
     /// DPI for image conversion
     pub dpi: f32,
     /// PDF document title
+    ///
+    /// Titles and bookmark names outside ASCII, e.g. in Chinese or with emoji,
+    /// are stored as UTF-16 text strings so PDF readers show them as written.
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    /// use printpdf::lopdf::{Document, Object};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// image::RgbImage::new(40, 30).save(dir.path().join("第一页.png"))?;
+    /// let output = dir.path().join("album.pdf");
+    /// let config = PdfConfig { title: "旅行相册 📷".to_string(), bookmarks: true, ..PdfConfig::default() };
+    /// PdfConverter::with_config(config).convert_folder_to_pdf(dir.path(), &output)?;
+    ///
+    /// // Text strings start with the byte order mark FE FF, followed by UTF-16BE
+    /// let text = |object: &Object| {
+    ///     let bytes = object.as_str().unwrap();
+    ///     assert_eq!(bytes[..2], [0xFE, 0xFF]);
+    ///     let units: Vec<u16> = bytes[2..].chunks(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+    ///     String::from_utf16(&units).unwrap()
+    /// };
+    ///
+    /// let document = Document::load(&output)?;
+    /// let info = document.trailer.get(b"Info")?.as_reference()?;
+    /// let title = document.get_dictionary(info)?.get(b"Title")?;
+    /// assert_eq!(text(title), "旅行相册 📷");
+    /// let outlines = document.catalog()?.get(b"Outlines")?.as_reference()?;
+    /// let first = document.get_dictionary(outlines)?.get(b"First")?.as_reference()?;
+    /// let bookmark = document.get_dictionary(first)?.get(b"Title")?;
+    /// assert_eq!(text(bookmark), "第一页");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
     pub title: String,
+    /// Maximum effective resolution of placed images; larger images are downscaled
+    pub max_dpi: Option<f32>,
+    /// Lowest acceptable effective resolution of placed images, e.g. 150 for print
+    ///
+    /// Images whose pixels are spread thinner at their displayed size are
+    /// listed in `ConversionReport::low_resolution` and logged as warnings;
+    /// the conversion still succeeds.
+    pub min_effective_dpi: Option<f32>,
+    /// Downscale every image to at most `dpi` at its displayed size before embedding it
+    ///
+    /// Keeps the pixel buffers held until the document is saved small, so peak
+    /// memory stays bounded for folders of high-resolution photos. Combines
+    /// with `max_dpi`, the lower limit wins.
+    pub low_memory: bool,
+    /// Resolution of images on the printed page, in pixels per inch of their displayed size
+    ///
+    /// The displayed size follows from the page, the margins, `scale_mode`
+    /// and the grid cell, so the same setting gives the same print quality on
+    /// A4 and on Letter. An image is embedded with `print_dpi` pixels for every
+    /// inch it covers: fit into 190 mm (7.48 in) at 200 DPI, it is embedded
+    /// 1496 pixels wide. Larger images are downscaled to that; smaller ones
+    /// are never upscaled, so set `min_effective_dpi` as well to have them
+    /// reported. `dpi` plays no part here: it only converts pixels to
+    /// millimeters where images are sized from their pixels, as with
+    /// `ScaleMode::FixedPercent`.
+    ///
+    /// Replaces the limits of `max_dpi`, `low_memory` and `content_dpi`.
+    /// Ignored in native DPI mode, where images are never resampled.
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// image::RgbImage::from_fn(3000, 2000, |x, y| image::Rgb([x as u8, y as u8, 128])).save(dir.path().join("photo.png"))?;
+    /// for (width_mm, height_mm) in [(210.0, 297.0), (215.9, 279.4)] {
+    ///     let config = PdfConfig { page_width_mm: width_mm, page_height_mm: height_mm, print_dpi: Some(200.0), ..PdfConfig::default() };
+    ///     let report = PdfConverter::with_config(config).convert_folder_to_pdf_with_report(dir.path(), dir.path().join("print.pdf"))?;
+    ///     assert!((report.images[0].effective_dpi - 200.0).abs() < 0.5);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub print_dpi: Option<f32>,
+    /// Embed the small preview stored in the EXIF data of JPEG files instead of the full image
+    ///
+    /// Produces a tiny preview PDF quickly, since the full images are never
+    /// decoded. Thumbnails are typically 160x120 pixels and scaled up to fit
+    /// the page; crops given in pixels refer to the thumbnail. Files without
+    /// a thumbnail are embedded in full.
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// let encode = |width, height| -> image::ImageResult<Vec<u8>> {
+    ///     let mut bytes = Vec::new();
+    ///     image::RgbImage::from_pixel(width, height, image::Rgb([90, 140, 60]))
+    ///         .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Jpeg(80))?;
+    ///     Ok(bytes)
+    /// };
+    /// let (photo, thumbnail) = (encode(1600, 1200)?, encode(160, 120)?);
+    ///
+    /// // EXIF segment whose second directory points at the thumbnail
+    /// let mut tiff = b"II*\0\x08\0\0\0\0\0\x0e\0\0\0\x02\0".to_vec();
+    /// for (tag, value) in [(0x0201u16, 44u32), (0x0202, thumbnail.len() as u32)] {
+    ///     tiff.extend(tag.to_le_bytes());
+    ///     tiff.extend([4, 0, 1, 0, 0, 0]);
+    ///     tiff.extend(value.to_le_bytes());
+    /// }
+    /// tiff.extend([0; 4]);
+    /// tiff.extend(&thumbnail);
+    /// let mut file = vec![0xFF, 0xD8, 0xFF, 0xE1];
+    /// file.extend((tiff.len() as u16 + 8).to_be_bytes());
+    /// file.extend(b"Exif\0\0");
+    /// file.extend(tiff);
+    /// file.extend(&photo[2..]);
+    /// std::fs::write(dir.path().join("photo.jpg"), file)?;
+    ///
+    /// let config = PdfConfig { use_embedded_thumbnail: true, ..PdfConfig::default() };
+    /// let report = PdfConverter::with_config(config).convert_folder_to_pdf_with_report(dir.path(), dir.path().join("preview.pdf"))?;
+    /// assert_eq!(report.images[0].pixels, (160, 120));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub use_embedded_thumbnail: bool,
+    /// Turn JPEG files upright according to the orientation tag in their EXIF data
+    ///
+    /// Cameras and phones store photos as the sensor saw them and record in
+    /// this tag how to turn them for viewing. Orientation comes first in the
+    /// processing order, so `crop` and `image_crops` refer to the upright
+    /// image and `rotations` turn it further; see `PdfConverter::placement`
+    /// for the placement that follows. Images are never rotated automatically
+    /// to match the page. Turned JPEG files are decoded instead of embedded as
+    /// they are.
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use std::io::Read;
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    /// use printpdf::lopdf::{Document, Object};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// // Stored 40x20, red on the left and blue on the right
+    /// let stored = image::RgbImage::from_fn(40, 20, |x, _| image::Rgb(if x < 20 { [255, 0, 0] } else { [0, 0, 255] }));
+    /// let mut jpeg = Vec::new();
+    /// stored.write_to(&mut std::io::Cursor::new(&mut jpeg), image::ImageOutputFormat::Jpeg(95))?;
+    /// // EXIF segment with orientation 6: turn 90 degrees clockwise for viewing
+    /// let mut exif = b"Exif\0\0II*\0\x08\0\0\0\x01\0".to_vec();
+    /// exif.extend([0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0]);
+    /// let mut file = vec![0xFF, 0xD8, 0xFF, 0xE1];
+    /// file.extend((exif.len() as u16 + 2).to_be_bytes());
+    /// file.extend(exif);
+    /// file.extend(&jpeg[2..]);
+    /// let path = dir.path().join("photo.jpg");
+    /// std::fs::write(&path, file)?;
+    ///
+    /// // Upright the photo is 20x40 with red on top; the user rotation turns
+    /// // it on by 90 degrees, to 40x20 with red on the right
+    /// let config = PdfConfig {
+    ///     exif_orientation: true,
+    ///     rotations: HashMap::from([("photo.jpg".to_string(), 90.0)]),
+    ///     ..PdfConfig::default()
+    /// };
+    /// let output = dir.path().join("photo.pdf");
+    /// PdfConverter::with_config(config).convert_images_to_pdf(&[path], &output)?;
+    ///
+    /// let document = Document::load(&output)?;
+    /// let image = document.objects.values().filter_map(|object| object.as_stream().ok())
+    ///     .find(|stream| stream.dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Image".as_slice()))
+    ///     .unwrap();
+    /// assert_eq!((image.dict.get(b"Width")?.as_i64()?, image.dict.get(b"Height")?.as_i64()?), (40, 20));
+    /// let mut pixels = image.content.clone();
+    /// if image.dict.get(b"Filter").is_ok() {
+    ///     pixels.clear();
+    ///     flate2::read::ZlibDecoder::new(image.content.as_slice()).read_to_end(&mut pixels)?;
+    /// }
+    /// let pixel = |x: usize, y: usize| &pixels[(y * 40 + x) * 3..][..3];
+    /// assert!(pixel(35, 10)[0] > 200 && pixel(35, 10)[2] < 60);
+    /// assert!(pixel(5, 10)[2] > 200 && pixel(5, 10)[0] < 60);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// Sizes read from file headers, for orientation checks, layout previews
+    /// and pages sized by DPI, take the orientation into account as well:
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    /// use printpdf::lopdf::Document;
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// # let mut jpeg = Vec::new();
+    /// # image::RgbImage::new(40, 20).write_to(&mut std::io::Cursor::new(&mut jpeg), image::ImageOutputFormat::Jpeg(95))?;
+    /// # let mut exif = b"Exif\0\0II*\0\x08\0\0\0\x01\0".to_vec();
+    /// # exif.extend([0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0]);
+    /// # let mut file = vec![0xFF, 0xD8, 0xFF, 0xE1];
+    /// # file.extend((exif.len() as u16 + 2).to_be_bytes());
+    /// # file.extend(exif);
+    /// # file.extend(&jpeg[2..]);
+    /// // A 40x20 phone photo tagged with orientation 6 next to a 20x40 scan
+    /// let folder = dir.path().join("mixed");
+    /// std::fs::create_dir(&folder)?;
+    /// std::fs::write(folder.join("photo.jpg"), file)?;
+    /// image::RgbImage::new(20, 40).save(folder.join("scan.png"))?;
+    ///
+    /// let converter = PdfConverter::with_config(PdfConfig {
+    ///     exif_orientation: true,
+    ///     require_consistent_orientation: true,
+    ///     margin_mm: 0.0,
+    ///     ..PdfConfig::default()
+    /// });
+    /// assert_eq!(converter.count_pages(&folder)?, 2);
+    ///
+    /// // One pixel per millimeter: the page of the upright photo is 20x40 mm
+    /// let output = dir.path().join("photo.pdf");
+    /// let dpi_map = HashMap::from([(folder.join("photo.jpg"), 25.4)]);
+    /// converter.convert_images_to_pdf_with_dpi(&[folder.join("photo.jpg")], &output, &dpi_map)?;
+    /// let document = Document::load(&output)?;
+    /// let page = document.get_object(*document.get_pages().values().next().unwrap())?.as_dict()?;
+    /// let media_box: Vec<f32> = page.get(b"MediaBox")?.as_array()?.iter().map(|value| value.as_float().unwrap()).collect();
+    /// assert!(media_box[2] < media_box[3]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub exif_orientation: bool,
+    /// Choose the resolution limit of every image from its content; see `ContentDpi`
+    pub content_dpi: Option<ContentDpi>,
+    /// Turn every image into a two-color print, e.g. `Tint::SEPIA`, for a stylized look
+    ///
+    /// JPEG files are decoded instead of embedded as they are.
+    pub tint: Option<Tint>,
+    /// Remove a uniform background, e.g. the green or white backdrop of product photos
+    ///
+    /// Pixels matching the key color are replaced with its `replacement`,
+    /// white like the page by default, before the image is placed. Trimming
+    /// then removes the cleared borders. JPEG files are decoded instead of
+    /// embedded as they are.
+    ///
+    /// ```rust
+    /// use pdf_converter::{ChromaKey, PdfConfig, PdfConverter};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// // A red product on a green backdrop
+    /// let photo = image::RgbImage::from_fn(60, 40, |x, y| match (x, y) {
+    ///     (20..=39, 10..=29) => image::Rgb([200, 30, 30]),
+    ///     _ => image::Rgb([30, 180 + (x % 8) as u8, 40]),
+    /// });
+    /// photo.save(dir.path().join("product.png"))?;
+    ///
+    /// let config = PdfConfig { chroma_key: Some(ChromaKey::new([30, 184, 40], 20)), ..PdfConfig::default() };
+    /// PdfConverter::with_config(config).for_each_image(dir.path(), |_, img, _| {
+    ///     let img = img.to_rgb8();
+    ///     assert_eq!(img.get_pixel(0, 0).0, [255, 255, 255]);
+    ///     assert_eq!(img.get_pixel(30, 20).0, [200, 30, 30]);
+    /// })?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub chroma_key: Option<ChromaKey>,
+    /// Attach a companion grayscale mask file to images, e.g. `photo.mask.png` for `photo.jpg`
+    ///
+    /// The mask becomes the image's soft mask: white keeps the image opaque,
+    /// black lets the page background show through, and gray blends the two.
+    /// Masks should have the size of their image; they are cropped and rotated
+    /// with it and stretched to its final size, but not deskewed or trimmed.
+    /// Mask files found in a folder are not converted as pages of their own.
+    /// PDF/X-1a forbids transparency and rejects masks.
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// image::RgbImage::from_pixel(40, 40, image::Rgb([200, 30, 30])).save(dir.path().join("logo.png"))?;
+    /// // A round mask cutting a circle out of the square image
+    /// let mask = image::GrayImage::from_fn(40, 40, |x, y| {
+    ///     let inside = (x as i32 - 20).pow(2) + (y as i32 - 20).pow(2) < 400;
+    ///     image::Luma([if inside { 255 } else { 0 }])
+    /// });
+    /// mask.save(dir.path().join("logo.mask.png"))?;
+    ///
+    /// let output = dir.path().join("logo.pdf");
+    /// let config = PdfConfig { mask_files: true, ..PdfConfig::default() };
+    /// PdfConverter::with_config(config).convert_folder_to_pdf(dir.path(), &output)?;
+    ///
+    /// let document = printpdf::lopdf::Document::load(&output)?;
+    /// assert_eq!(document.get_pages().len(), 1);
+    /// let masked = document.objects.values().filter_map(|object| object.as_stream().ok()).filter(|stream| stream.dict.has(b"SMask")).count();
+    /// assert_eq!(masked, 1);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub mask_files: bool,
+    /// Mask files of individual images, keyed by file name, taking precedence over `mask_files`
+    pub masks: HashMap<String, PathBuf>,
+    /// Resampling filter used when downscaling images
+    pub resize_filter: ResizeFilter,
+    /// How the pixels of decoded images are compressed; see `ImageCompression`
+    pub image_compression: ImageCompression,
+    /// Flate-compress every stream of the document at the highest level, including page contents
+    ///
+    /// By default printpdf compresses images and fonts in release builds
+    /// only, and never the content streams of pages, so text-heavy pages such
+    /// as the table of contents, notes and captions stay uncompressed. This
+    /// compresses all of them in every build, leaving JPEG data and XMP
+    /// metadata as they are. Object streams, which would also compress the
+    /// document structure, are not written.
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// image::RgbImage::from_pixel(32, 32, image::Rgb([20, 120, 200])).save(dir.path().join("photo.png"))?;
+    /// let notes = vec!["Captured on the first morning of the trip. ".repeat(200)];
+    ///
+    /// let mut sizes = Vec::new();
+    /// for compress_streams in [false, true] {
+    ///     let output = dir.path().join(format!("notes-{compress_streams}.pdf"));
+    ///     let config = PdfConfig { notes: notes.clone(), compress_streams, ..PdfConfig::default() };
+    ///     PdfConverter::with_config(config).convert_folder_to_pdf(dir.path(), &output)?;
+    ///     sizes.push(std::fs::metadata(&output)?.len());
+    /// }
+    /// assert!(sizes[1] < sizes[0]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub compress_streams: bool,
+    /// Print every image pixel as exactly one dot of a device with this resolution
+    ///
+    /// Each page is sized to its image at this DPI plus the margins instead of
+    /// fitting the image into `page_width_mm` x `page_height_mm`, and images
+    /// are never resampled, so `max_dpi` and `low_memory` have no effect. Set
+    /// `margin_mm` to 0 for label printers.
+    pub native_dpi: Option<f32>,
+    /// Give every page the size of the largest one, centering each image on its padded page
+    ///
+    /// Meant for page sizes that follow the images (`native_dpi`,
+    /// `ScaleMode::FitWidth` or per-image page sizes), which some printers
+    /// handle badly. The width and the height are the largest of any page,
+    /// so pages can grow in both directions. All images are prepared before
+    /// the first page is written, so they are held in memory together, as in
+    /// strip mode, which ignores this option.
+    pub uniform_page_size: bool,
+    /// How images are sized on their pages
+    pub scale_mode: ScaleMode,
+    /// Clip images that extend past the margins to the area inside them
+    ///
+    /// Only `ScaleMode::FixedPercent` can place images beyond the margins;
+    /// without clipping they are cut off at the page edge instead.
+    pub clip_to_margins: bool,
+    /// Stack all images top to bottom on a single page instead of one page per image
+    ///
+    /// The page keeps the configured width and grows to the height of the
+    /// stacked images, which are fit to the width between the margins.
+    pub strip_mode: Option<StripMode>,
+    /// Place several images on each page in a grid of equal cells, filled row by row
+    ///
+    /// Each image is fit into its cell. Ignored in strip mode, native DPI mode
+    /// and with `ScaleMode::FitWidth`.
+    pub grid: Option<Grid>,
+    /// Space between neighbouring grid cells in millimeters; 0 packs them edge to edge
+    pub grid_gutter_mm: f32,
+    /// Number of images printed on each sheet for saving paper: 1, 2, 4, 6 or 9
+    ///
+    /// Images fill the sheet in reading order, left to right and top to
+    /// bottom, each fit into its cell like with `grid`, which this replaces
+    /// when above 1. For 2-up and 6-up the configured page is turned by 90
+    /// degrees, so the cells keep its orientation: two portrait A5 cells on a
+    /// landscape A4 sheet, for example. Every sheet holds consecutive images
+    /// and no blank pages are added, so each side of a duplex print reads on
+    /// from the one before; print turned sheets with short-edge binding to
+    /// keep the backs upright. Other values fail the conversion with
+    /// `PdfError::Config`. Ignored where `grid` is.
+    ///
+    /// ```
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// # let folder = dir.path().join("slides");
+    /// # std::fs::create_dir(&folder)?;
+    /// # let files: Vec<_> = (0..5).map(|index| {
+    /// #     let path = folder.join(format!("page{index}.png"));
+    /// #     image::RgbImage::from_pixel(210, 297, image::Rgb([255, 255, 255])).save(&path).unwrap();
+    /// #     path
+    /// # }).collect();
+    /// let config = PdfConfig { n_up: 4, n_up_cut_lines: true, ..PdfConfig::default() };
+    /// let report = PdfConverter::with_config(config).convert_folder_to_pdf_with_report(&folder, dir.path().join("handout.pdf"))?;
+    /// // Five images take two sheets, the second holding the fifth alone
+    /// assert_eq!(report.page_count, 2);
+    ///
+    /// let config = PdfConfig { n_up: 2, ..PdfConfig::default() };
+    /// // The A4 portrait page becomes a landscape sheet
+    /// assert_eq!(config.page_size().width_mm, 297.0);
+    ///
+    /// let config = PdfConfig { n_up: 3, ..PdfConfig::default() };
+    /// assert!(PdfConverter::with_config(config).convert_images_to_pdf(&files, dir.path().join("three.pdf")).is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub n_up: usize,
+    /// Draw dashed cut lines between the cells of `n_up` sheets, running from edge to edge
+    pub n_up_cut_lines: bool,
+    /// Exact number of pages of the document, e.g. for a booklet of fixed length
+    ///
+    /// Pages of the table of contents, notes, `cover_pdf` and inserted PDF
+    /// files are counted first, and the images share the pages left over.
+    /// With more images than that, consecutive images share pages in a grid
+    /// of at least as many cells as needed, about as many rows as columns and
+    /// with more rows on portrait pages. Documents still shorter than the
+    /// target, including those with fewer images, are padded with blank pages
+    /// at the end.
+    ///
+    /// Grouping needs the number of images in advance, so it applies to
+    /// folders, lists of files, ZIP archives and decoded images, but not to
+    /// animations expanded into frames, iterators and streams, which are only
+    /// padded. A configured `grid` or `n_up` is kept as it is, and so are the
+    /// modes that use no grid. Conversions that cannot fit into the target,
+    /// e.g. because the other pages alone fill it, fail with
+    /// `PdfError::Config`.
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    /// use printpdf::lopdf::Document;
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// # let folder = dir.path().join("photos");
+    /// # std::fs::create_dir(&folder)?;
+    /// for index in 0..10 {
+    ///     image::RgbImage::new(40, 30).save(folder.join(format!("{index:02}.png")))?;
+    /// }
+    /// let config = PdfConfig { target_pages: Some(4), ..PdfConfig::default() };
+    /// let output = dir.path().join("booklet.pdf");
+    /// let report = PdfConverter::with_config(config).convert_folder_to_pdf_with_report(&folder, &output)?;
+    /// // Three pages of 2x2 images, then one blank page
+    /// assert_eq!(report.page_count, 4);
+    /// assert_eq!(Document::load(&output)?.get_pages().len(), 4);
+    ///
+    /// let config = PdfConfig { target_pages: Some(16), ..PdfConfig::default() };
+    /// let report = PdfConverter::with_config(config).convert_folder_to_pdf_with_report(&folder, &output)?;
+    /// assert_eq!(report.page_count, 16);
+    ///
+    /// // The contents page leaves three pages, now filled with 2x2 images
+    /// let config = PdfConfig { target_pages: Some(4), generate_toc: true, ..PdfConfig::default() };
+    /// let report = PdfConverter::with_config(config).convert_folder_to_pdf_with_report(&folder, &output)?;
+    /// assert_eq!(report.page_count, 4);
+    ///
+    /// let config = PdfConfig { target_pages: Some(1), generate_toc: true, ..PdfConfig::default() };
+    /// assert!(PdfConverter::with_config(config).convert_folder_to_pdf_with_report(&folder, &output).is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub target_pages: Option<usize>,
+    /// Crop near-uniform (e.g. white or black scanner) borders before placement
+    pub auto_trim: bool,
+    /// Maximum luma spread (0-255) of a row or column still treated as border
+    pub trim_tolerance: u8,
+    /// Clockwise rotation in degrees of individual images, keyed by file name
+    ///
+    /// Applied right after decoding and cropping, before deskewing, trimming
+    /// and placement; files not listed are not rotated. With
+    /// `exif_orientation` the rotation turns the upright image further.
+    /// Usually filled from a sidecar file with `load_rotation_sidecar`.
+    pub rotations: HashMap<String, f32>,
+    /// Rectangle every image is cropped to right after decoding, e.g. to cut off scanner edges
+    ///
+    /// Cropping comes before rotating, so the rectangle refers to the image as
+    /// stored, or as turned upright with `exif_orientation`. Rectangles reaching past the image are clamped to it with a
+    /// warning. Cropped JPEG files are decoded instead of embedded as they are.
+    pub crop: Option<ImageCrop>,
+    /// Crop rectangles of individual images, keyed by file name, replacing `crop` for them
+    pub image_crops: HashMap<String, ImageCrop>,
+    /// Straighten slightly rotated scans before placement
+    pub deskew: bool,
+    /// Largest tilt in degrees that deskewing corrects; larger angles are left alone
+    pub deskew_max_angle: f32,
+    /// Order of the pages created from a folder or archive
+    pub page_order: PageOrder,
+    /// Insert the pages of PDF files found in a folder as they are, between the image pages
+    ///
+    /// Only `convert_folder_to_pdf` reads PDF files. Their pages keep their
+    /// vector content instead of being rasterized, and take the place of the
+    /// file in page order; in grid and strip mode they follow the page
+    /// holding the image before them. Inserted pages get no running text,
+    /// QR stamp or crop box and are not counted in `{page}` and `{total}`.
+    /// PDF files sort by pixel area as if they had none.
+    pub include_pdf_files: bool,
+    /// PDF file whose pages start every generated document, e.g. a branded cover
+    ///
+    /// The pages are copied like those of `include_pdf_files`, before the
+    /// contents page, and get no running text, QR stamp or crop box. Since
+    /// the configuration is shared, `convert_folders_to_pdfs` starts every
+    /// output with the same cover.
+    pub cover_pdf: Option<PathBuf>,
+    /// Leave out files of a folder whose names start with a dot
+    ///
+    /// Such files are hidden on macOS and Linux and are usually artifacts
+    /// like thumbnails or the `._` resource forks macOS writes to foreign
+    /// file systems, even when they carry an image extension. Applies to
+    /// images and PDF files found in folders; archive entries are not
+    /// filtered.
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// image::RgbImage::new(40, 30).save(dir.path().join("page.png"))?;
+    /// image::RgbImage::new(40, 30).save(dir.path().join(".hidden.png"))?;
+    ///
+    /// assert_eq!(PdfConverter::new().count_pages(dir.path())?, 1);
+    /// let converter = PdfConverter::with_config(PdfConfig { skip_hidden: false, ..PdfConfig::default() });
+    /// assert_eq!(converter.count_pages(dir.path())?, 2);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub skip_hidden: bool,
+    /// Fail before converting a folder or archive whose images mix portrait and landscape
+    ///
+    /// Every image's size is read from its header, after the configured
+    /// rotation, and the conversion stops with `PdfError::MixedOrientation`
+    /// listing both groups; the smaller one usually holds the mis-rotated
+    /// scans. Square images and PDF files match either orientation.
+    pub require_consistent_orientation: bool,
+    /// Turn the frames of animated GIFs into pages; only the first frame is used when `None`
+    ///
+    /// The number of pages is only known after decoding, so conversions that
+    /// expand animations get no table of contents.
+    pub animation_frames: Option<FrameSampling>,
+    /// Add an outline (bookmark) entry for every image page
+    ///
+    /// Entries are titled with the file stem unless the converter has a
+    /// name transform, see `PdfConverter::with_name_transform`.
+    pub bookmarks: bool,
+    /// Nest the bookmarks of images in collapsed entries for their folders
+    ///
+    /// `convert_folder_tree_to_pdf` gets an outline mirroring the directory
+    /// tree below the folder it was given, with an entry for every subfolder.
+    /// Other conversions take folders relative to the deepest folder
+    /// containing all images. Each folder entry opens the page of its first
+    /// image.
+    /// Only takes effect together with `bookmarks`.
+    pub nested_bookmarks: bool,
+    /// Start the document with a table of contents listing every image page
+    ///
+    /// Entries use the page titles (see `bookmarks`) set in `notes_font_size`
+    /// and link to their pages.
+    pub generate_toc: bool,
+    /// Free-form text appended after the images, each entry starting on a new page
+    pub notes: Vec<String>,
+    /// Font size of notes pages in points
+    pub notes_font_size: f32,
+    /// Running header shown in the top margin of every page
+    ///
+    /// `{page}` and `{total}` are replaced with the page number and the
+    /// number of numbered pages, see `page_number_start`.
+    pub header_text: Option<String>,
+    /// Running footer shown in the bottom margin of every page, with the same tokens as the header
+    pub footer_text: Option<String>,
+    /// Horizontal alignment of the header within the margins
+    pub header_align: TextAlign,
+    /// Horizontal alignment of the footer within the margins
+    pub footer_align: TextAlign,
+    /// Font size of header and footer in points
+    pub header_footer_font_size: f32,
+    /// Page (1-based) numbered 1 in the header and footer
+    ///
+    /// Pages before it, e.g. a cover and title page, are not counted and
+    /// leave out header and footer lines containing `{page}` or `{total}`.
+    ///
+    /// ```rust
+    /// use pdf_converter::{PageNumberFormat, PdfConfig};
+    ///
+    /// // Cover and title page unnumbered, then "Page i of x", "Page ii of x", ...
+    /// let config = PdfConfig {
+    ///     footer_text: Some("Page {page} of {total}".to_string()),
+    ///     page_number_start: 3,
+    ///     page_number_format: PageNumberFormat::LowerRoman,
+    ///     ..PdfConfig::default()
+    /// };
+    /// assert_eq!(config.page_number_format.format(4), "iv");
+    /// ```
+    pub page_number_start: usize,
+    /// How `{page}` and `{total}` are written in the header and footer
+    pub page_number_format: PageNumberFormat,
+    /// TrueType/OpenType font for all rendered text; built-in Helvetica when `None`
+    ///
+    /// Needed for scripts outside Windows-1252, such as CJK text.
+    pub font: Option<PathBuf>,
+    /// Radius of rounded image corners in millimeters; 0 keeps square corners
+    ///
+    /// Limited to half the shorter side of the displayed image.
+    pub corner_radius_mm: f32,
+    /// Blend mode used when compositing images onto the page
+    #[cfg_attr(feature = "serde", serde(with = "crate::preset::blend_mode"))]
+    pub blend_mode: Option<BlendMode>,
+    /// Rendering intent for color conversion of embedded images
+    #[cfg_attr(feature = "serde", serde(with = "crate::preset::rendering_intent"))]
+    pub rendering_intent: Option<RenderingIntent>,
+    /// QR code stamped onto every page, e.g. for document tracking
+    #[cfg(feature = "qr")]
+    pub qr_stamp: Option<crate::qr::QrStamp>,
+    /// Visible area of every page, inset from the page edges; the full page when `None`
+    ///
+    /// The page itself becomes the media box, including bleed, while viewers
+    /// and printers that honor the crop box show only the area inside it.
+    pub crop_box: Option<CropBox>,
+    /// Draw the margin box of every page as a thin dashed gray line, for proofing layouts
+    ///
+    /// The guides are part of the page content and print like everything
+    /// else, so leave this off for final output.
+    pub show_margin_guides: bool,
+    /// Image drawn over the content of the first page only, e.g. a logo or letterhead
+    ///
+    /// The first page is the contents page when `generate_toc` is set.
+    pub first_page_overlay: Option<PageOverlay>,
+    /// Bleed, trim and art boxes of individual pages for prepress, keyed by page number starting at 1
+    ///
+    /// Page numbers count every page of the finished document, including the
+    /// table of contents, notes and inserted PDF pages. Boxes set here replace
+    /// the trim box written for `crop_box`. A page number beyond the last page
+    /// fails the conversion with `PdfError::Config`.
+    pub page_boxes: BTreeMap<usize, PageBoxes>,
+    /// Record the source file path of every image page in the page's `/PieceInfo`
+    ///
+    /// The paths are stored under `/PieceInfo /PdfConverter /Private /SourceFiles`
+    /// as an array of text strings, one per image on the page. Viewers ignore
+    /// page-piece dictionaries, so the pages look the same.
+    pub source_metadata: bool,
+    /// Record the capture location from the EXIF GPS data of JPEG files in the page's `/PieceInfo`
+    ///
+    /// Locations are stored under `/PieceInfo /PdfConverter /Private /Locations`
+    /// as an array with one `[latitude longitude]` pair in degrees per located
+    /// image on the page; southern latitudes and western longitudes are
+    /// negative. Pages whose images have no GPS data are not tagged.
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// let mut photo = Vec::new();
+    /// image::RgbImage::from_pixel(64, 48, image::Rgb([90, 140, 60]))
+    ///     .write_to(&mut std::io::Cursor::new(&mut photo), image::ImageOutputFormat::Jpeg(80))?;
+    ///
+    /// // EXIF segment with a GPS directory for 48°51'30"N 2°17'40"E
+    /// let mut tiff = b"II*\0\x08\0\0\0\x01\0\x25\x88\x04\0\x01\0\0\0\x1a\0\0\0\0\0\0\0\x04\0".to_vec();
+    /// for (tag, kind, value) in [(1u16, 2u16, u32::from(b'N')), (2, 5, 80), (3, 2, u32::from(b'E')), (4, 5, 104)] {
+    ///     tiff.extend(tag.to_le_bytes());
+    ///     tiff.extend(kind.to_le_bytes());
+    ///     tiff.extend(if kind == 2 { 2u32 } else { 3 }.to_le_bytes());
+    ///     tiff.extend(value.to_le_bytes());
+    /// }
+    /// tiff.extend([0; 4]);
+    /// for value in [48u32, 1, 51, 1, 30, 1, 2, 1, 17, 1, 40, 1] {
+    ///     tiff.extend(value.to_le_bytes());
+    /// }
+    /// let mut file = vec![0xFF, 0xD8, 0xFF, 0xE1];
+    /// file.extend((tiff.len() as u16 + 8).to_be_bytes());
+    /// file.extend(b"Exif\0\0");
+    /// file.extend(tiff);
+    /// file.extend(&photo[2..]);
+    /// std::fs::write(dir.path().join("survey.jpg"), file)?;
+    ///
+    /// let output = dir.path().join("survey.pdf");
+    /// let config = PdfConfig { gps_metadata: true, ..PdfConfig::default() };
+    /// PdfConverter::with_config(config).convert_folder_to_pdf(dir.path(), &output)?;
+    ///
+    /// let document = printpdf::lopdf::Document::load(&output)?;
+    /// let page = document.get_dictionary(document.page_iter().next().unwrap())?;
+    /// let private = page.get(b"PieceInfo")?.as_dict()?.get(b"PdfConverter")?.as_dict()?.get(b"Private")?.as_dict()?;
+    /// let location = private.get(b"Locations")?.as_array()?[0].as_array()?;
+    /// assert!((location[0].as_float()? - 48.8583).abs() < 1e-3);
+    /// assert!((location[1].as_float()? - 2.2944).abs() < 1e-3);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub gps_metadata: bool,
+    /// Produce a PDF/X document for commercial printing; see the `pdfx` module
+    ///
+    /// Images are converted to CMYK. Conversion fails with
+    /// `PdfError::Conformance` for settings the standard forbids, such as
+    /// encryption or text without an embedded `font`.
+    pub pdf_x: Option<PdfX>,
+    /// Password-protect the output; see the `encryption` module for the algorithm used
+    pub encryption: Option<Encryption>,
+    /// XMP packet embedded as the document's metadata, e.g. Dublin Core fields for archives
+    ///
+    /// Stored as given, so it should be a complete packet from `<?xpacket
+    /// begin` to `<?xpacket end`. It replaces the packet printpdf writes,
+    /// which carries the PDF/X identification when `pdf_x` is set; include
+    /// that in packets for PDF/X output.
+    pub xmp_metadata: Option<String>,
+    /// Natural language of the document as a BCP 47 tag, e.g. `"en-US"`
+    ///
+    /// Written as the catalog's `/Lang` entry, which screen readers use to
+    /// pick a voice and PDF/UA requires.
+    pub language: Option<String>,
+    /// Retry writing the output file after IO errors, e.g. on flaky network drives
+    pub save_retry: Option<RetryPolicy>,
+    /// Read image files again after a wait when they appear truncated, e.g. while a download is still writing them
+    ///
+    /// Empty files count as incomplete, and so do files that fail to decode
+    /// and lack the end marker of their format (JPEG, PNG and GIF). JPEG files
+    /// embedded without decoding must contain the end marker after their
+    /// compressed data; data appended after it, such as maker trailers or the
+    /// video of a motion photo, is left out. Files still incomplete after the
+    /// last attempt fail with `PdfError::TruncatedImage`, which truncated files
+    /// report even without retries.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use pdf_converter::{PdfConfig, PdfConverter, PdfError, RetryPolicy};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// let mut png = Vec::new();
+    /// image::RgbImage::from_fn(64, 64, |x, y| image::Rgb([x as u8 * 4, y as u8 * 4, 128]))
+    ///     .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)?;
+    /// let path = dir.path().join("download.png");
+    /// std::fs::write(&path, &png[..png.len() / 2])?;
+    /// let files = [path.clone()];
+    ///
+    /// let error = PdfConverter::new().convert_images_to_pdf(&files, dir.path().join("a.pdf")).unwrap_err();
+    /// assert!(matches!(error, PdfError::ImageAt { source, .. } if matches!(*source, PdfError::TruncatedImage(_))));
+    ///
+    /// // The download finishes while the converter waits
+    /// let writer = std::thread::spawn({
+    ///     let path = path.clone();
+    ///     move || {
+    ///         std::thread::sleep(Duration::from_millis(100));
+    ///         std::fs::write(path, png)
+    ///     }
+    /// });
+    /// let retry = RetryPolicy { attempts: 10, backoff: Duration::from_millis(50) };
+    /// let config = PdfConfig { read_retry: Some(retry), ..PdfConfig::default() };
+    /// let converter = PdfConverter::with_config(config);
+    /// converter.convert_images_to_pdf(&files, dir.path().join("b.pdf"))?;
+    /// writer.join().unwrap()?;
+    ///
+    /// // A motion photo: a complete JPEG followed by its video
+    /// let mut jpeg = Vec::new();
+    /// image::RgbImage::new(64, 48).write_to(&mut std::io::Cursor::new(&mut jpeg), image::ImageOutputFormat::Jpeg(90))?;
+    /// let motion = dir.path().join("motion.jpg");
+    /// std::fs::write(&motion, [jpeg.as_slice(), b"\0\0\0\x18ftypmp42 video data"].concat())?;
+    /// let output = dir.path().join("c.pdf");
+    /// converter.convert_images_to_pdf(&[motion], &output)?;
+    ///
+    /// let document = printpdf::lopdf::Document::load(&output)?;
+    /// let image = document.objects.values().filter_map(|object| object.as_stream().ok())
+    ///     .find(|stream| stream.dict.has(b"Width"))
+    ///     .unwrap();
+    /// assert_eq!(image.content, jpeg);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub read_retry: Option<RetryPolicy>,
+    /// Memory allowed for decoding images, see `ResourceLimits`
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter, PdfError, ResourceLimits};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// // 1000x1000 RGB decodes to 3 MB
+    /// let path = dir.path().join("scan.png");
+    /// image::RgbImage::new(1000, 1000).save(&path)?;
+    /// let files = [path];
+    ///
+    /// let limits = ResourceLimits { max_decoded_bytes: Some(1_000_000) };
+    /// let config = PdfConfig { resource_limits: limits, ..PdfConfig::default() };
+    /// let error = PdfConverter::with_config(config).convert_images_to_pdf(&files, dir.path().join("a.pdf")).unwrap_err();
+    /// assert!(matches!(error, PdfError::ImageAt { source, .. } if matches!(*source, PdfError::ImageTooLarge { limit: 1_000_000, .. })));
+    ///
+    /// PdfConverter::new().convert_images_to_pdf(&files, dir.path().join("b.pdf"))?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub resource_limits: ResourceLimits,
+    /// Reject empty files and files whose first bytes match no image format before decoding them
+    ///
+    /// Meant for services converting uploaded files: the file name is not
+    /// trusted, and content such as an executable renamed to `.png` fails with
+    /// `PdfError::NotAnImage` naming what was found instead of a generic
+    /// decoding error. Any format the decoder recognizes passes, even where
+    /// the file name says otherwise. With `read_retry`, empty files are first
+    /// waited for as incomplete ones.
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter, PdfError};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// let path = dir.path().join("invoice.png");
+    /// std::fs::write(&path, b"MZ\x90\x00\x03\x00\x00\x00")?;
+    /// let files = [path.clone()];
+    ///
+    /// let config = PdfConfig { validate_image_magic: true, ..PdfConfig::default() };
+    /// let error = PdfConverter::with_config(config).convert_images_to_pdf(&files, dir.path().join("a.pdf")).unwrap_err();
+    /// assert!(matches!(error, PdfError::ImageAt { source, .. }
+    ///     if matches!(&*source, PdfError::NotAnImage { reason, .. } if reason == "Windows executable")));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub validate_image_magic: bool,
+    /// Sync the output file to disk before the conversion returns
+    ///
+    /// Without this the written bytes may still sit in the operating system's
+    /// cache, so a crash right after returning can lose them. Tools that start
+    /// processing as soon as the file appears should set it.
+    pub sync_output: bool,
+    /// Write the output to a temporary file in the same folder and rename it into place when complete
+    ///
+    /// Readers and folder watchers then never see a half-written PDF, and of
+    /// two conversions writing the same output at once the last to finish
+    /// wins with a complete file instead of both corrupting it. A failed
+    /// conversion leaves an existing output untouched. The temporary file is
+    /// hidden, named after the output with a `.tmp` suffix, and removed when
+    /// writing fails.
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// # let images = dir.path().join("images");
+    /// # std::fs::create_dir(&images)?;
+    /// image::RgbImage::new(40, 30).save(images.join("page.png"))?;
+    /// let output = dir.path().join("shared.pdf");
+    ///
+    /// let config = PdfConfig { atomic_output: true, ..PdfConfig::default() };
+    /// std::thread::scope(|scope| {
+    ///     for _ in 0..4 {
+    ///         scope.spawn(|| PdfConverter::with_config(config.clone()).convert_folder_to_pdf(&images, &output).unwrap());
+    ///     }
+    /// });
+    /// assert_eq!(printpdf::lopdf::Document::load(&output)?.get_pages().len(), 1);
+    /// // No temporary files are left behind
+    /// assert_eq!(std::fs::read_dir(dir.path())?.count(), 2);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub atomic_output: bool,
+    /// Save every embedded image as a file, to check what processing did to it
+    ///
+    /// The images go into a folder named after the output with `-images`
+    /// appended, e.g. `book-images/` for `book.pdf`, as `page-0001.png` by
+    /// page number, with the position on the page appended (`page-0001-2.png`)
+    /// in grid and strip mode. They show the pixels as embedded, after
+    /// cropping, rotation, trimming and downscaling; CMYK images are converted
+    /// to RGB for viewing, and JPEG files embedded as they are keep their
+    /// data and a `.jpg` extension. Failures to write are logged and do not
+    /// stop the conversion. Not available for checkpointed or project
+    /// conversions, whose parts are merged afterwards.
+    pub debug_images: bool,
+    /// Convert folders in chunks saved to a checkpoint directory, so an interrupted job can resume
+    ///
+    /// Only `convert_folder_to_pdf` and its report variant use it; strip mode
+    /// ignores it. See `Checkpoint` for what a resumed conversion reuses and
+    /// which settings are applied per chunk.
+    pub checkpoint: Option<Checkpoint>,
+    /// Open the finished PDF in the system's default viewer
+    ///
+    /// A viewer that fails to launch is logged as a warning; the conversion
+    /// still succeeds.
+    #[cfg(feature = "open")]
+    pub open_after: bool,
+}
+
+impl PdfConfig {
+    /// Default configuration with settings overridden by environment variables
+    ///
+    /// Reads `PDF_DPI`, `PDF_MARGIN_MM`, `PDF_PAGE_WIDTH_MM`,
+    /// `PDF_PAGE_HEIGHT_MM` and `PDF_TITLE`; unset variables keep their
+    /// defaults. Returns `PdfError::Config` for numbers that do not parse and
+    /// for values that are not valid Unicode.
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+        if let Some(dpi) = env_number("PDF_DPI")? {
+            config.dpi = dpi;
+        }
+        if let Some(margin_mm) = env_number("PDF_MARGIN_MM")? {
+            config.margin_mm = margin_mm;
+        }
+        if let Some(width_mm) = env_number("PDF_PAGE_WIDTH_MM")? {
+            config.page_width_mm = width_mm;
+        }
+        if let Some(height_mm) = env_number("PDF_PAGE_HEIGHT_MM")? {
+            config.page_height_mm = height_mm;
+        }
+        if let Some(title) = env_value("PDF_TITLE")? {
+            config.title = title;
+        }
+        Ok(config)
+    }
+
+    /// Configuration for UI screenshots, embedded with lossless compression
+    ///
+    /// Keeps text and icons free of compression artifacts while still
+    /// shrinking the file compared to uncompressed pixels.
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// // Window with a title bar and lines of dark "text" on a light background
+    /// let screenshot = image::RgbImage::from_fn(400, 300, |x, y| match (x, y) {
+    ///     (_, 0..=29) => image::Rgb([40, 90, 200]),
+    ///     (20..=379, _) if y % 20 < 8 && (x / 6) % 5 != 0 => image::Rgb([30, 30, 30]),
+    ///     _ => image::Rgb([245, 245, 245]),
+    /// });
+    /// screenshot.save(dir.path().join("window.png"))?;
+    ///
+    /// let output = dir.path().join("window.pdf");
+    /// PdfConverter::with_config(PdfConfig::screenshot()).convert_folder_to_pdf(dir.path(), &output)?;
+    /// // Far smaller than the 360,000 bytes of raw pixels
+    /// assert!(std::fs::metadata(&output)?.len() < 20_000);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn screenshot() -> Self {
+        Self {
+            image_compression: ImageCompression::Lossless,
+            ..Self::default()
+        }
+    }
+
+    /// Page size configured for every page, turned sideways for 2-up and 6-up `n_up` sheets
+    pub fn page_size(&self) -> PageSize {
+        match self.n_up_grid() {
+            Some(grid) if grid.columns != grid.rows => PageSize::new(self.page_height_mm, self.page_width_mm),
+            _ => PageSize::new(self.page_width_mm, self.page_height_mm),
+        }
+    }
+
+    /// Cells of an `n_up` sheet; `None` for one image per sheet, unsupported counts and modes without grids
+    fn n_up_grid(&self) -> Option<Grid> {
+        if self.strip_mode.is_some() || self.native_dpi.is_some() || self.scale_mode == ScaleMode::FitWidth {
+            return None;
+        }
+        let (long, short) = match self.n_up {
+            2 => (2, 1),
+            4 => (2, 2),
+            6 => (3, 2),
+            9 => (3, 3),
+            _ => return None,
+        };
+        // 转向后的纸张长边排列较多的格子，格子方向与原页面一致
+        Some(match self.page_width_mm <= self.page_height_mm {
+            true => Grid::new(long, short),
+            false => Grid::new(short, long),
+        })
+    }
+
+    /// Margin in millimeters used on every page, following `margin_mode`
+    pub fn effective_margin_mm(&self) -> f32 {
+        match self.margin_mode {
+            MarginMode::Fixed => self.margin_mm,
+            MarginMode::PercentOfPage(percent) => self.page_width_mm.min(self.page_height_mm) * percent / 100.0,
+        }
+    }
+
+    /// Add the rotations listed in a JSON or CSV sidecar file to `rotations`
+    ///
+    /// The file is either a JSON object such as `{"scan.jpg": 90}` or CSV
+    /// lines of `scan.jpg,90`, with an optional header line and `#` comments.
+    /// Names are file names without directories. Entries replace existing
+    /// rotations of the same file.
+    ///
+    /// Rotations come first in the processing pipeline, so trimming and
+    /// placement see the upright image:
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// # let folder = dir.path().join("scans");
+    /// # std::fs::create_dir(&folder)?;
+    /// // 300x100 px scan with a white 50 px border on its right side
+    /// let scan = image::RgbImage::from_fn(300, 100, |x, y| match x {
+    ///     0..=249 => image::Rgb([((x * 7 + y * 13) % 200) as u8; 3]),
+    ///     _ => image::Rgb([255, 255, 255]),
+    /// });
+    /// scan.save(folder.join("scan.png")).unwrap();
+    /// std::fs::write(dir.path().join("rotations.csv"), "scan.png,90\n")?;
+    ///
+    /// let mut config = PdfConfig { auto_trim: true, ..PdfConfig::default() };
+    /// config.load_rotation_sidecar(dir.path().join("rotations.csv"))?;
+    /// PdfConverter::with_config(config).for_each_image(&folder, |_, img, placement| {
+    ///     // After rotating, the border is at the bottom and trimmed; the upright image fills the page height
+    ///     assert_eq!((img.width(), img.height()), (100, 250));
+    ///     assert!(placement.height_mm > placement.width_mm);
+    /// })?;
+    /// # Ok::<(), pdf_converter::PdfError>(())
+    /// ```
+    pub fn load_rotation_sidecar<P: AsRef<Path>>(&mut self, sidecar_path: P) -> Result<()> {
+        self.rotations.extend(sidecar::load_rotations(sidecar_path.as_ref())?);
+        Ok(())
+    }
 }
 
 impl Default for PdfConfig {
@@ -28,15 +1038,819 @@ impl Default for PdfConfig {
             page_width_mm: A4_WIDTH_MM,
             page_height_mm: A4_HEIGHT_MM,
             margin_mm: DEFAULT_MARGIN_MM,
+            margin_mode: MarginMode::Fixed,
             dpi: DEFAULT_DPI,
             title: "Generated PDF".to_string(),
+            max_dpi: None,
+            min_effective_dpi: None,
+            low_memory: false,
+            print_dpi: None,
+            use_embedded_thumbnail: false,
+            exif_orientation: false,
+            content_dpi: None,
+            tint: None,
+            chroma_key: None,
+            mask_files: false,
+            masks: HashMap::new(),
+            resize_filter: ResizeFilter::default(),
+            image_compression: ImageCompression::default(),
+            compress_streams: false,
+            native_dpi: None,
+            uniform_page_size: false,
+            scale_mode: ScaleMode::default(),
+            clip_to_margins: false,
+            strip_mode: None,
+            grid: None,
+            grid_gutter_mm: 0.0,
+            n_up: 1,
+            n_up_cut_lines: false,
+            target_pages: None,
+            rotations: HashMap::new(),
+            crop: None,
+            image_crops: HashMap::new(),
+            auto_trim: false,
+            trim_tolerance: DEFAULT_TRIM_TOLERANCE,
+            deskew: false,
+            deskew_max_angle: DEFAULT_DESKEW_MAX_ANGLE,
+            page_order: PageOrder::default(),
+            include_pdf_files: false,
+            cover_pdf: None,
+            skip_hidden: true,
+            require_consistent_orientation: false,
+            animation_frames: None,
+            bookmarks: false,
+            nested_bookmarks: false,
+            generate_toc: false,
+            notes: Vec::new(),
+            notes_font_size: DEFAULT_NOTES_FONT_SIZE,
+            header_text: None,
+            footer_text: None,
+            header_align: TextAlign::default(),
+            footer_align: TextAlign::default(),
+            header_footer_font_size: DEFAULT_HEADER_FOOTER_FONT_SIZE,
+            page_number_start: 1,
+            page_number_format: PageNumberFormat::default(),
+            font: None,
+            corner_radius_mm: 0.0,
+            blend_mode: None,
+            rendering_intent: None,
+            #[cfg(feature = "qr")]
+            qr_stamp: None,
+            crop_box: None,
+            show_margin_guides: false,
+            first_page_overlay: None,
+            page_boxes: BTreeMap::new(),
+            source_metadata: false,
+            gps_metadata: false,
+            pdf_x: None,
+            encryption: None,
+            xmp_metadata: None,
+            language: None,
+            save_retry: None,
+            read_retry: None,
+            resource_limits: ResourceLimits::default(),
+            validate_image_magic: false,
+            sync_output: false,
+            atomic_output: false,
+            debug_images: false,
+            checkpoint: None,
+            #[cfg(feature = "open")]
+            open_after: false,
+        }
+    }
+}
+
+/// Size of a single PDF page in millimeters
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PageSize {
+    /// Page width in millimeters
+    pub width_mm: f32,
+    /// Page height in millimeters
+    pub height_mm: f32,
+}
+
+impl PageSize {
+    /// ISO A3 (297 x 420 mm)
+    pub const A3: Self = Self::new(297.0, 420.0);
+    /// ISO A4 (210 x 297 mm)
+    pub const A4: Self = Self::new(A4_WIDTH_MM, A4_HEIGHT_MM);
+    /// ISO A5 (148 x 210 mm)
+    pub const A5: Self = Self::new(148.0, 210.0);
+    /// US Letter (215.9 x 279.4 mm)
+    pub const LETTER: Self = Self::new(215.9, 279.4);
+    /// US Legal (215.9 x 355.6 mm)
+    pub const LEGAL: Self = Self::new(215.9, 355.6);
+
+    /// Create a page size from its dimensions in millimeters
+    pub const fn new(width_mm: f32, height_mm: f32) -> Self {
+        Self { width_mm, height_mm }
+    }
+
+    /// The same page turned by 90 degrees
+    pub const fn landscape(self) -> Self {
+        Self::new(self.height_mm, self.width_mm)
+    }
+}
+
+/// Position and size of an image on its page
+///
+/// Coordinates follow the PDF convention: the origin is the bottom-left
+/// corner of the page and the Y axis points up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    /// Size of the page the image is placed on
+    pub page: PageSize,
+    /// Distance from the left page edge to the image in millimeters
+    pub x_mm: f32,
+    /// Distance from the bottom page edge to the image in millimeters
+    pub y_mm: f32,
+    /// Displayed image width in millimeters
+    pub width_mm: f32,
+    /// Displayed image height in millimeters
+    pub height_mm: f32,
+    /// Horizontal scale applied to the image at the configured DPI
+    pub scale_x: f32,
+    /// Vertical scale applied to the image at the configured DPI
+    pub scale_y: f32,
+}
+
+/// Resampling filter used when downscaling oversized images
+///
+/// Listed from fastest to slowest. `Nearest` is nearly free but produces
+/// jagged edges; `Triangle` (the default) is a good balance for photos and
+/// scans; `CatmullRom` is sharper at roughly twice the cost; `Lanczos3` gives
+/// the best quality and is several times slower than `Triangle` on large images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResizeFilter {
+    /// Nearest-neighbour sampling
+    Nearest,
+    /// Linear (bilinear) filter
+    #[default]
+    Triangle,
+    /// Cubic Catmull-Rom filter
+    CatmullRom,
+    /// Lanczos filter with a window of 3
+    Lanczos3,
+}
+
+impl From<ResizeFilter> for ::image::imageops::FilterType {
+    fn from(filter: ResizeFilter) -> Self {
+        match filter {
+            ResizeFilter::Nearest => Self::Nearest,
+            ResizeFilter::Triangle => Self::Triangle,
+            ResizeFilter::CatmullRom => Self::CatmullRom,
+            ResizeFilter::Lanczos3 => Self::Lanczos3,
+        }
+    }
+}
+
+/// Compression of the images embedded with their decoded pixels
+///
+/// JPEG files that need no processing keep their DCT data either way, since
+/// decoding cannot undo their compression. Prefer `Lossless` for screenshots,
+/// scanned text and line art, where flat colors and sharp edges compress
+/// well without loss and re-encoding as JPEG would blur text. Photos compress
+/// far better as JPEG files embedded as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ImageCompression {
+    /// Uncompressed pixels, which printpdf deflates in release builds only
+    #[default]
+    Standard,
+    /// Deflate with PNG row predictors in every build, as PNG files do
+    ///
+    /// Saving takes longer, about as long as writing the images as PNG files.
+    Lossless,
+}
+
+/// How an image is sized on its page
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScaleMode {
+    /// As large as fits within the margins, keeping the aspect ratio
+    #[default]
+    Fit,
+    /// The given percentage of the image's size at the configured DPI, centered on the page
+    ///
+    /// Images larger than the page overflow it; see `PdfConfig::clip_to_margins`.
+    FixedPercent(f32),
+    /// As wide as the margins allow, on a page as tall as the image plus the top and bottom margins
+    ///
+    /// Every page gets its own height, so tall content such as receipts uses
+    /// the full width instead of shrinking to fit. Grids are not used in this
+    /// mode; in strip mode it is the same as `Fit`.
+    FitWidth,
+}
+
+/// How the page margins are sized, see `PdfConfig::margin_mode`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MarginMode {
+    /// `margin_mm` on every page size
+    #[default]
+    Fixed,
+    /// The given percentage of the configured page's shorter side, ignoring `margin_mm`
+    ///
+    /// All four margins are equal, so 8% gives about 17 mm on A4 and 12 mm
+    /// on A5. Pages sized by their images (`native_dpi`, `ScaleMode::FitWidth`)
+    /// still use the margin of the configured page.
+    PercentOfPage(f32),
+}
+
+/// Columns and rows of images per page, see `PdfConfig::grid`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Grid {
+    /// Number of cells side by side; 0 is treated as 1
+    pub columns: usize,
+    /// Number of cells stacked vertically; 0 is treated as 1
+    pub rows: usize,
+}
+
+impl Grid {
+    /// Grid with the given number of columns and rows
+    pub const fn new(columns: usize, rows: usize) -> Self {
+        Self { columns, rows }
+    }
+
+    /// Number of images on a full page
+    pub fn cells(self) -> usize {
+        self.columns.max(1) * self.rows.max(1)
+    }
+}
+
+/// Layout of a single tall page holding every image, see `PdfConfig::strip_mode`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StripMode {
+    /// Vertical space between consecutive images in millimeters
+    pub gap_mm: f32,
+    /// Remove the rows an image repeats from the bottom of the image above it
+    ///
+    /// Meant for scrolling screenshots, which overlap where one scroll
+    /// position ends and the next begins. Use a `gap_mm` of 0 for a seamless
+    /// result.
+    pub overlap: Option<StripOverlap>,
+}
+
+/// Detection of the band repeated between consecutive images in strip mode
+///
+/// Only images with the same embedded width and color format are compared,
+/// after all other processing, and JPEG files are decoded instead of embedded
+/// as they are. The tallest matching band is removed, which includes uniform
+/// background repeated at both edges.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StripOverlap {
+    /// Share of compared pixels (0.0 to 1.0) that must match for a band to count as repeated
+    pub similarity: f32,
+    /// Tallest band searched, in pixels of the embedded images
+    pub max_overlap_px: u32,
+}
+
+impl Default for StripOverlap {
+    fn default() -> Self {
+        Self {
+            similarity: 0.98,
+            max_overlap_px: 1000,
+        }
+    }
+}
+
+/// Horizontal alignment of a line of text between the page margins
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextAlign {
+    /// Flush with the left margin
+    Left,
+    /// Centered on the page
+    #[default]
+    Center,
+    /// Flush with the right margin
+    Right,
+}
+
+/// Numeral style of page numbers in the header and footer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PageNumberFormat {
+    /// 1, 2, 3, ...
+    #[default]
+    Arabic,
+    /// i, ii, iii, ..., as often used for a preface
+    LowerRoman,
+    /// I, II, III, ...
+    UpperRoman,
+}
+
+impl PageNumberFormat {
+    /// Write a page number, which should be at least 1, in this style
+    pub fn format(self, number: usize) -> String {
+        const ROMAN: [(usize, &str); 13] = [
+            (1000, "m"), (900, "cm"), (500, "d"), (400, "cd"), (100, "c"), (90, "xc"),
+            (50, "l"), (40, "xl"), (10, "x"), (9, "ix"), (5, "v"), (4, "iv"), (1, "i"),
+        ];
+        let roman = |mut number: usize| {
+            let mut numeral = String::new();
+            for (value, digits) in ROMAN {
+                while number >= value {
+                    numeral.push_str(digits);
+                    number -= value;
+                }
+            }
+            numeral
+        };
+        match self {
+            Self::Arabic => number.to_string(),
+            Self::LowerRoman => roman(number),
+            Self::UpperRoman => roman(number).to_uppercase(),
+        }
+    }
+}
+
+/// Rectangle of an image to keep, see `PdfConfig::crop`
+///
+/// The origin is the top-left corner of the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ImageCrop {
+    /// Position and size in pixels
+    Pixels {
+        /// Distance from the left edge
+        x: u32,
+        /// Distance from the top edge
+        y: u32,
+        /// Width of the rectangle
+        width: u32,
+        /// Height of the rectangle
+        height: u32,
+    },
+    /// Position and size in percent of the image's width and height
+    Percent {
+        /// Distance from the left edge
+        x: f32,
+        /// Distance from the top edge
+        y: f32,
+        /// Width of the rectangle
+        width: f32,
+        /// Height of the rectangle
+        height: f32,
+    },
+}
+
+impl ImageCrop {
+    /// Pixel rectangle `(x, y, width, height)` on an image of the given size, and whether it had to be clamped
+    fn pixel_rect(self, img_width: u32, img_height: u32) -> ((u32, u32, u32, u32), bool) {
+        let (x, y, width, height) = match self {
+            Self::Pixels { x, y, width, height } => (x as f64, y as f64, width as f64, height as f64),
+            Self::Percent { x, y, width, height } => {
+                let (w, h) = (img_width as f64 / 100.0, img_height as f64 / 100.0);
+                (x as f64 * w, y as f64 * h, width as f64 * w, height as f64 * h)
+            }
+        };
+        let requested = [x.round(), y.round(), width.round(), height.round()];
+
+        // 至少保留一个像素
+        let left = requested[0].clamp(0.0, img_width.saturating_sub(1) as f64);
+        let top = requested[1].clamp(0.0, img_height.saturating_sub(1) as f64);
+        let right = (requested[0] + requested[2]).min(img_width as f64).max(left + 1.0);
+        let bottom = (requested[1] + requested[3]).min(img_height as f64).max(top + 1.0);
+        let rect = [left, top, right - left, bottom - top];
+        ((rect[0] as u32, rect[1] as u32, rect[2] as u32, rect[3] as u32), rect != requested)
+    }
+}
+
+/// Crop box of a page, given as distances from the page edges in millimeters
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CropBox {
+    /// Distance from the left page edge
+    pub left_mm: f32,
+    /// Distance from the bottom page edge
+    pub bottom_mm: f32,
+    /// Distance from the right page edge
+    pub right_mm: f32,
+    /// Distance from the top page edge
+    pub top_mm: f32,
+}
+
+impl CropBox {
+    /// Crop box with the same distance from every page edge, e.g. the bleed width
+    pub const fn uniform(inset_mm: f32) -> Self {
+        Self { left_mm: inset_mm, bottom_mm: inset_mm, right_mm: inset_mm, top_mm: inset_mm }
+    }
+}
+
+/// Duotone tint: the image in grayscale, with black and white replaced by two colors
+///
+/// Every gray level maps to the color at that point of the gradient from
+/// `shadow` to `highlight`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tint {
+    /// RGB color of black areas
+    pub shadow: [u8; 3],
+    /// RGB color of white areas
+    pub highlight: [u8; 3],
+}
+
+impl Tint {
+    /// Warm brown tones of old photographs
+    pub const SEPIA: Self = Self {
+        shadow: [40, 26, 13],
+        highlight: [255, 240, 205],
+    };
+}
+
+/// Background color removed from images, see `PdfConfig::chroma_key`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChromaKey {
+    /// RGB color of the background
+    pub color: [u8; 3],
+    /// Largest difference of any channel from `color` still counted as background
+    ///
+    /// Pixels up to twice as far are blended with the replacement to keep
+    /// edges smooth.
+    pub tolerance: u8,
+    /// RGB color the background is replaced with
+    pub replacement: [u8; 3],
+}
+
+impl ChromaKey {
+    /// Replace `color` and pixels within `tolerance` of it with white
+    pub const fn new(color: [u8; 3], tolerance: u8) -> Self {
+        Self { color, tolerance, replacement: [255, 255, 255] }
+    }
+}
+
+/// Image drawn over a page's content at a fixed position, see `PdfConfig::first_page_overlay`
+///
+/// The height follows from the width and the image's aspect ratio.
+/// Transparent areas are blended onto white, so the image covers what lies
+/// beneath its whole rectangle.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PageOverlay {
+    /// Image file to draw
+    pub path: PathBuf,
+    /// Distance from the left page edge in millimeters
+    pub x_mm: f32,
+    /// Distance from the top page edge in millimeters
+    pub top_mm: f32,
+    /// Displayed width in millimeters
+    pub width_mm: f32,
+}
+
+impl PageOverlay {
+    /// Draw `path` 50 mm wide in the top-left corner, 10 mm from the edges
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            x_mm: 10.0,
+            top_mm: 10.0,
+            width_mm: 50.0,
+        }
+    }
+}
+
+/// Prepress boxes of a single page, each given as distances from the page edges
+///
+/// Boxes left `None` keep what the page already has.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PageBoxes {
+    /// Area to which content is clipped in production, including bleed
+    pub bleed: Option<CropBox>,
+    /// Intended size of the finished page after trimming
+    pub trim: Option<CropBox>,
+    /// Extent of the page's meaningful content
+    pub art: Option<CropBox>,
+}
+
+/// Resolution limits chosen per image by classifying it as text-like or photo-like
+///
+/// An image counts as text-like when most sampled pixels are close to black
+/// or white and its colors are muted, as on scanned documents. Text-like
+/// images are downscaled to `text_dpi` and embedded in grayscale, all others
+/// are downscaled to `photo_dpi`. Both limits combine with `max_dpi` and
+/// `low_memory`, the lower limit wins.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContentDpi {
+    /// Resolution limit of text-like images
+    pub text_dpi: f32,
+    /// Resolution limit of photo-like images
+    pub photo_dpi: f32,
+    /// Minimum share (0 to 1) of sampled pixels within 64 levels of black or white in text-like images
+    pub min_ink_paper_ratio: f32,
+    /// Maximum mean saturation (0 to 1) of text-like images
+    pub max_saturation: f32,
+}
+
+impl Default for ContentDpi {
+    fn default() -> Self {
+        Self {
+            text_dpi: 150.0,
+            photo_dpi: 300.0,
+            min_ink_paper_ratio: 0.9,
+            max_saturation: 0.15,
+        }
+    }
+}
+
+/// How often and how patiently a failed write of the output file, or a read of an incomplete image, is repeated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first
+    pub attempts: u32,
+    /// Wait before the first retry; doubled before every further retry
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Memory allowed for decoding images, see `PdfConfig::resource_limits`
+///
+/// Images are decoded one at a time while the pages are built, so the limit
+/// on a single decode is also the limit on decoded bytes in flight. Images
+/// processed for strip mode are all held until the page is sized and are
+/// not counted.
+///
+/// Only the per-image byte limit is implemented. A limit on concurrent
+/// decodes, a shared budget of bytes in flight and their interaction with a
+/// `max_threads` setting are deferred until decoding runs in parallel; the
+/// converter has no thread setting yet. See "Parallel image decoding" in
+/// PROJECT_STATUS.md.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResourceLimits {
+    /// Largest decoded size of an image in bytes, `None` for no limit
+    ///
+    /// Checked against the pixel size and color type from the image header
+    /// before any pixels are decoded; larger images fail with
+    /// `PdfError::ImageTooLarge`. JPEG files embedded without decoding are
+    /// not checked. Defaults to 512 MiB, the limit the `image`
+    /// crate applies on its own, which fits a 100 megapixel RGB photo or an
+    /// 8-bit RGBA image of about 11500×11500 pixels.
+    pub max_decoded_bytes: Option<u64>,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            max_decoded_bytes: Some(512 * 1024 * 1024),
+        }
+    }
+}
+
+/// Checkpoint directory of a resumable folder conversion, see `PdfConfig::checkpoint`
+///
+/// The images are converted in chunks, each saved as `chunk-NNNNN.pdf` in
+/// `dir` and recorded in `manifest.txt` once complete. Running the same
+/// conversion again skips every chunk recorded with the same list of files
+/// (files edited in place are not noticed), converts the rest and merges all
+/// chunks into the output. The directory is left in place afterwards; delete
+/// it to start over.
+///
+/// Settings that concern the whole document (cover, page boxes, PDF/X, XMP
+/// metadata, language, encryption and the first page overlay) are applied to
+/// the merged output. A checkpointed conversion has no table of contents,
+/// notes pages or bookmarks, and `{page}` and `{total}` in running text count
+/// the pages of each chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Checkpoint {
+    /// Directory holding the chunk PDFs and the manifest, created if needed
+    pub dir: PathBuf,
+    /// Images converted per chunk, rounded up to whole pages in grid mode
+    pub images_per_chunk: usize,
+}
+
+impl Checkpoint {
+    /// Checkpoint in `dir` with 200 images per chunk
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            images_per_chunk: 200,
         }
     }
 }
 
+/// Order of the pages created from the images of a folder or archive
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PageOrder {
+    /// Sorted by file name
+    #[default]
+    FileName,
+    /// Sorted by the number captured by the first group of `pattern` in the file name
+    ///
+    /// Useful when the page number is not the only number in the name, e.g.
+    /// `doc_p(\d+)_` for `doc_p003_final.png`. Files with the same number
+    /// keep their file name order.
+    CapturedNumber {
+        /// Pattern whose first capture group holds the page number
+        #[cfg_attr(feature = "serde", serde(with = "crate::preset::regex"))]
+        pattern: Regex,
+        /// What to do with files whose name has no page number
+        unmatched: UnmatchedPages,
+    },
+    /// Sorted by the size of the image file in bytes
+    FileSize(SortDirection),
+    /// Sorted by width times height in pixels, read from the image headers
+    PixelArea(SortDirection),
+}
+
+/// Direction of `PageOrder::FileSize` and `PageOrder::PixelArea`; ties keep file name order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SortDirection {
+    /// Smallest first
+    #[default]
+    Ascending,
+    /// Largest first
+    Descending,
+}
+
+/// Metadata of an image file used for ordering pages
+#[derive(Debug, Clone, Copy)]
+enum SortKey {
+    FileSize,
+    PixelArea,
+}
+
+/// Frames of an animation that become pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrameSampling {
+    /// Every frame
+    #[default]
+    All,
+    /// The frame shown at the start and at every multiple of the interval after it
+    ///
+    /// A frame shown at several sampling times becomes a single page, and an
+    /// interval of zero takes every frame. Frames with a delay of 10 ms or less
+    /// count as 100 ms, as in web browsers.
+    Interval(Duration),
+}
+
+/// Handling of files without a page number when ordering by `PageOrder::CapturedNumber`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnmatchedPages {
+    /// Put them after all numbered pages, sorted by file name
+    #[default]
+    Last,
+    /// Fail the conversion with `PdfError::UnorderedFile`
+    Error,
+}
+
+/// Image ready to be drawn, named by its path for page titles and reports
+struct PreparedImage {
+    path: PathBuf,
+    object: ImageXObject,
+    placement: Placement,
+    /// Soft mask at the size of `object`
+    mask: Option<SoftMask>,
+    /// Caller-supplied transform replacing the one derived from `placement`
+    transform: Option<ImageTransform>,
+    /// Capture location as `(latitude, longitude)` in degrees
+    location: Option<(f64, f64)>,
+}
+
+/// Images of a checkpointed conversion saved together as one chunk PDF
+struct Chunk<'a> {
+    /// Position of the first image among all images
+    start: usize,
+    images: &'a [PathBuf],
+    /// PDF files inserted into the chunk, with the number of its images before each
+    pdf_files: Vec<(usize, PathBuf)>,
+}
+
+impl Chunk<'_> {
+    /// Files the chunk is built from, as recorded in the manifest
+    fn files(&self) -> Vec<PathBuf> {
+        self.images.iter().cloned().chain(self.pdf_files.iter().map(|(_, path)| path.clone())).collect()
+    }
+}
+
+/// PDF assembled one image at a time, created by `PdfConverter::document_builder`
+///
+/// Each added image is decoded and processed right away, so problems with a
+/// file surface when it is added. The pages are laid out and written by
+/// `finish`, since the table of contents, running text and grid or strip
+/// layouts depend on all images.
+pub struct DocumentBuilder<'a> {
+    converter: &'a PdfConverter,
+    images: Vec<PreparedImage>,
+    /// Number of files added, for error positions
+    files: usize,
+}
+
+impl DocumentBuilder<'_> {
+    /// Process an image file and queue its pages, returning how many page images it adds
+    ///
+    /// This is one for still images and the number of sampled frames for
+    /// animated GIFs when `animation_frames` is set. A failed image leaves
+    /// the builder unchanged.
+    pub fn add_image<P: AsRef<Path>>(&mut self, image_path: P) -> Result<usize> {
+        self.add(image_path.as_ref(), None)
+    }
+
+    /// Like `add_image`, but draw the image exactly with `transform` instead of fitting it to the page
+    ///
+    /// The transform is handed to printpdf unchanged: translation from the
+    /// bottom-left page corner, scale relative to the image's size at the
+    /// transform's `dpi` (300 when unset) and rotation. Decoding, processing
+    /// and embedding work as usual, and the image still gets a page of its
+    /// own, or a grid cell's page; only its position and size come from the
+    /// transform. Rounded corners are left out for rotated images.
+    ///
+    /// ```rust
+    /// use pdf_converter::{ImageTransform, Mm, PdfConverter};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// let stamp = dir.path().join("stamp.png");
+    /// image::RgbImage::from_pixel(200, 100, image::Rgb([180, 20, 20])).save(&stamp)?;
+    ///
+    /// let converter = PdfConverter::new();
+    /// let mut builder = converter.document_builder();
+    /// // 200 px at 100 DPI, doubled: 4 inches wide, 10 mm from the bottom-left corner
+    /// builder.add_image_with_transform(&stamp, ImageTransform {
+    ///     translate_x: Some(Mm(10.0)),
+    ///     translate_y: Some(Mm(10.0)),
+    ///     scale_x: Some(2.0),
+    ///     scale_y: Some(2.0),
+    ///     dpi: Some(100.0),
+    ///     ..ImageTransform::default()
+    /// })?;
+    /// let report = builder.finish(dir.path().join("stamp.pdf"))?;
+    /// assert!((report.images[0].effective_dpi - 50.0).abs() < 0.1);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn add_image_with_transform<P: AsRef<Path>>(&mut self, image_path: P, transform: ImageTransform) -> Result<usize> {
+        self.add(image_path.as_ref(), Some(transform))
+    }
+
+    fn add(&mut self, image_path: &Path, transform: Option<ImageTransform>) -> Result<usize> {
+        let page = self.converter.config.page_size();
+        let prepared = self.converter
+            .read_with_retry(|| self.converter.prepare_file(image_path, &std::fs::read(image_path)?, page))
+            .map_err(|e| e.at_image(image_path, self.files))?;
+        self.files += 1;
+        let count = prepared.len();
+        self.images.extend(prepared.into_iter().map(|image| match transform {
+            // 报告和圆角按变换后的实际位置计算
+            Some(transform) => PreparedImage {
+                placement: self.converter.transform_placement(&image.object, &transform, image.placement.page),
+                transform: Some(transform),
+                ..image
+            },
+            None => image,
+        }));
+        Ok(count)
+    }
+
+    /// Number of page images added so far
+    pub fn image_count(&self) -> usize {
+        self.images.len()
+    }
+
+    /// Lay out the added images and save the PDF to `output_path`
+    pub fn finish<P: AsRef<Path>>(self, output_path: P) -> Result<ConversionReport> {
+        let count = self.images.len();
+        self.converter.build_pdf(self.images.into_iter().map(Ok), Some(count), &[], output_path.as_ref())
+    }
+}
+
+/// Maps an image path to the title shown for its page
+type NameTransform = std::sync::Arc<dyn Fn(&Path) -> String + Send + Sync>;
+
+/// Called after every converted image, shared with the converters of checkpoint chunks
+type ProgressCallback = std::sync::Arc<dyn Fn(&Progress) + Send + Sync>;
+
+/// Custom processing of every decoded image, shared with the converters of checkpoint chunks
+type ImageProcessor = std::sync::Arc<dyn Fn(::image::DynamicImage) -> ::image::DynamicImage + Send + Sync>;
+
 /// PDF converter for images
 pub struct PdfConverter {
     config: PdfConfig,
+    name_transform: Option<NameTransform>,
+    progress: Option<ProgressCallback>,
+    image_processor: Option<ImageProcessor>,
+    /// Folder the nested bookmarks are relative to, see `PdfConfig::nested_bookmarks`
+    outline_root: Option<PathBuf>,
 }
 
 impl Default for PdfConverter {
@@ -48,14 +1862,119 @@ impl Default for PdfConverter {
 impl PdfConverter {
     /// Create a new PDF converter with default settings
     pub fn new() -> Self {
-        Self {
-            config: PdfConfig::default(),
-        }
+        Self::with_config(PdfConfig::default())
     }
 
     /// Create a new PDF converter with custom configuration
     pub fn with_config(config: PdfConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            name_transform: None,
+            progress: None,
+            image_processor: None,
+            outline_root: None,
+        }
+    }
+
+    /// Derive page titles (used for bookmarks) from image paths with `transform`
+    /// 
+    /// Without a transform the file stem is used. This lets the outline read
+    /// nicely without renaming the source files.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust,no_run
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    /// 
+    /// let config = PdfConfig { bookmarks: true, ..PdfConfig::default() };
+    /// let converter = PdfConverter::with_config(config).with_name_transform(|path| {
+    ///     let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    ///     stem.trim_start_matches("scan_").replace('_', " ")
+    /// });
+    /// converter.convert_folder_to_pdf("images/", "output.pdf")?;
+    /// # Ok::<(), pdf_converter::PdfError>(())
+    /// ```
+    pub fn with_name_transform<F>(mut self, transform: F) -> Self
+    where
+        F: Fn(&Path) -> String + Send + Sync + 'static,
+    {
+        self.name_transform = Some(std::sync::Arc::new(transform));
+        self
+    }
+
+    /// Report the progress of conversions to `callback` after every converted image
+    /// 
+    /// The callback runs on the converting thread, so it should return
+    /// quickly. Images count in page order; with `animation_frames` every
+    /// frame counts and the total is unknown.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust,no_run
+    /// use pdf_converter::PdfConverter;
+    /// 
+    /// let converter = PdfConverter::new().with_progress(|progress| {
+    ///     let total = progress.total.map_or("?".to_string(), |total| total.to_string());
+    ///     eprintln!("{}/{} {}", progress.index + 1, total, progress.image.path.display());
+    /// });
+    /// converter.convert_folder_to_pdf("images/", "output.pdf")?;
+    /// # Ok::<(), pdf_converter::PdfError>(())
+    /// ```
+    pub fn with_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Progress) + Send + Sync + 'static,
+    {
+        self.progress = Some(std::sync::Arc::new(callback));
+        self
+    }
+
+    /// Pass every decoded image through `processor` before it is placed
+    /// 
+    /// Extends the built-in processing with custom filters or drawing on the
+    /// pixels. The processor runs after trimming and before placement, so the
+    /// page layout follows the size of the image it returns. JPEG files are
+    /// always decoded while a processor is set, since they cannot be embedded
+    /// as they are.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use pdf_converter::PdfConverter;
+    /// 
+    /// # let dir = tempfile::tempdir()?;
+    /// # let folder = dir.path().join("scans");
+    /// # std::fs::create_dir(&folder)?;
+    /// # image::RgbImage::new(40, 30).save(folder.join("a.png"))?;
+    /// # image::RgbImage::new(30, 40).save(folder.join("b.jpg"))?;
+    /// let calls = Arc::new(AtomicUsize::new(0));
+    /// let converter = PdfConverter::new().with_image_processor({
+    ///     let calls = calls.clone();
+    ///     move |mut img| {
+    ///         calls.fetch_add(1, Ordering::Relaxed);
+    ///         img.invert();
+    ///         img
+    ///     }
+    /// });
+    /// converter.convert_folder_to_pdf(&folder, dir.path().join("inverted.pdf"))?;
+    /// assert_eq!(calls.load(Ordering::Relaxed), 2);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_image_processor<F>(mut self, processor: F) -> Self
+    where
+        F: Fn(::image::DynamicImage) -> ::image::DynamicImage + Send + Sync + 'static,
+    {
+        self.image_processor = Some(std::sync::Arc::new(processor));
+        self
+    }
+
+    /// Title of the page showing the image at `image_path`
+    fn page_title(&self, image_path: &Path) -> String {
+        match &self.name_transform {
+            Some(transform) => transform(image_path),
+            None => image_path.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+        }
     }
 
     /// Convert all images in a folder to a single PDF
@@ -67,34 +1986,440 @@ impl PdfConverter {
     /// 
     /// # Example
     /// 
-    /// ```rust
+    /// ```rust,no_run
     /// use pdf_converter::PdfConverter;
     /// 
     /// let converter = PdfConverter::new();
     /// converter.convert_folder_to_pdf("images/", "output.pdf")?;
+    /// # Ok::<(), pdf_converter::PdfError>(())
     /// ```
-    pub fn convert_folder_to_pdf<P: AsRef<Path>>(&self, folder_path: P, output_path: P) -> Result<()> {
-        let folder = folder_path.as_ref();
-        let output = output_path.as_ref();
-        
-        log::info!("📄 开始生成PDF: {} -> {}", folder.display(), output.display());
-        
-        if !folder.exists() || !folder.is_dir() {
+    pub fn convert_folder_to_pdf<P: AsRef<Path>, Q: AsRef<Path>>(&self, folder_path: P, output_path: Q) -> Result<()> {
+        self.convert_folder_to_pdf_with_report(folder_path, output_path)?;
+        Ok(())
+    }
+
+    /// Convert all images in a folder to a single PDF and summarize the conversion
+    /// 
+    /// Works like `convert_folder_to_pdf`. The report lists the converted
+    /// images, timings and the files skipped because they are not images, and
+    /// can be printed with `write_report`.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust,no_run
+    /// use pdf_converter::PdfConverter;
+    /// 
+    /// let converter = PdfConverter::new();
+    /// let report = converter.convert_folder_to_pdf_with_report("images/", "output.pdf")?;
+    /// converter.write_report(&report, std::io::stderr())?;
+    /// # Ok::<(), pdf_converter::PdfError>(())
+    /// ```
+    ///
+    /// The folder path may contain doubled, trailing or, on Windows, mixed
+    /// separators; the reported image paths are built from the cleaned-up path:
+    ///
+    /// ```rust
+    /// use pdf_converter::PdfConverter;
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// let scans = dir.path().join("scans");
+    /// std::fs::create_dir(&scans)?;
+    /// image::RgbImage::new(20, 20).save(scans.join("page.png"))?;
+    ///
+    /// let folder = format!("{}//scans/", dir.path().display());
+    /// let report = PdfConverter::new().convert_folder_to_pdf_with_report(&folder, dir.path().join("scans.pdf"))?;
+    /// assert_eq!(report.images[0].path.to_str(), scans.join("page.png").to_str());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn convert_folder_to_pdf_with_report<P: AsRef<Path>, Q: AsRef<Path>>(&self, folder_path: P, output_path: Q) -> Result<ConversionReport> {
+        let folder = folder_path.as_ref();
+        let output = output_path.as_ref();
+
+        log::info!("📄 开始生成PDF: {} -> {}", folder.display(), output.display());
+
+        // 上次生成的输出文件可能就在同一文件夹中
+        let previous_output = std::fs::canonicalize(output).ok();
+        let mut image_files = Vec::new();
+        let mut pdf_files = Vec::new();
+        for path in self.folder_images(folder, self.config.include_pdf_files)? {
+            if !is_pdf_file(&path) {
+                image_files.push(path);
+            } else if std::fs::canonicalize(&path).ok() != previous_output {
+                // 记录PDF之前的图片数量，确定插入位置
+                pdf_files.push((image_files.len(), path));
+            }
+        }
+        log::info!("📸 找到 {} 张图片，开始生成PDF", image_files.len());
+
+        let grouped = self.grouped_for(self.file_page_count(image_files.len()), &pdf_files)?;
+        let converter = grouped.as_ref().unwrap_or(self);
+        let mut report = match &self.config.checkpoint {
+            Some(checkpoint) if self.config.strip_mode.is_none() => converter.build_checkpointed(checkpoint, &image_files, &pdf_files, output)?,
+            _ => {
+                let page_size = converter.config.page_size();
+                let images = converter.prepare_files(&image_files, |path| Ok(std::fs::read(path)?), |_| page_size);
+                converter.build_pdf(images, converter.file_page_count(image_files.len()), &pdf_files, output)?
+            }
+        };
+
+        // 输出文件可能就在同一文件夹中，不计入跳过的文件
+        let output_file = std::fs::canonicalize(output)?;
+        let converted = |path: &Path| is_image_file(path) || (self.config.include_pdf_files && is_pdf_file(path));
+        for entry in std::fs::read_dir(folder)? {
+            let path = entry?.path();
+            if path.is_file() && !converted(&path) && std::fs::canonicalize(&path)? != output_file {
+                report.skipped.push(path);
+            }
+        }
+        report.skipped.sort();
+        Ok(report)
+    }
+
+    /// Convert the images of a folder and all its subfolders into one PDF
+    ///
+    /// Every folder contributes its own images first, in the configured page
+    /// order, followed by its subfolders sorted by name. With `bookmarks` and
+    /// `nested_bookmarks` set, the outline mirrors the folder structure, e.g.
+    /// of a book scanned into chapter and section folders. PDF files in the
+    /// folders are not inserted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// let book = dir.path().join("book");
+    /// for (folder, name) in [("", "cover.png"), ("chapter-1", "p1.png"), ("chapter-1", "p2.png"), ("chapter-2/section-1", "p3.png")] {
+    ///     std::fs::create_dir_all(book.join(folder))?;
+    ///     image::RgbImage::from_pixel(20, 30, image::Rgb([200, 200, 190])).save(book.join(folder).join(name))?;
+    /// }
+    ///
+    /// let config = PdfConfig { bookmarks: true, nested_bookmarks: true, ..PdfConfig::default() };
+    /// let output = dir.path().join("book.pdf");
+    /// let report = PdfConverter::with_config(config).convert_folder_tree_to_pdf(&book, &output)?;
+    /// assert_eq!(report.images.len(), 4);
+    ///
+    /// // Top level: the cover and the two chapter folders
+    /// let document = printpdf::lopdf::Document::load(&output)?;
+    /// let outlines = document.catalog()?.get(b"Outlines")?.as_reference()?;
+    /// assert_eq!(document.get_dictionary(outlines)?.get(b"Count")?.as_i64()?, 3);
+    ///
+    /// // A single chapter folder still gets its own entry
+    /// std::fs::remove_file(book.join("cover.png"))?;
+    /// std::fs::remove_dir_all(book.join("chapter-2"))?;
+    /// let config = PdfConfig { bookmarks: true, nested_bookmarks: true, ..PdfConfig::default() };
+    /// PdfConverter::with_config(config).convert_folder_tree_to_pdf(&book, &output)?;
+    /// let document = printpdf::lopdf::Document::load(&output)?;
+    /// let outlines = document.catalog()?.get(b"Outlines")?.as_reference()?;
+    /// let first = document.get_dictionary(outlines)?.get(b"First")?.as_reference()?;
+    /// assert_eq!(document.get_dictionary(first)?.get(b"Title")?.as_str()?, b"chapter-1");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// With `require_consistent_orientation`, all images of the tree must
+    /// share an orientation, not only those within each folder:
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter, PdfError};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// let book = dir.path().join("book");
+    /// for (folder, width, height) in [("ch1", 20, 30), ("ch2", 30, 20)] {
+    ///     std::fs::create_dir_all(book.join(folder))?;
+    ///     image::RgbImage::new(width, height).save(book.join(folder).join("page.png"))?;
+    /// }
+    ///
+    /// let config = PdfConfig { require_consistent_orientation: true, ..PdfConfig::default() };
+    /// let error = PdfConverter::with_config(config).convert_folder_tree_to_pdf(&book, dir.path().join("book.pdf")).unwrap_err();
+    /// assert!(matches!(error, PdfError::MixedOrientation { .. }));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn convert_folder_tree_to_pdf<P: AsRef<Path>, Q: AsRef<Path>>(&self, folder_path: P, output_path: Q) -> Result<ConversionReport> {
+        let folder = folder_path.as_ref();
+        let output = output_path.as_ref();
+        if !folder.is_dir() {
             return Err(PdfError::InvalidPath(folder.display().to_string()));
         }
 
-        // 收集所有图片文件
-        let mut image_files = self.collect_image_files(folder)?;
-        
+        log::info!("📄 开始生成PDF（含子文件夹）: {} -> {}", folder.display(), output.display());
+        let mut image_files = Vec::new();
+        self.collect_tree_images(folder, &mut image_files)?;
         if image_files.is_empty() {
             return Err(PdfError::NoImagesFound(folder.display().to_string()));
         }
-
-        // 按文件名排序
-        image_files.sort();
+        self.check_orientation(&image_files, |path| self.upright_dimensions(path).map(Some))?;
         log::info!("📸 找到 {} 张图片，开始生成PDF", image_files.len());
 
-        self.convert_images_to_pdf(&image_files, output)
+        // 书签按传入的文件夹分层，每个子文件夹都有条目
+        let mut tree = self.derived(self.config.clone());
+        tree.outline_root = Some(folder.to_path_buf());
+        let grouped = tree.grouped_for(tree.file_page_count(image_files.len()), &[])?;
+        let converter = grouped.as_ref().unwrap_or(&tree);
+        let page_size = converter.config.page_size();
+        let images = converter.prepare_files(&image_files, |path| Ok(std::fs::read(path)?), |_| page_size);
+        converter.build_pdf(images, converter.file_page_count(image_files.len()), &[], output)
+    }
+
+    /// Append the images of `folder` in page order, then those of its subfolders by name
+    ///
+    /// Orientations are not checked here, since they must agree across the whole tree.
+    fn collect_tree_images(&self, folder: &Path, image_files: &mut Vec<PathBuf>) -> Result<()> {
+        match self.sorted_folder_images(folder, false) {
+            Ok(images) => image_files.extend(images),
+            Err(PdfError::NoImagesFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+
+        // 不跟随符号链接，避免目录循环
+        let mut subfolders = Vec::new();
+        for entry in std::fs::read_dir(folder)? {
+            let entry = entry?;
+            let hidden = self.config.skip_hidden && entry.file_name().as_encoded_bytes().starts_with(b".");
+            if entry.file_type()?.is_dir() && !hidden {
+                subfolders.push(entry.path());
+            }
+        }
+        subfolders.sort();
+        for subfolder in subfolders {
+            self.collect_tree_images(&subfolder, image_files)?;
+        }
+        Ok(())
+    }
+
+    /// Convert every image in a folder to its own single-image PDF
+    /// 
+    /// Each image is written to `<stem>.pdf` in `output_dir`, which is created
+    /// if needed. Images sharing a stem, such as `scan.jpg` and `scan.png`,
+    /// keep their extension instead (`scan.jpg.pdf`) so none is overwritten.
+    /// Returns the written files in page order.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust,no_run
+    /// use pdf_converter::PdfConverter;
+    /// 
+    /// let converter = PdfConverter::new();
+    /// for pdf in converter.convert_folder_to_individual_pdfs("images/", "pdfs/")? {
+    ///     println!("{}", pdf.display());
+    /// }
+    /// # Ok::<(), pdf_converter::PdfError>(())
+    /// ```
+    pub fn convert_folder_to_individual_pdfs<P: AsRef<Path>, Q: AsRef<Path>>(&self, folder_path: P, output_dir: Q) -> Result<Vec<PathBuf>> {
+        let folder = folder_path.as_ref();
+        let output_dir = output_dir.as_ref();
+
+        log::info!("📄 逐张生成PDF: {} -> {}", folder.display(), output_dir.display());
+
+        let image_files = self.folder_images(folder, false)?;
+        std::fs::create_dir_all(output_dir)?;
+
+        let stem = |path: &Path| path.file_stem().unwrap_or_default().to_os_string();
+        let mut outputs = Vec::with_capacity(image_files.len());
+        for image_path in &image_files {
+            let shared_stem = image_files.iter().filter(|other| stem(other) == stem(image_path)).count() > 1;
+            let mut name = if shared_stem { image_path.file_name().unwrap_or_default().to_os_string() } else { stem(image_path) };
+            name.push(".pdf");
+
+            let output = output_dir.join(name);
+            self.convert_images_to_pdf(std::slice::from_ref(image_path), &output)?;
+            outputs.push(output);
+        }
+
+        log::info!("✅ 共生成 {} 个PDF", outputs.len());
+        Ok(outputs)
+    }
+
+    /// Convert each of several folders to its own PDF
+    /// 
+    /// Each folder is written to `<folder name>.pdf` in `output_dir`, which is
+    /// created if needed; folders sharing a name get a numbered suffix
+    /// (`scans_2.pdf`) so none is overwritten. Every folder gets the same
+    /// configuration, so a `cover_pdf` starts each output with the same
+    /// cover pages. A folder that fails to convert
+    /// does not stop the others: when any fails, the result is
+    /// `PdfError::Batch` with the reports of the converted folders and the
+    /// error of every failed one.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust,no_run
+    /// use pdf_converter::{PdfConverter, PdfError};
+    /// use std::path::PathBuf;
+    /// 
+    /// let folders = vec![PathBuf::from("chapter1"), PathBuf::from("chapter2")];
+    /// 
+    /// let converter = PdfConverter::new();
+    /// match converter.convert_folders_to_pdfs(&folders, "pdfs/") {
+    ///     Ok(reports) => println!("{} PDFs written", reports.len()),
+    ///     Err(PdfError::Batch { failures, .. }) => {
+    ///         for (folder, e) in failures {
+    ///             eprintln!("{}: {}", folder.display(), e);
+    ///         }
+    ///     }
+    ///     Err(e) => return Err(e),
+    /// }
+    /// # Ok::<(), pdf_converter::PdfError>(())
+    /// ```
+    pub fn convert_folders_to_pdfs<P: AsRef<Path>>(&self, folders: &[PathBuf], output_dir: P) -> Result<Vec<ConversionReport>> {
+        let output_dir = output_dir.as_ref();
+        std::fs::create_dir_all(output_dir)?;
+
+        let mut used_names = HashMap::new();
+        let mut reports = Vec::with_capacity(folders.len());
+        let mut failures = Vec::new();
+        for (index, folder) in folders.iter().enumerate() {
+            log::info!("📁 转换第 {}/{} 个文件夹: {}", index + 1, folders.len(), folder.display());
+
+            // 以文件夹名命名，同名文件夹依次加编号
+            let name = std::fs::canonicalize(folder)
+                .ok()
+                .and_then(|path| path.file_name().map(|name| name.to_string_lossy().into_owned()))
+                .unwrap_or_else(|| "output".to_string());
+            let count = used_names.entry(name.clone()).or_insert(0);
+            *count += 1;
+            let file_name = if *count == 1 { format!("{}.pdf", name) } else { format!("{}_{}.pdf", name, count) };
+
+            match self.convert_folder_to_pdf_with_report(folder, output_dir.join(file_name)) {
+                Ok(report) => reports.push(report),
+                Err(e) => {
+                    log::warn!("⚠️ 文件夹转换失败: {}: {}", folder.display(), e);
+                    failures.push((folder.clone(), e));
+                }
+            }
+        }
+
+        log::info!("✅ 共转换 {} 个文件夹，失败 {} 个", reports.len(), failures.len());
+        if failures.is_empty() {
+            Ok(reports)
+        } else {
+            Err(PdfError::Batch { reports, failures })
+        }
+    }
+
+    /// Convert the sections of a project file into one PDF
+    /// 
+    /// A project file lists the sections of a document, each taking its
+    /// images from a `folder`, a `manifest` (a text file naming one image per
+    /// line) or an `images` list, and optionally overriding fields of the
+    /// configuration. The project itself names the `output` file, an optional
+    /// `cover` PDF and overrides for the whole document. Files ending in
+    /// `.toml` are read as TOML, others as JSON; relative paths are resolved
+    /// against the project file's directory.
+    /// 
+    /// Document-wide settings such as the title, encryption or metadata are
+    /// taken from the converter's configuration and the project's overrides;
+    /// section overrides affect how their images are laid out. Requires the
+    /// `serde` feature.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use pdf_converter::PdfConverter;
+    /// 
+    /// # let dir = tempfile::tempdir()?;
+    /// # for (folder, count) in [("day1", 2), ("day2", 3)] {
+    /// #     std::fs::create_dir(dir.path().join(folder))?;
+    /// #     for i in 0..count {
+    /// #         image::RgbImage::new(40, 30).save(dir.path().join(folder).join(format!("{i}.png"))).unwrap();
+    /// #     }
+    /// # }
+    /// std::fs::write(dir.path().join("day2.txt"), "# best of day 2\nday2/2.png\nday2/0.png\n")?;
+    /// std::fs::write(dir.path().join("album.toml"), r#"
+    ///     output = "album.pdf"
+    /// 
+    ///     [config]
+    ///     title = "Holiday"
+    /// 
+    ///     [[sections]]
+    ///     folder = "day1"
+    /// 
+    ///     [[sections]]
+    ///     manifest = "day2.txt"
+    ///     config = { margin_mm = 0.0 }
+    /// "#)?;
+    /// 
+    /// let report = PdfConverter::new().convert_project(dir.path().join("album.toml"))?;
+    /// assert_eq!(report.page_count, 4);
+    /// assert!(report.output.ends_with("album.pdf"));
+    /// # Ok::<(), pdf_converter::PdfError>(())
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn convert_project<P: AsRef<Path>>(&self, project_file: P) -> Result<ConversionReport> {
+        let started = Instant::now();
+        let project_path = project_file.as_ref();
+        let project = project::load(project_path)?;
+        let mut config = project::apply_overrides(&self.config, &project.config, project_path)?;
+        if project.cover.is_some() {
+            config.cover_pdf = project.cover.clone();
+        }
+        let document = PdfConverter::with_config(config);
+        log::info!("📚 开始生成项目: {} ({} 个部分) -> {}", project_path.display(), project.sections.len(), project.output.display());
+
+        // 各部分先写入临时目录，合并后删除
+        static NEXT_PROJECT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let project_id = NEXT_PROJECT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let part_dir = std::env::temp_dir().join(format!("pdf-converter-project-{}-{}", std::process::id(), project_id));
+        std::fs::create_dir_all(&part_dir)?;
+
+        let mut images = Vec::new();
+        let mut skipped = Vec::new();
+        let merged = (|| {
+            let mut parts = Vec::with_capacity(project.sections.len());
+            for (index, section) in project.sections.iter().enumerate() {
+                log::info!("📑 转换第 {}/{} 部分", index + 1, project.sections.len());
+                let section_config = project::apply_overrides(&document.config, &section.config, project_path)?;
+                let mut converter = PdfConverter::with_config(part_config(&section_config));
+                converter.image_processor = self.image_processor.clone();
+                // 进度按整个项目计数
+                if let Some(progress) = self.progress.clone() {
+                    let start = images.len();
+                    converter = converter.with_progress(move |section_progress| {
+                        progress(&Progress {
+                            index: start + section_progress.index,
+                            total: None,
+                            image: section_progress.image,
+                            elapsed: started.elapsed(),
+                        })
+                    });
+                }
+
+                let part = part_dir.join(format!("section-{:03}.pdf", index));
+                let report = match &section.folder {
+                    Some(folder) => converter.convert_folder_to_pdf_with_report(folder, &part)?,
+                    None => {
+                        let files = section.listed_images()?;
+                        let page_size = converter.config.page_size();
+                        let prepared = converter.prepare_files(&files, |path| Ok(std::fs::read(path)?), |_| page_size);
+                        converter.build_pdf(prepared, converter.file_page_count(files.len()), &[], &part)?
+                    }
+                };
+                images.extend(report.images);
+                skipped.extend(report.skipped);
+                parts.push(part);
+            }
+            document.merge_parts(&parts, &project.output)
+        })();
+        if let Err(e) = std::fs::remove_dir_all(&part_dir) {
+            log::warn!("⚠️ 无法删除临时文件: {}: {}", part_dir.display(), e);
+        }
+        let (output_bytes, page_count) = merged?;
+
+        Ok(ConversionReport {
+            output: project.output,
+            output_bytes,
+            page_count,
+            low_resolution: document.low_resolution(&images),
+            images,
+            skipped,
+            elapsed: started.elapsed(),
+        })
+    }
+
+    /// Write a conversion summary, as printed by the command-line tools, to `w`
+    pub fn write_report<W: Write>(&self, report: &ConversionReport, w: W) -> Result<()> {
+        report::write_report(report, w)?;
+        Ok(())
     }
 
     /// Convert a single image to PDF
@@ -103,7 +2428,7 @@ impl PdfConverter {
     /// 
     /// * `image_path` - Path to the image file
     /// * `output_path` - Path where the PDF will be saved
-    pub fn convert_image_to_pdf<P: AsRef<Path>>(&self, image_path: P, output_path: P) -> Result<()> {
+    pub fn convert_image_to_pdf<P: AsRef<Path>, Q: AsRef<Path>>(&self, image_path: P, output_path: Q) -> Result<()> {
         let image = image_path.as_ref();
         let output = output_path.as_ref();
         
@@ -119,136 +2444,3003 @@ impl PdfConverter {
 
     /// Convert multiple specific images to PDF
     /// 
+    /// Every image, the first included, is prepared, logged and placed by the
+    /// same loop; the first one merely lands on the page created together
+    /// with the document. Identical images therefore give identical pages:
+    /// 
+    /// ```rust
+    /// use pdf_converter::PdfConverter;
+    /// use printpdf::lopdf::Document;
+    /// 
+    /// # let dir = tempfile::tempdir()?;
+    /// # let image = dir.path().join("wide.png");
+    /// # image::RgbImage::new(400, 100).save(&image).unwrap();
+    /// # let output = dir.path().join("output.pdf");
+    /// let converter = PdfConverter::new();
+    /// converter.convert_images_to_pdf(&[image.clone(), image], &output)?;
+    /// 
+    /// let doc = Document::load(&output).unwrap();
+    /// let pages: Vec<_> = doc.get_pages().into_values().collect();
+    /// // lopdf objects only implement Debug, so pages are compared by their Debug output
+    /// let media_box = |page| format!("{:?}", doc.get_dictionary(page).unwrap().get(b"MediaBox"));
+    /// let image_matrix = |page| {
+    ///     let content = doc.get_and_decode_page_content(page).unwrap();
+    ///     format!("{:?}", content.operations.into_iter().find(|op| op.operator == "cm").map(|op| op.operands))
+    /// };
+    /// assert_eq!(media_box(pages[0]), media_box(pages[1]));
+    /// assert_eq!(image_matrix(pages[0]), image_matrix(pages[1]));
+    /// # Ok::<(), pdf_converter::PdfError>(())
+    /// ```
+    /// 
     /// # Arguments
     /// 
     /// * `image_paths` - Vector of paths to image files
     /// * `output_path` - Path where the PDF will be saved
     pub fn convert_images_to_pdf<P: AsRef<Path>>(&self, image_paths: &[PathBuf], output_path: P) -> Result<()> {
-        let output = output_path.as_ref();
-        
-        if image_paths.is_empty() {
-            return Err(PdfError::Custom("No images provided".to_string()));
-        }        // 创建PDF文档
-        let (doc, page1, layer1) = printpdf::PdfDocument::new(
-            &self.config.title,
-            printpdf::Mm(self.config.page_width_mm),
-            printpdf::Mm(self.config.page_height_mm),
-            "Layer 1"
-        );
-        
-        // 处理第一张图片
-        let current_layer = doc.get_page(page1).get_layer(layer1);
-        self.add_image_to_pdf_layer(&current_layer, &image_paths[0])?;
-
-        // 处理剩余图片（每张图片一页）
-        for (index, image_path) in image_paths.iter().enumerate().skip(1) {
-            log::info!("  处理第 {}/{} 张图片: {}", index + 1, image_paths.len(), 
-                image_path.file_name().unwrap_or_default().to_string_lossy());            // 添加新页面
-            let (page_index, layer_index) = doc.add_page(
-                printpdf::Mm(self.config.page_width_mm),
-                printpdf::Mm(self.config.page_height_mm),
-                "Layer 1"
-            );
-            
-            let current_layer = doc.get_page(page_index).get_layer(layer_index);
-            self.add_image_to_pdf_layer(&current_layer, image_path)?;
-        }
+        let page_size = self.config.page_size();
+        self.convert_images_to_pdf_with_page_sizes(image_paths, output_path, |_| page_size)
+    }
 
-        // 保存PDF - 使用BufWriter
-        let file = std::fs::File::create(output)?;
-        let mut buf_writer = BufWriter::new(file);
-        doc.save(&mut buf_writer)?;
-        
-        log::info!("✅ PDF生成完成: {}", output.display());
+    /// Convert multiple specific images to PDF, choosing the page size of each page
+    /// 
+    /// # Arguments
+    /// 
+    /// * `image_paths` - Vector of paths to image files
+    /// * `output_path` - Path where the PDF will be saved
+    /// * `page_size_for` - Maps the index of an image to the size of its page
+    /// 
+    /// # Example
+    /// 
+    /// ```rust,no_run
+    /// use pdf_converter::{PageSize, PdfConverter};
+    /// use std::path::PathBuf;
+    /// 
+    /// let images = vec![PathBuf::from("text.jpg"), PathBuf::from("foldout.jpg")];
+    /// let sizes = [PageSize::A4, PageSize::A3];
+    /// 
+    /// let converter = PdfConverter::new();
+    /// converter.convert_images_to_pdf_with_page_sizes(&images, "output.pdf", |i| sizes[i])?;
+    /// # Ok::<(), pdf_converter::PdfError>(())
+    /// ```
+    pub fn convert_images_to_pdf_with_page_sizes<P, F>(&self, image_paths: &[PathBuf], output_path: P, page_size_for: F) -> Result<()>
+    where
+        P: AsRef<Path>,
+        F: Fn(usize) -> PageSize,
+    {
+        let grouped = self.grouped_for(self.file_page_count(image_paths.len()), &[])?;
+        let converter = grouped.as_ref().unwrap_or(self);
+        let images = converter.prepare_files(image_paths, |path| Ok(std::fs::read(path)?), page_size_for);
+        converter.build_pdf(images, converter.file_page_count(image_paths.len()), &[], output_path.as_ref())?;
         Ok(())
     }
 
-    /// Get the current configuration
-    pub fn config(&self) -> &PdfConfig {
-        &self.config
-    }
+    /// Convert multiple specific images to PDF, sizing every page to its image's true resolution
+    /// 
+    /// Each page is as large as its image printed at its own DPI plus the
+    /// margins, so scans captured at different resolutions keep their physical
+    /// size. Images missing from `dpi_map` use the configured `dpi`. Trimming
+    /// or deskewing may change an image's proportions after the page is sized;
+    /// it is then fitted into the page as usual.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `image_paths` - Vector of paths to image files
+    /// * `output_path` - Path where the PDF will be saved
+    /// * `dpi_map` - Resolution of individual images, keyed by their path in `image_paths`
+    /// 
+    /// # Example
+    /// 
+    /// ```rust,no_run
+    /// use pdf_converter::PdfConverter;
+    /// use std::collections::HashMap;
+    /// use std::path::PathBuf;
+    /// 
+    /// let images = vec![PathBuf::from("scan_300.png"), PathBuf::from("scan_600.png")];
+    /// let dpi_map = HashMap::from([(PathBuf::from("scan_600.png"), 600.0)]);
+    /// 
+    /// let converter = PdfConverter::new();
+    /// converter.convert_images_to_pdf_with_dpi(&images, "output.pdf", &dpi_map)?;
+    /// # Ok::<(), pdf_converter::PdfError>(())
+    /// ```
+    pub fn convert_images_to_pdf_with_dpi<P: AsRef<Path>>(&self, image_paths: &[PathBuf], output_path: P, dpi_map: &HashMap<PathBuf, f32>) -> Result<()> {
+        let margin_mm = self.config.effective_margin_mm();
+        let mut page_sizes = Vec::with_capacity(image_paths.len());
+        for image_path in image_paths {
+            let dpi = dpi_map.get(image_path).copied().unwrap_or(self.config.dpi);
+            // 只读取文件头获取像素尺寸
+            let (img_width, img_height) = self.upright_dimensions(image_path)?;
+            page_sizes.push(PageSize::new(
+                img_width as f32 * 25.4 / dpi + 2.0 * margin_mm,
+                img_height as f32 * 25.4 / dpi + 2.0 * margin_mm,
+            ));
+        }
 
-    /// Update the configuration
-    pub fn set_config(&mut self, config: PdfConfig) {
-        self.config = config;
+        self.convert_images_to_pdf_with_page_sizes(image_paths, output_path, |index| page_sizes[index])
     }
 
-    /// Collect all image files from folder
-    fn collect_image_files(&self, folder: &Path) -> Result<Vec<PathBuf>> {
-        let image_extensions = ["jpg", "jpeg", "png", "gif", "bmp", "webp"];
-        let mut image_files = Vec::new();
+    /// Convert all images in a ZIP archive (such as a CBZ comic) to a single PDF
+    /// 
+    /// Image entries are read straight from the archive in sorted entry order,
+    /// without extracting them to disk. Other entries are ignored, like
+    /// non-image files in a folder. Entry names may separate directories with
+    /// backslashes, as some Windows tools write them; file names are matched
+    /// against `rotations` and `page_order` the same way either way:
+    /// 
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter};
+    /// use printpdf::lopdf::{Document, Object};
+    /// use std::io::Write;
+    /// 
+    /// # let dir = tempfile::tempdir()?;
+    /// let mut png = std::io::Cursor::new(Vec::new());
+    /// image::RgbImage::new(300, 100).write_to(&mut png, image::ImageOutputFormat::Png)?;
+    /// let mut zip = zip::ZipWriter::new(std::fs::File::create(dir.path().join("scans.zip"))?);
+    /// zip.start_file("scans\\page.png", Default::default())?;
+    /// zip.write_all(png.get_ref())?;
+    /// zip.finish()?;
+    /// 
+    /// let mut config = PdfConfig::default();
+    /// config.rotations.insert("page.png".to_string(), 90.0);
+    /// let output = dir.path().join("scans.pdf");
+    /// PdfConverter::with_config(config).convert_zip_to_pdf(dir.path().join("scans.zip"), &output)?;
+    /// 
+    /// // The rotated image is embedded upright
+    /// let document = Document::load(&output)?;
+    /// let image = document.objects.values().find_map(|object| match object {
+    ///     Object::Stream(stream) if stream.dict.has(b"Width") => Some(&stream.dict),
+    ///     _ => None,
+    /// }).unwrap();
+    /// assert_eq!((image.get(b"Width")?.as_i64()?, image.get(b"Height")?.as_i64()?), (100, 300));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    /// 
+    /// # Arguments
+    /// 
+    /// * `zip_path` - Path to the ZIP archive
+    /// * `output_path` - Path where the PDF will be saved
+    pub fn convert_zip_to_pdf<P: AsRef<Path>, Q: AsRef<Path>>(&self, zip_path: P, output_path: Q) -> Result<()> {
+        let zip_path = zip_path.as_ref();
+        let output = output_path.as_ref();
 
-        for entry in std::fs::read_dir(folder)? {
-            let entry = entry?;
-            if entry.file_type()?.is_file() {
-                if let Some(extension) = entry.path().extension() {
-                    if let Some(ext_str) = extension.to_str() {
-                        if image_extensions.contains(&ext_str.to_lowercase().as_str()) {
-                            image_files.push(entry.path());
-                        }
-                    }
-                }
-            }
+        log::info!("📄 从压缩包生成PDF: {} -> {}", zip_path.display(), output.display());
+
+        let file = std::fs::File::open(zip_path)?;
+        let mut archive = zip::ZipArchive::new(std::io::BufReader::new(file))?;
+        // 条目按规范化的路径排序和命名，读取时仍使用原始条目名
+        let mut entry_names = HashMap::new();
+        let mut entries: Vec<PathBuf> = archive
+            .file_names()
+            .filter(|name| !name.ends_with(['/', '\\']))
+            .filter_map(|name| {
+                let path = archive_entry_path(name);
+                is_image_file(&path).then(|| {
+                    entry_names.insert(path.clone(), name.to_string());
+                    path
+                })
+            })
+            .collect();
+
+        if entries.is_empty() {
+            return Err(PdfError::NoImagesFound(zip_path.display().to_string()));
         }
+        self.sort_images(&mut entries, |name, key| {
+            let mut entry = archive.by_name(&entry_names[name])?;
+            Ok(match key {
+                SortKey::FileSize => entry.size(),
+                SortKey::PixelArea => {
+                    let mut bytes = Vec::with_capacity(entry.size() as usize);
+                    std::io::Read::read_to_end(&mut entry, &mut bytes)?;
+                    let (width, height) = image_dimensions_in(bytes)?;
+                    width as u64 * height as u64
+                }
+            })
+        })?;
+        self.check_orientation(&entries, |name| {
+            let mut entry = archive.by_name(&entry_names[name])?;
+            let mut bytes = Vec::with_capacity(entry.size() as usize);
+            std::io::Read::read_to_end(&mut entry, &mut bytes)?;
+            let orientation = self.orientation_of(&bytes);
+            image_dimensions_in(bytes).map(|size| Some(upright_size(size, orientation)))
+        })?;
+        log::info!("📸 找到 {} 张图片，开始生成PDF", entries.len());
 
-        Ok(image_files)
-    }    /// Add an image to PDF page with automatic fitting
-    fn add_image_to_pdf_layer(&self, current_layer: &PdfLayerReference, image_path: &Path) -> Result<()> {        // 读取并处理图片
-        let img = ::image::open(image_path)?;
-        let (img_width, img_height) = img.dimensions();
-        
-        // 转换为RGB8格式
-        let rgb_img = img.to_rgb8();
-        let raw_data = rgb_img.as_raw().clone();
-        
-        // 计算缩放和位置（居中显示，适配页面）
-        let available_width_mm = self.config.page_width_mm - (2.0 * self.config.margin_mm);
-        let available_height_mm = self.config.page_height_mm - (2.0 * self.config.margin_mm);
-        
-        // 根据DPI进行转换
-        let pixel_to_mm = 25.4 / self.config.dpi;
-        let img_width_mm = img_width as f32 * pixel_to_mm;
-        let img_height_mm = img_height as f32 * pixel_to_mm;
-        
-        let scale_x = available_width_mm / img_width_mm;
-        let scale_y = available_height_mm / img_height_mm;
-        let scale = scale_x.min(scale_y);        let display_width_mm = img_width_mm * scale;
-        let display_height_mm = img_height_mm * scale;        let x_mm = self.config.margin_mm + (available_width_mm - display_width_mm) / 2.0;
-        // PDF坐标系统：(0,0)在左下角，Y轴向上为正
-        // 计算正确的Y坐标 - 从页面底部开始向上
-        let y_mm = self.config.margin_mm + (available_height_mm - display_height_mm) / 2.0;// 调试信息
-        println!("  📊 图片原始尺寸: {}x{} px", img_width, img_height);
-        println!("  📏 转换为mm: {:.1}x{:.1} mm", img_width_mm, img_height_mm);
-        println!("  📐 可用空间: {:.1}x{:.1} mm", available_width_mm, available_height_mm);
-        println!("  🔍 缩放比例: {:.3}", scale);
-        println!("  📍 显示尺寸: {:.1}x{:.1} mm", display_width_mm, display_height_mm);
-        println!("  🎯 位置: ({:.1}, {:.1}) mm", x_mm, y_mm);        // 创建图片对象
-        let image_file = printpdf::Image::try_from(printpdf::ImageXObject {
-            width: printpdf::Px(img_width as usize),
-            height: printpdf::Px(img_height as usize),
-            color_space: printpdf::ColorSpace::Rgb,
-            bits_per_component: printpdf::ColorBits::Bit8,
-            interpolate: true,
-            image_data: raw_data,
-            image_filter: None,
-            clipping_bbox: None,
-            smask: None,
-        }).unwrap(); // This should never fail with valid inputs        // 添加图片到PDF - 恢复正确的缩放计算
-        image_file.add_to_layer(
-            current_layer.clone(),
-            ImageTransform {
-                translate_x: Some(printpdf::Mm(x_mm)),
-                translate_y: Some(printpdf::Mm(y_mm)),
-                scale_x: Some(scale),
-                scale_y: Some(scale),
-                rotate: None,
-                dpi: Some(self.config.dpi),
-            },
-        );
+        let page_size = self.config.page_size();
+        let load = |name: &Path| -> Result<Vec<u8>> {
+            let mut entry = archive.by_name(&entry_names[name])?;
+            let mut bytes = Vec::with_capacity(entry.size() as usize);
+            std::io::Read::read_to_end(&mut entry, &mut bytes)?;
+            Ok(bytes)
+        };
+        let grouped = self.grouped_for(self.file_page_count(entries.len()), &[])?;
+        let converter = grouped.as_ref().unwrap_or(self);
+        converter.build_pdf(converter.prepare_files(&entries, load, |_| page_size), converter.file_page_count(entries.len()), &[], output)?;
+        Ok(())
+    }
 
-        log::debug!("  图片添加成功: {}x{} -> {:.1}x{:.1}mm @ ({:.1}, {:.1})mm", 
-            img_width, img_height, display_width_mm, display_height_mm, x_mm, y_mm);
+    /// Convert images produced on the fly, such as video frames, to PDF
+    /// 
+    /// Pages are added as items arrive, so the images never have to be
+    /// materialized up front. Each image gets the configured processing and
+    /// one page of the configured size; an `Err` item aborts the conversion.
+    /// The document keeps every embedded image until it is saved, so combine
+    /// this with `low_memory` or `max_dpi` to keep memory bounded. Bookmarks
+    /// are titled `Image 1`, `Image 2`, ... and `generate_toc` is not
+    /// supported since the page count is unknown in advance.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `images` - Decoded images in page order
+    /// * `output_path` - Path where the PDF will be saved
+    /// 
+    /// # Example
+    /// 
+    /// ```rust,no_run
+    /// use pdf_converter::PdfConverter;
+    /// 
+    /// let frames = (0..100u32).map(|i| {
+    ///     let frame = image::RgbImage::from_pixel(640, 480, image::Rgb([i as u8, 0, 0]));
+    ///     Ok(image::DynamicImage::ImageRgb8(frame))
+    /// });
+    /// 
+    /// let converter = PdfConverter::new();
+    /// converter.convert_image_iter_to_pdf(frames, "frames.pdf")?;
+    /// # Ok::<(), pdf_converter::PdfError>(())
+    /// ```
+    pub fn convert_image_iter_to_pdf<I, P>(&self, images: I, output_path: P) -> Result<()>
+    where
+        I: IntoIterator<Item = Result<::image::DynamicImage>>,
+        P: AsRef<Path>,
+    {
+        let page = self.config.page_size();
+        let prepared = images.into_iter().enumerate().map(|(index, img)| {
+            let (img, placement) = self.process_image(img?, None, None, page);
+            Ok(PreparedImage {
+                path: PathBuf::from(format!("Image {}", index + 1)),
+                object: self.raw_image_object(&img),
+                placement,
+                mask: None,
+                transform: None,
+                location: None,
+            })
+        });
+
+        self.build_pdf(prepared, None, &[], output_path.as_ref())?;
+        Ok(())
+    }
+
+    /// Convert images read from a stream of length-prefixed blobs to PDF
+    /// 
+    /// Each blob is the contents of an image file preceded by its length as a
+    /// 4-byte big-endian unsigned integer. Blobs are decoded as they arrive
+    /// and get the same processing as image files, including JPEG
+    /// passthrough and animation frames, one page each. The stream ends
+    /// cleanly at end of input between blobs; input ending inside a blob is
+    /// reported as an IO error. Bookmarks are titled `Image 1`, `Image 2`,
+    /// ... and `generate_toc` is not supported since the page count is
+    /// unknown in advance.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `reader` - Source of the blobs, e.g. standard input
+    /// * `output_path` - Path where the PDF will be saved
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use pdf_converter::PdfConverter;
+    /// use std::io::Cursor;
+    /// 
+    /// let mut stream = Vec::new();
+    /// for shade in [0u8, 128, 255] {
+    ///     let mut png = Vec::new();
+    ///     let img = image::GrayImage::from_pixel(40, 30, image::Luma([shade]));
+    ///     img.write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)?;
+    ///     stream.extend_from_slice(&(png.len() as u32).to_be_bytes());
+    ///     stream.extend_from_slice(&png);
+    /// }
+    /// 
+    /// # let dir = tempfile::tempdir()?;
+    /// let output = dir.path().join("stream.pdf");
+    /// PdfConverter::new().convert_image_stream_to_pdf(stream.as_slice(), &output)?;
+    /// assert_eq!(printpdf::lopdf::Document::load(&output)?.get_pages().len(), 3);
+    /// 
+    /// // Input ending inside a blob is an error
+    /// let truncated = &stream[..stream.len() - 1];
+    /// assert!(PdfConverter::new().convert_image_stream_to_pdf(truncated, &output).is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn convert_image_stream_to_pdf<R: Read, P: AsRef<Path>>(&self, mut reader: R, output_path: P) -> Result<()> {
+        let output = output_path.as_ref();
+        log::info!("📄 从数据流生成PDF: {}", output.display());
+
+        let page = self.config.page_size();
+        let mut index = 0;
+        // 数据流读取错误不属于某张图片，不附加图片信息
+        let prepared = std::iter::from_fn(|| {
+            let blob = read_blob(&mut reader).transpose()?;
+            index += 1;
+            let path = PathBuf::from(format!("Image {index}"));
+            Some(blob.and_then(|bytes| self.prepare_file(&path, &bytes, page).map_err(|e| e.at_image(&path, index - 1))))
+        })
+        .flat_map(|prepared| match prepared {
+            Ok(images) => images.into_iter().map(Ok).collect(),
+            Err(e) => vec![Err(e)],
+        });
 
+        self.build_pdf(prepared, None, &[], output)?;
         Ok(())
     }
+
+    /// Start a PDF that images are added to one at a time
+    /// 
+    /// Useful when the images are not known up front, e.g. while a user picks
+    /// them in an interface. Images get the same processing and placement as
+    /// with `convert_images_to_pdf`.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use pdf_converter::PdfConverter;
+    /// 
+    /// # let dir = tempfile::tempdir()?;
+    /// # for name in ["front.png", "back.png"] {
+    /// #     image::RgbImage::new(40, 30).save(dir.path().join(name)).unwrap();
+    /// # }
+    /// let converter = PdfConverter::new();
+    /// let mut builder = converter.document_builder();
+    /// builder.add_image(dir.path().join("front.png"))?;
+    /// assert!(builder.add_image(dir.path().join("missing.png")).is_err());
+    /// builder.add_image(dir.path().join("back.png"))?;
+    /// 
+    /// let report = builder.finish(dir.path().join("card.pdf"))?;
+    /// assert_eq!(report.page_count, 2);
+    /// # Ok::<(), pdf_converter::PdfError>(())
+    /// ```
+    pub fn document_builder(&self) -> DocumentBuilder<'_> {
+        DocumentBuilder {
+            converter: self,
+            images: Vec::new(),
+            files: 0,
+        }
+    }
+
+    /// Convert already decoded images to a PDF without file IO or re-encoding
+    /// 
+    /// Each image gets the configured processing and one page of the
+    /// configured size. Images that need no trimming, deskewing or
+    /// downscaling are embedded straight from the slice; the others are
+    /// copied before processing. Bookmarks and contents entries are titled
+    /// `Image 1`, `Image 2`, ...
+    /// 
+    /// # Arguments
+    /// 
+    /// * `images` - Decoded images in page order
+    /// * `output_path` - Path where the PDF will be saved
+    pub fn convert_dynamic_images_to_pdf<P: AsRef<Path>>(&self, images: &[::image::DynamicImage], output_path: P) -> Result<()> {
+        let grouped = self.grouped_for(Some(images.len()), &[])?;
+        let converter = grouped.as_ref().unwrap_or(self);
+        let page = converter.config.page_size();
+        let prepared = images.iter().enumerate().map(|(index, img)| {
+            let (object, placement) = converter.decoded_image_object(img, page);
+            Ok(PreparedImage {
+                path: PathBuf::from(format!("Image {}", index + 1)),
+                object,
+                placement,
+                mask: None,
+                transform: None,
+                location: None,
+            })
+        });
+
+        converter.build_pdf(prepared, Some(images.len()), &[], output_path.as_ref())?;
+        Ok(())
+    }
+
+    /// Read image files with `load` and prepare them lazily for `build_pdf`
+    fn prepare_files<'a, L, F>(&'a self, image_paths: &'a [PathBuf], mut load: L, page_size_for: F) -> impl Iterator<Item = Result<PreparedImage>> + 'a
+    where
+        L: FnMut(&Path) -> Result<Vec<u8>> + 'a,
+        F: Fn(usize) -> PageSize + 'a,
+    {
+        image_paths.iter().enumerate().flat_map(move |(index, image_path)| {
+            let prepared = self
+                .read_with_retry(|| load(image_path).and_then(|bytes| self.prepare_file(image_path, &bytes, page_size_for(index))))
+                .map_err(|e| e.at_image(image_path, index));
+            match prepared {
+                Ok(images) => images.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            }
+        })
+    }
+
+    /// Prepare the page images of one file, one per sampled frame for animations
+    fn prepare_file(&self, image_path: &Path, bytes: &[u8], page: PageSize) -> Result<Vec<PreparedImage>> {
+        if self.config.validate_image_magic {
+            check_image_magic(bytes).map_err(|reason| PdfError::NotAnImage { path: image_path.display().to_string(), reason })?;
+        }
+        // 位置和方向从原文件读取，缩略图不带EXIF数据
+        let location = self.config.gps_metadata.then(|| jpeg::exif_location(bytes)).flatten();
+        let orientation = self.orientation_of(bytes);
+        let bytes = match self.config.use_embedded_thumbnail.then(|| jpeg::exif_thumbnail(bytes)).flatten() {
+            Some(thumbnail) => {
+                log::debug!("  使用EXIF缩略图: {}", image_path.display());
+                thumbnail
+            }
+            None => bytes,
+        };
+        let mask = self.load_mask(image_path)?;
+        let Some(frames) = self.animation_frames(bytes)? else {
+            let (object, placement) = self.image_object(image_path, bytes, orientation, page)?;
+            let mask = mask.map(|mask| self.sized_mask(&mask, &object));
+            return Ok(vec![PreparedImage { path: image_path.to_path_buf(), object, placement, mask, transform: None, location }]);
+        };
+
+        log::debug!("  动画展开为 {} 页: {}", frames.len(), image_path.display());
+        Ok(frames
+            .into_iter()
+            .map(|frame| {
+                let (img, placement) = self.process_image(frame, Some(image_path), None, page);
+                let object = self.raw_image_object(&img);
+                PreparedImage {
+                    path: image_path.to_path_buf(),
+                    mask: mask.as_ref().map(|mask| self.sized_mask(mask, &object)),
+                    object,
+                    placement,
+                    transform: None,
+                    location,
+                }
+            })
+            .collect())
+    }
+
+    /// Convert images in chunks saved to the checkpoint directory and merge them into `output`
+    ///
+    /// Chunks recorded in the manifest with the same files are reused. The
+    /// report lists the images converted in this run only.
+    fn build_checkpointed(&self, checkpoint: &Checkpoint, image_files: &[PathBuf], pdf_files: &[(usize, PathBuf)], output: &Path) -> Result<ConversionReport> {
+        let started = Instant::now();
+        if image_files.is_empty() {
+            return Err(PdfError::Custom("No images provided".to_string()));
+        }
+        std::fs::create_dir_all(&checkpoint.dir)?;
+
+        // 网格模式下每块取整页，避免块末页面留空
+        let cells = self.active_grid().map_or(1, Grid::cells);
+        let chunk_len = checkpoint.images_per_chunk.max(1).div_ceil(cells) * cells;
+        let chunks: Vec<Chunk> = image_files
+            .chunks(chunk_len)
+            .enumerate()
+            .map(|(index, images)| {
+                let start = index * chunk_len;
+                // PDF文件归入其前一张图片所在的块，位于开头的归入第一块
+                let pdf_files = pdf_files
+                    .iter()
+                    .filter(|(images_before, _)| (*images_before > start || index == 0) && *images_before <= start + images.len())
+                    .map(|(images_before, path)| (images_before - start, path.clone()))
+                    .collect();
+                Chunk { start, images, pdf_files }
+            })
+            .collect();
+        let chunk_path = |index: usize| checkpoint.dir.join(format!("chunk-{:05}.pdf", index));
+
+        // 清单每行记录一个已完成块中的文件：块序号<TAB>路径
+        let manifest_path = checkpoint.dir.join("manifest.txt");
+        let mut recorded: BTreeMap<usize, Vec<PathBuf>> = BTreeMap::new();
+        if let Ok(manifest) = std::fs::read_to_string(&manifest_path) {
+            for line in manifest.lines() {
+                if let Some((index, path)) = line.split_once('\t').and_then(|(index, path)| Some((index.parse().ok()?, path))) {
+                    recorded.entry(index).or_default().push(PathBuf::from(path));
+                }
+            }
+        }
+        let resume_from = (0..chunks.len())
+            .find(|&index| recorded.get(&index) != Some(&chunks[index].files()) || !chunk_path(index).is_file())
+            .unwrap_or(chunks.len());
+        if resume_from > 0 {
+            log::info!("♻️ 从检查点恢复: 跳过 {}/{} 个已完成的块", resume_from, chunks.len());
+        }
+
+        // 只保留仍然有效的块，之后的块重新生成
+        let mut manifest = std::fs::File::create(&manifest_path)?;
+        for (index, chunk) in chunks.iter().enumerate().take(resume_from) {
+            for path in chunk.files() {
+                writeln!(manifest, "{}\t{}", index, path.display())?;
+            }
+        }
+        manifest.sync_all()?;
+
+        let chunk_config = part_config(&self.config);
+        let page_size = self.config.page_size();
+        let mut reports = Vec::new();
+        for (index, chunk) in chunks.iter().enumerate().skip(resume_from) {
+            log::info!("🧩 生成第 {}/{} 块", index + 1, chunks.len());
+            // 叠加图片只画在第一块的首页
+            let mut chunk_config = chunk_config.clone();
+            if index > 0 {
+                chunk_config.first_page_overlay = None;
+            }
+            let mut chunk_converter = PdfConverter::with_config(chunk_config);
+            chunk_converter.image_processor = self.image_processor.clone();
+            // 进度按整个文件夹计数
+            if let Some(progress) = self.progress.clone() {
+                let (start, total) = (chunk.start, self.file_page_count(image_files.len()));
+                chunk_converter = chunk_converter.with_progress(move |chunk_progress| {
+                    progress(&Progress {
+                        index: start + chunk_progress.index,
+                        total,
+                        image: chunk_progress.image,
+                        elapsed: started.elapsed(),
+                    })
+                });
+            }
+            let prepared = chunk_converter.prepare_files(chunk.images, |path| Ok(std::fs::read(path)?), |_| page_size);
+            let report = chunk_converter
+                .build_pdf(prepared, chunk_converter.file_page_count(chunk.images.len()), &chunk.pdf_files, &chunk_path(index))
+                // 块内的图片序号换算为整个文件夹中的序号
+                .map_err(|e| match e {
+                    PdfError::ImageAt { path, index, source } => PdfError::ImageAt { path, index: chunk.start + index, source },
+                    e => e,
+                })?;
+            reports.extend(report.images);
+
+            for path in chunk.files() {
+                writeln!(manifest, "{}\t{}", index, path.display())?;
+            }
+            manifest.sync_all()?;
+        }
+
+        let parts: Vec<PathBuf> = (0..chunks.len()).map(chunk_path).collect();
+        let (output_bytes, page_count) = self.merge_parts(&parts, output)?;
+        Ok(ConversionReport {
+            output: output.to_path_buf(),
+            output_bytes,
+            page_count,
+            low_resolution: self.low_resolution(&reports),
+            images: reports,
+            skipped: Vec::new(),
+            elapsed: started.elapsed(),
+        })
+    }
+
+    /// Merge PDFs converted with `part_config` into `output`, finishing the document as a whole
+    ///
+    /// Returns the size of the written file and its page count.
+    fn merge_parts(&self, parts: &[PathBuf], output: &Path) -> Result<(u64, usize)> {
+        // 以第一部分为基础，依次追加其余部分的页面
+        let mut document = printpdf::lopdf::Document::load(&parts[0])?;
+        let base_pages = document.get_pages().len();
+        let cover = self.config.cover_pdf.iter().map(|path| PdfInsert::load(0, path));
+        let inserts = cover.chain(parts[1..].iter().map(|path| PdfInsert::load(base_pages, path))).collect::<Result<Vec<_>>>()?;
+        self.finish_document(&mut document, &[], inserts)?;
+        let page_count = document.get_pages().len();
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes)?;
+        let retry = self.config.save_retry.unwrap_or(RetryPolicy { attempts: 1, backoff: Duration::ZERO });
+        self.write_output(output, |target| write_with_retry(target, &bytes, retry, self.config.sync_output))?;
+
+        log::info!("✅ PDF生成完成: {}", output.display());
+        #[cfg(feature = "open")]
+        if self.config.open_after {
+            if let Err(e) = open::that(output) {
+                log::warn!("⚠️ 无法打开PDF查看器: {}", e);
+            }
+        }
+        Ok((bytes.len() as u64, page_count))
+    }
+
+    /// Images of a report below `min_effective_dpi`
+    fn low_resolution(&self, images: &[ImageReport]) -> Vec<PathBuf> {
+        let Some(min_dpi) = self.config.min_effective_dpi else { return Vec::new() };
+        images.iter().filter(|image| image.effective_dpi < min_dpi).map(|image| image.path.clone()).collect()
+    }
+
+    /// Converter placing `image_count` images in a grid that fits them into `target_pages`
+    ///
+    /// The pages left for images are the target minus `extra_pages`. `None`
+    /// when no grouping is needed: without a target or a known count, or when
+    /// the images fit as they are. Fails with `PdfError::Config` when they
+    /// cannot fit, because no pages are left, a configured grid holds too few
+    /// images or the mode uses no grid.
+    fn grouped_for(&self, image_count: Option<usize>, pdf_files: &[(usize, PathBuf)]) -> Result<Option<PdfConverter>> {
+        let (Some(target), Some(count)) = (self.config.target_pages, image_count) else { return Ok(None) };
+        // 目录、说明和PDF页面数量固定，剩余页数留给图片
+        let extra = self.extra_pages(Some(count), pdf_files)?;
+        let available = target.saturating_sub(extra);
+        if count.div_ceil(self.images_per_page()) <= available {
+            return Ok(None);
+        }
+
+        let grouped = (available > 0 && self.active_grid().is_none()).then(|| {
+            let mut config = self.config.clone();
+            config.grid = Some(target_grid(count.div_ceil(available), config.page_size()));
+            self.derived(config)
+        });
+        match grouped.as_ref().and_then(PdfConverter::active_grid) {
+            Some(grid) => {
+                log::info!("🧮 {} 张图片排入 {} 页，每页 {}x{}", count, available, grid.columns, grid.rows);
+                Ok(grouped)
+            }
+            None => {
+                log::warn!("⚠️ {} 张图片和 {} 个其他页面无法排入 {} 页", count, extra, target);
+                Err(PdfError::Config { name: "target_pages".to_string(), value: target.to_string() })
+            }
+        }
+    }
+
+    /// Number of pages besides the image pages: contents, notes, `cover_pdf` and `pdf_files`
+    ///
+    /// The contents pages depend on `image_count` and are left out when it is unknown.
+    fn extra_pages(&self, image_count: Option<usize>, pdf_files: &[(usize, PathBuf)]) -> Result<usize> {
+        let page = self.config.page_size();
+        let toc_pages = match image_count {
+            Some(count) if self.config.generate_toc => {
+                toc::pages_needed(count, page, self.config.effective_margin_mm(), self.config.notes_font_size)
+            }
+            _ => 0,
+        };
+        let mut notes_pages = 0;
+        if !self.config.notes.is_empty() {
+            // 文字分页取决于字体宽度，字体加载到临时文档中测量
+            let font = TextFont::load(&PdfDocument::empty(""), self.config.font.as_deref())?;
+            for note in &self.config.notes {
+                notes_pages += text::text_pages_needed(&font, note, page, self.config.effective_margin_mm(), self.config.notes_font_size);
+            }
+        }
+        let mut pdf_pages = match &self.config.cover_pdf {
+            Some(cover) => PdfInsert::load(0, cover)?.page_count(),
+            None => 0,
+        };
+        for (_, path) in pdf_files {
+            pdf_pages += self.pages_for_image(path)?;
+        }
+        Ok(toc_pages + notes_pages + pdf_pages)
+    }
+
+    /// Converter with `config` sharing the hooks of this one
+    fn derived(&self, config: PdfConfig) -> PdfConverter {
+        PdfConverter {
+            config,
+            name_transform: self.name_transform.clone(),
+            progress: self.progress.clone(),
+            image_processor: self.image_processor.clone(),
+            outline_root: self.outline_root.clone(),
+        }
+    }
+
+    /// Number of images to expect from `file_count` files, unknown when animations may add pages
+    fn file_page_count(&self, file_count: usize) -> Option<usize> {
+        match self.config.animation_frames {
+            Some(_) => None,
+            None => Some(file_count),
+        }
+    }
+
+    /// Build and save a PDF from prepared images
+    ///
+    /// In strip mode all images are prepared up front to size the single
+    /// image page; in grid mode each image is moved into its cell as it
+    /// arrives. See `build_pages` for the rest.
+    fn build_pdf<I>(&self, images: I, image_count: Option<usize>, pdf_files: &[(usize, PathBuf)], output: &Path) -> Result<ConversionReport>
+    where
+        I: Iterator<Item = Result<PreparedImage>>,
+    {
+        if let Some(strip) = self.config.strip_mode {
+            let mut images = images.collect::<Result<Vec<_>>>()?;
+            if let Some(overlap) = strip.overlap {
+                remove_strip_overlaps(&mut images, overlap);
+            }
+            stack_strip(&mut images, self.config.effective_margin_mm(), strip.gap_mm);
+            for image in &mut images {
+                fit_page_limit(&mut image.placement);
+            }
+            let count = images.len();
+            return self.build_pages(images.into_iter().map(Ok), Some(count), pdf_files, output);
+        }
+        if self.config.uniform_page_size {
+            let mut images = images.collect::<Result<Vec<_>>>()?;
+            for image in &mut images {
+                fit_page_limit(&mut image.placement);
+            }
+            pad_to_largest_page(&mut images);
+            let count = images.len();
+            return match self.active_grid() {
+                Some(grid) => {
+                    for (index, image) in images.iter_mut().enumerate() {
+                        self.place_in_cell(&mut image.placement, grid, index % grid.cells());
+                    }
+                    self.build_pages(images.into_iter().map(Ok), Some(count), pdf_files, output)
+                }
+                None => self.build_pages(images.into_iter().map(Ok), Some(count), pdf_files, output),
+            };
+        }
+
+        let images = images.map(|image| {
+            image.map(|mut image| {
+                fit_page_limit(&mut image.placement);
+                image
+            })
+        });
+        match self.active_grid() {
+            Some(grid) => {
+                let images = images.enumerate().map(|(index, image)| {
+                    image.map(|mut image| {
+                        self.place_in_cell(&mut image.placement, grid, index % grid.cells());
+                        image
+                    })
+                });
+                self.build_pages(images, image_count, pdf_files, output)
+            }
+            None => self.build_pages(images, image_count, pdf_files, output),
+        }
+    }
+
+    /// Grid in effect: the `n_up` or configured one unless strip, native DPI or fit-width mode takes precedence
+    fn active_grid(&self) -> Option<Grid> {
+        if let Some(grid) = self.config.n_up_grid() {
+            return Some(grid);
+        }
+        match self.config.grid {
+            Some(grid) if self.config.strip_mode.is_none() && self.config.native_dpi.is_none() && self.config.scale_mode != ScaleMode::FitWidth => Some(grid),
+            _ => None,
+        }
+    }
+
+    /// Number of consecutive images sharing a page
+    fn images_per_page(&self) -> usize {
+        if self.config.strip_mode.is_some() {
+            return usize::MAX;
+        }
+        self.active_grid().map_or(1, |grid| grid.cells())
+    }
+
+    /// Size of one grid cell on `page`: the area inside the margins minus the gutters, split evenly
+    fn grid_cell_size(&self, page: PageSize, grid: Grid) -> (f32, f32) {
+        let gutter_mm = self.config.grid_gutter_mm.max(0.0);
+        let (columns, rows) = (grid.columns.max(1) as f32, grid.rows.max(1) as f32);
+        let width_mm = (page.width_mm - 2.0 * self.config.effective_margin_mm() - gutter_mm * (columns - 1.0)) / columns;
+        let height_mm = (page.height_mm - 2.0 * self.config.effective_margin_mm() - gutter_mm * (rows - 1.0)) / rows;
+        (width_mm, height_mm)
+    }
+
+    /// Move a placement computed for a single cell into cell `cell`, counted row by row
+    fn place_in_cell(&self, placement: &mut Placement, grid: Grid, cell: usize) {
+        let (cell_width_mm, cell_height_mm) = self.grid_cell_size(placement.page, grid);
+        let gutter_mm = self.config.grid_gutter_mm.max(0.0);
+        let columns = grid.columns.max(1);
+        let (column, row) = ((cell % columns) as f32, (cell / columns) as f32);
+
+        // 行从页面顶部开始排列，PDF坐标Y轴向上
+        let left_mm = self.config.effective_margin_mm() + column * (cell_width_mm + gutter_mm);
+        let top_mm = placement.page.height_mm - self.config.effective_margin_mm() - row * (cell_height_mm + gutter_mm);
+        placement.x_mm = left_mm + (cell_width_mm - placement.width_mm) / 2.0;
+        placement.y_mm = top_mm - cell_height_mm + (cell_height_mm - placement.height_mm) / 2.0;
+    }
+
+    /// Build and save a PDF with one page per image, preparing images as they are needed
+    ///
+    /// `image_count` is the number of images if known in advance; the table of
+    /// contents needs it to reserve its pages and is skipped without it. In
+    /// strip mode every image goes onto the page created for the first one.
+    /// `pdf_files` pairs PDF files whose pages are inserted with the number of
+    /// images before them.
+    fn build_pages<I>(&self, mut images: I, image_count: Option<usize>, pdf_files: &[(usize, PathBuf)], output: &Path) -> Result<ConversionReport>
+    where
+        I: Iterator<Item = Result<PreparedImage>>,
+    {
+        if image_count == Some(0) {
+            return Err(PdfError::Custom("No images provided".to_string()));
+        }
+        if let Some(level) = self.config.pdf_x {
+            pdfx::check_config(&self.config, level)?;
+        }
+        if !matches!(self.config.n_up, 1 | 2 | 4 | 6 | 9) {
+            return Err(PdfError::Config { name: "n_up".to_string(), value: self.config.n_up.to_string() });
+        }
+
+        // 目录页预留在最前面，内容在排版完成后填写
+        let toc_page_count = match image_count {
+            Some(count) if self.config.generate_toc => {
+                toc::pages_needed(count, self.config.page_size(), self.config.effective_margin_mm(), self.config.notes_font_size)
+            }
+            None if self.config.generate_toc => {
+                log::warn!("⚠️ 图片数量未知，跳过目录页");
+                0
+            }
+            _ => 0,
+        };
+
+        // 页面尺寸可能取决于图片（原生DPI模式），先准备第一张图片
+        let started = Instant::now();
+        let first = images
+            .next()
+            .ok_or_else(|| PdfError::Custom("No images provided".to_string()))??;
+        let first_page = if toc_page_count > 0 { self.config.page_size() } else { first.placement.page };
+        let mut first_image = Some((first, started.elapsed()));
+
+        // 创建PDF文档
+        let (doc, page1, layer1) = printpdf::PdfDocument::new(
+            &self.config.title,
+            printpdf::Mm(first_page.width_mm),
+            printpdf::Mm(first_page.height_mm),
+            "Layer 1"
+        );
+        let doc = match self.config.pdf_x {
+            Some(level) => doc.with_conformance(level.conformance()).with_trapping(false),
+            None => doc,
+        };
+        let mut first_layer = Some((page1, layer1));
+        let mut new_page = |page: PageSize| {
+            first_layer.take().unwrap_or_else(|| doc.add_page(
+                printpdf::Mm(page.width_mm),
+                printpdf::Mm(page.height_mm),
+                "Layer 1"
+            ))
+        };
+
+        let mut pages = Vec::new();
+        let mut reports = Vec::with_capacity(image_count.unwrap_or(0));
+        let mut toc_layers = Vec::new();
+        for _ in 0..toc_page_count {
+            let (page_index, layer_index) = new_page(self.config.page_size());
+            pages.push((page_index, self.config.page_size()));
+            toc_layers.push(doc.get_page(page_index).get_layer(layer_index));
+        }
+
+        // 处理图片（每页一张，网格模式每页多张，长条模式共用一页）
+        let images_per_page = self.images_per_page();
+        let mut current_page = None;
+        let mut image_pages = Vec::new();
+        let mut image_locations = Vec::new();
+        let mut masks = Vec::new();
+        loop {
+            let image_started = Instant::now();
+            let (image, prepare_time) = match first_image.take() {
+                Some(prepared) => prepared,
+                None => match images.next() {
+                    Some(image) => (image?, Duration::ZERO),
+                    None => break,
+                },
+            };
+            let PreparedImage { path: image_path, object: mut image_object, placement, mask, transform, location } = image;
+            if let Some(mask) = mask {
+                image_object.clipping_bbox = Some(smask::tag(masks.len()));
+                masks.push(mask);
+            }
+            let pixels = (image_object.width.0 as u32, image_object.height.0 as u32);
+
+            let total = image_count.map_or_else(|| "?".to_string(), |count| count.to_string());
+            log::info!("  处理第 {}/{} 张图片: {}", reports.len() + 1, total,
+                image_path.file_name().unwrap_or_default().to_string_lossy());
+
+            // 添加新页面
+            let (page_index, layer_index) = match current_page {
+                Some(layer) if reports.len() % images_per_page != 0 => layer,
+                _ => {
+                    let layer = new_page(placement.page);
+                    pages.push((layer.0, placement.page));
+                    current_page = Some(layer);
+                    layer
+                }
+            };
+            image_pages.push((page_index, image_path.clone()));
+            if let Some(location) = location {
+                image_locations.push((page_index, location));
+            }
+            if self.config.debug_images {
+                let position = (images_per_page > 1).then_some(reports.len() % images_per_page + 1);
+                save_debug_image(&image_object, output, pages.len(), position);
+            }
+            let current_layer = doc.get_page(page_index).get_layer(layer_index);
+            self.add_image_to_pdf_layer(&current_layer, image_object, &placement, transform);
+            if self.config.bookmarks && !self.config.nested_bookmarks {
+                doc.add_bookmark(self.page_title(&image_path), page_index);
+            }
+            // 有效分辨率取两个方向中较低者
+            let effective_dpi = (pixels.0 as f32 * 25.4 / placement.width_mm).min(pixels.1 as f32 * 25.4 / placement.height_mm);
+            if self.config.min_effective_dpi.is_some_and(|min_dpi| effective_dpi < min_dpi) {
+                log::warn!("⚠️ 图片分辨率过低（{:.0} DPI）: {}", effective_dpi, image_path.display());
+            }
+            reports.push(ImageReport {
+                path: image_path,
+                pixels,
+                effective_dpi,
+                page: placement.page,
+                elapsed: prepare_time + image_started.elapsed(),
+            });
+            if let (Some(progress), Some(image)) = (&self.progress, reports.last()) {
+                progress(&Progress {
+                    index: reports.len() - 1,
+                    total: image_count,
+                    image,
+                    elapsed: started.elapsed(),
+                });
+            }
+        }
+
+        // 追加文字备注页，填写目录并添加页眉页脚
+        let has_running_text = self.config.header_text.is_some() || self.config.footer_text.is_some();
+        let mut toc_links = Vec::new();
+        if !self.config.notes.is_empty() || has_running_text || toc_page_count > 0 {
+            let font = TextFont::load(&doc, self.config.font.as_deref())?;
+            for note in &self.config.notes {
+                let page = self.config.page_size();
+                for page_index in text::add_text_pages(&doc, &font, note, page, self.config.effective_margin_mm(), self.config.notes_font_size)? {
+                    pages.push((page_index, page));
+                }
+            }
+            if toc_page_count > 0 {
+                let entries: Vec<_> = reports
+                    .iter()
+                    .enumerate()
+                    .map(|(index, image)| (self.page_title(&image.path), toc_page_count + index / images_per_page))
+                    .collect();
+                toc_links = toc::write_toc(&toc_layers, &font, &entries, self.config.page_size(), self.config.effective_margin_mm(), self.config.notes_font_size);
+            }
+            self.add_running_text(&doc, &font, &pages);
+        }
+        if let (Some(overlay), Some(&(page_index, page))) = (&self.config.first_page_overlay, pages.first()) {
+            self.add_overlay(&doc, overlay, page_index, page)?;
+        }
+        if self.config.show_margin_guides {
+            self.add_margin_guides(&doc, &pages);
+        }
+        if let (true, Some(grid)) = (self.config.n_up_cut_lines, self.config.n_up_grid()) {
+            self.add_cut_lines(&doc, &pages, &image_pages, grid);
+        }
+        #[cfg(feature = "qr")]
+        if let Some(stamp) = &self.config.qr_stamp {
+            self.add_qr_stamps(&doc, stamp, &pages, &image_pages)?;
+        }
+        let outline = match self.config.bookmarks && self.config.nested_bookmarks {
+            true => outline::folder_tree(self.outline_root.as_deref(), image_pages.iter().map(|(page_index, path)| {
+                let page = pages.iter().position(|(index, _)| index == page_index).unwrap_or_default();
+                (page, path.as_path(), self.page_title(path))
+            })),
+            false => Vec::new(),
+        };
+        let source_files = if self.config.source_metadata { image_pages.as_slice() } else { &[] };
+        extend_pages(&doc, &pages, self.config.crop_box, source_files, &image_locations);
+
+        // 动画的多帧来自同一文件，记录每个文件最后一帧的位置
+        let mut last_frames: Vec<usize> = Vec::new();
+        for (index, (_, path)) in image_pages.iter().enumerate() {
+            match last_frames.last_mut() {
+                Some(last) if image_pages[*last].1 == *path => *last = index,
+                _ => last_frames.push(index),
+            }
+        }
+
+        // 封面在最前，PDF页面插在其前一张图片所在页之后
+        let cover = self.config.cover_pdf.iter().map(|path| PdfInsert::load(0, path));
+        let pdf_inserts = cover
+            .chain(pdf_files.iter().map(|(images_before, path)| {
+                let previous_page = images_before.checked_sub(1).and_then(|last| last_frames.get(last)).map(|&frame| &image_pages[frame]);
+                let position = match previous_page {
+                    Some((page_index, _)) => pages.iter().position(|(index, _)| index == page_index).map_or(pages.len(), |page| page + 1),
+                    None => toc_page_count,
+                };
+                PdfInsert::load(position, path)
+            }))
+            .collect::<Result<Vec<_>>>()?;
+
+        let page_count = pages.len() + pdf_inserts.iter().map(PdfInsert::page_count).sum::<usize>();
+        // 图片数量事先未知时无法分组，超出目标页数只能在此发现
+        if let Some(target) = self.config.target_pages.filter(|&target| page_count > target) {
+            log::warn!("⚠️ 文档共 {} 页，超过目标页数 {}", page_count, target);
+            return Err(PdfError::Config { name: "target_pages".to_string(), value: target.to_string() });
+        }
+        let page_count = page_count.max(self.config.target_pages.unwrap_or(0));
+        self.save_document(doc, &toc_links, &outline, pdf_inserts, masks, output)?;
+        
+        log::info!("✅ PDF生成完成: {}", output.display());
+        #[cfg(feature = "open")]
+        if self.config.open_after {
+            if let Err(e) = open::that(output) {
+                log::warn!("⚠️ 无法打开PDF查看器: {}", e);
+            }
+        }
+        Ok(ConversionReport {
+            output: output.to_path_buf(),
+            output_bytes: std::fs::metadata(output)?.len(),
+            page_count,
+            low_resolution: self.low_resolution(&reports),
+            images: reports,
+            skipped: Vec::new(),
+            elapsed: started.elapsed(),
+        })
+    }
+
+    /// Decode every image in a folder and hand it to `visit` with its placement
+    /// 
+    /// Images are visited in the same order and with the same processing and
+    /// geometry as `convert_folder_to_pdf`, but no PDF is built, so the caller
+    /// decides what to do with each one.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `folder_path` - Path to folder containing images
+    /// * `visit` - Called with the image path, the decoded image and its placement
+    /// 
+    /// # Example
+    /// 
+    /// ```rust,no_run
+    /// use pdf_converter::PdfConverter;
+    /// 
+    /// let converter = PdfConverter::new();
+    /// converter.for_each_image("images/", |path, img, placement| {
+    ///     println!("{}: {}x{} px at ({:.1}, {:.1}) mm",
+    ///         path.display(), img.width(), img.height(), placement.x_mm, placement.y_mm);
+    /// })?;
+    /// # Ok::<(), pdf_converter::PdfError>(())
+    /// ```
+    pub fn for_each_image<P, F>(&self, folder_path: P, mut visit: F) -> Result<()>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Path, &::image::DynamicImage, &Placement),
+    {
+        let page = self.config.page_size();
+        for image_path in self.folder_images(folder_path.as_ref(), false)? {
+            let (img, placement) = self.prepare_image(&image_path, page)?;
+            visit(&image_path, &img, &placement);
+        }
+        Ok(())
+    }
+
+    /// Draw where the images of a folder land on their pages as an SVG diagram
+    /// 
+    /// Every page is shown side by side with its margins (dashed) and the
+    /// rectangle of its placed image, using the same processing and geometry
+    /// as `convert_folder_to_pdf`. This is a debugging aid for layout issues
+    /// that does not require building or opening the PDF. Only the image
+    /// headers are read unless a setting changes sizes or page counts
+    /// depending on the file contents: trimming, deskewing, an image
+    /// processor, embedded thumbnails or expanded animations.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust,no_run
+    /// use pdf_converter::PdfConverter;
+    /// 
+    /// let converter = PdfConverter::new();
+    /// std::fs::write("layout.svg", converter.render_layout_preview("images/")?)?;
+    /// # Ok::<(), pdf_converter::PdfError>(())
+    /// ```
+    /// 
+    /// Expanded animations show one page per sampled frame, as in the PDF:
+    /// 
+    /// ```rust
+    /// use pdf_converter::{FrameSampling, PdfConfig, PdfConverter};
+    /// 
+    /// # let dir = tempfile::tempdir()?;
+    /// let mut encoder = image::codecs::gif::GifEncoder::new(std::fs::File::create(dir.path().join("spinner.gif"))?);
+    /// for shade in [0u8, 80, 160] {
+    ///     encoder.encode_frame(image::Frame::new(image::RgbaImage::from_pixel(20, 20, image::Rgba([shade, shade, shade, 255]))))?;
+    /// }
+    /// drop(encoder);
+    /// 
+    /// let converter = PdfConverter::with_config(PdfConfig {
+    ///     animation_frames: Some(FrameSampling::All),
+    ///     ..PdfConfig::default()
+    /// });
+    /// let svg = converter.render_layout_preview(dir.path())?;
+    /// assert_eq!(svg.matches("<g ").count(), converter.count_pages(dir.path())?);
+    /// assert_eq!(svg.matches("<g ").count(), 3);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn render_layout_preview<P: AsRef<Path>>(&self, folder_path: P) -> Result<String> {
+        let mut pages = Vec::new();
+        let name = |path: &Path| path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        if self.placement_from_header() {
+            let page = self.config.page_size();
+            for path in self.folder_images(folder_path.as_ref(), false)? {
+                let (width, height) = self.planned_pixel_size(&path)?;
+                pages.push((name(&path), self.compute_placement(width, height, page)));
+            }
+        } else {
+            // 与转换相同地准备每个文件，包括缩略图和动画帧
+            let page = self.config.page_size();
+            for path in self.folder_images(folder_path.as_ref(), false)? {
+                for image in self.prepare_file(&path, &std::fs::read(&path)?, page)? {
+                    pages.push((name(&path), image.placement));
+                }
+            }
+        }
+        Ok(preview::layout_svg(&pages, self.config.effective_margin_mm()))
+    }
+
+    /// Whether placements can be planned from image headers, without decoding
+    ///
+    /// Trimming, deskewing and image processors change an image's size
+    /// depending on its pixels; embedded thumbnails have their own size, and
+    /// expanded animations take a page per sampled frame.
+    fn placement_from_header(&self) -> bool {
+        !self.config.auto_trim
+            && !self.config.deskew
+            && self.image_processor.is_none()
+            && !self.config.use_embedded_thumbnail
+            && self.config.animation_frames.is_none()
+    }
+
+    /// Pixel size of an image file after orientation, cropping and rotation, read from its header
+    fn planned_pixel_size(&self, image_path: &Path) -> Result<(u32, u32)> {
+        let (mut width, mut height) = self.upright_dimensions(image_path)?;
+        if let Some(crop) = self.crop_for(Some(image_path)) {
+            let ((_, _, crop_width, crop_height), _) = crop.pixel_rect(width, height);
+            (width, height) = (crop_width, crop_height);
+        }
+        if let Some(degrees) = self.rotation_for(image_path) {
+            (width, height) = processing::rotated_size(width, height, degrees);
+        }
+        Ok((width, height))
+    }
+
+    /// Placement of an image of the given pixel size on `page` with the current configuration
+    ///
+    /// Ignores trimming and downscaling, which only change the embedded
+    /// pixels. Outside native DPI mode the image is centered on the page:
+    ///
+    /// ```rust
+    /// use pdf_converter::{PageSize, PdfConverter};
+    ///
+    /// let converter = PdfConverter::new();
+    /// for (width, height) in [(3000, 1000), (1000, 3000)] {
+    ///     let p = converter.placement(width, height, PageSize::A4);
+    ///     let (left, right) = (p.x_mm, p.page.width_mm - p.x_mm - p.width_mm);
+    ///     let (bottom, top) = (p.y_mm, p.page.height_mm - p.y_mm - p.height_mm);
+    ///     assert!((left - right).abs() < 1e-3 && (bottom - top).abs() < 1e-3);
+    /// }
+    /// ```
+    pub fn placement(&self, img_width: u32, img_height: u32, page: PageSize) -> Placement {
+        self.compute_placement(img_width, img_height, page)
+    }
+
+    /// DPI at which the median image of a folder covers `target_coverage` of the available area
+    ///
+    /// Coverage is the image's area at the DPI, times the `FixedPercent` scale
+    /// if set, relative to the area between the margins (or of a grid cell) of
+    /// the configured page. Image sizes are read from the file headers. Use
+    /// the result with `ScaleMode::FixedPercent`, since `ScaleMode::Fit`
+    /// sizes every image to the page whatever the DPI. Images with a different
+    /// aspect ratio than the available area may overflow it in one direction
+    /// at high coverage.
+    ///
+    /// ```rust
+    /// use pdf_converter::{PdfConfig, PdfConverter, ScaleMode};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// for (name, width) in [("a.png", 1000), ("b.png", 1600), ("c.png", 3000)] {
+    ///     image::RgbImage::new(width, 1200).save(dir.path().join(name)).unwrap();
+    /// }
+    ///
+    /// let mut converter = PdfConverter::with_config(PdfConfig {
+    ///     scale_mode: ScaleMode::FixedPercent(100.0),
+    ///     ..PdfConfig::default()
+    /// });
+    /// let dpi = converter.suggest_dpi(dir.path(), 0.5)?;
+    ///
+    /// converter.set_config(PdfConfig { dpi, ..converter.config().clone() });
+    /// let p = converter.placement(1600, 1200, converter.config().page_size());
+    /// let available = (210.0 - 40.0) * (297.0 - 40.0);
+    /// assert!((p.width_mm * p.height_mm / available - 0.5).abs() < 1e-3);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn suggest_dpi<P: AsRef<Path>>(&self, folder_path: P, target_coverage: f32) -> Result<f32> {
+        if !(target_coverage > 0.0 && target_coverage.is_finite()) {
+            return Err(PdfError::Config {
+                name: "target_coverage".to_string(),
+                value: target_coverage.to_string(),
+            });
+        }
+
+        let mut areas = self
+            .folder_images(folder_path.as_ref(), false)?
+            .iter()
+            .map(|path| image_dimensions(path).map(|(width, height)| width as f32 * height as f32))
+            .collect::<Result<Vec<_>>>()?;
+        areas.sort_by(f32::total_cmp);
+        let median_area = areas[areas.len() / 2];
+
+        let page = self.config.page_size();
+        let (available_width_mm, available_height_mm) = match self.active_grid() {
+            Some(grid) => self.grid_cell_size(page, grid),
+            None => (page.width_mm - 2.0 * self.config.effective_margin_mm(), page.height_mm - 2.0 * self.config.effective_margin_mm()),
+        };
+        let available_area = available_width_mm.max(MIN_AVAILABLE_MM) * available_height_mm.max(MIN_AVAILABLE_MM);
+        let scale = match self.config.scale_mode {
+            ScaleMode::FixedPercent(percent) => percent / 100.0,
+            ScaleMode::Fit | ScaleMode::FitWidth => 1.0,
+        };
+
+        // 面积随DPI的平方反比变化
+        Ok(25.4 * scale * (median_area / (target_coverage * available_area)).sqrt())
+    }
+
+    /// Number of pages `convert_folder_to_pdf` would produce for a folder, without building the PDF
+    ///
+    /// Applies the same rules as the conversion: images per page in grid and
+    /// strip mode, expanded animation frames, the contents page, notes pages
+    /// and the pages of the cover and included PDF files. Only animated GIFs are decoded;
+    /// other images are not read at all. A previous output file in the folder
+    /// counts like any other PDF file, since the output path is not known here.
+    ///
+    /// ```rust
+    /// use pdf_converter::{Grid, PdfConfig, PdfConverter};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// # let folder = dir.path().join("images");
+    /// # std::fs::create_dir(&folder)?;
+    /// for index in 0..5 {
+    ///     image::RgbImage::new(40, 30).save(folder.join(format!("{index}.png")))?;
+    /// }
+    ///
+    /// let converter = PdfConverter::with_config(PdfConfig {
+    ///     grid: Some(Grid::new(2, 1)),
+    ///     generate_toc: true,
+    ///     notes: vec!["Scanned at the archive".to_string()],
+    ///     ..PdfConfig::default()
+    /// });
+    /// let report = converter.convert_folder_to_pdf_with_report(&folder, dir.path().join("out.pdf"))?;
+    /// assert_eq!(converter.count_pages(&folder)?, report.page_count);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn count_pages<P: AsRef<Path>>(&self, folder_path: P) -> Result<usize> {
+        let mut image_count = 0;
+        let mut pdf_files = Vec::new();
+        for path in self.folder_images(folder_path.as_ref(), self.config.include_pdf_files)? {
+            if is_pdf_file(&path) {
+                pdf_files.push((image_count, path));
+            } else {
+                image_count += self.pages_for_image(&path)?;
+            }
+        }
+        if image_count == 0 {
+            return Err(PdfError::Custom("No images provided".to_string()));
+        }
+
+        let grouped = self.grouped_for(self.file_page_count(image_count), &pdf_files)?;
+        let image_pages = match self.config.strip_mode {
+            Some(_) => 1,
+            None => image_count.div_ceil(grouped.as_ref().unwrap_or(self).images_per_page()),
+        };
+        // 展开动画时图片数量事先未知，转换时不生成目录；长条模式先收集全部图片，数量已知
+        let known_count = match self.config.strip_mode {
+            Some(_) => Some(image_count),
+            None => self.file_page_count(image_count),
+        };
+        let extra_pages = self.extra_pages(known_count, &pdf_files)?;
+
+        Ok((image_pages + extra_pages).max(self.config.target_pages.unwrap_or(0)))
+    }
+
+    /// Number of page images a single file contributes under the current configuration
+    ///
+    /// This is 1 for an ordinary image, the number of sampled frames for an
+    /// animated GIF when `animation_frames` is set, and the number of pages
+    /// of a PDF file, which are inserted as they are. In grid mode each page
+    /// image fills one cell, so `n` images take `n / cells` pages rounded up,
+    /// and in strip mode all of them share one page; `count_pages` does that
+    /// aggregation for a whole folder. Only animated GIFs and PDF files are
+    /// read.
+    ///
+    /// ```rust
+    /// use pdf_converter::{FrameSampling, PdfConfig, PdfConverter};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// let path = dir.path().join("spinner.gif");
+    /// let mut encoder = image::codecs::gif::GifEncoder::new(std::fs::File::create(&path)?);
+    /// for shade in [0u8, 80, 160] {
+    ///     let frame = image::RgbaImage::from_pixel(20, 20, image::Rgba([shade, shade, shade, 255]));
+    ///     encoder.encode_frame(image::Frame::new(frame))?;
+    /// }
+    /// drop(encoder);
+    ///
+    /// assert_eq!(PdfConverter::new().pages_for_image(&path)?, 1);
+    /// let converter = PdfConverter::with_config(PdfConfig {
+    ///     animation_frames: Some(FrameSampling::All),
+    ///     ..PdfConfig::default()
+    /// });
+    /// assert_eq!(converter.pages_for_image(&path)?, 3);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn pages_for_image<P: AsRef<Path>>(&self, path: P) -> Result<usize> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(PdfError::InvalidPath(path.display().to_string()));
+        }
+        if is_pdf_file(path) {
+            return Ok(PdfInsert::load(0, path)?.page_count());
+        }
+        let Some(sampling) = self.config.animation_frames else { return Ok(1) };
+        let bytes = std::fs::read(path)?;
+        // 只读取帧延迟，无需解码像素；无法解析时按完整解码计数
+        match gif::frame_delays(&bytes) {
+            Some(delays) if delays.len() < 2 => Ok(1),
+            Some(delays) => Ok(sampled_frames(&delays, sampling).into_iter().filter(|shown| *shown).count()),
+            None => Ok(self.animation_frames(&bytes)?.map_or(1, |frames| frames.len())),
+        }
+    }
+
+    /// Lowercase file extensions of the images picked up from folders and archives
+    ///
+    /// Matching is case-insensitive. Useful for file picker filters; files
+    /// passed explicitly are recognized by their contents instead.
+    pub fn supported_extensions() -> &'static [&'static str] {
+        SUPPORTED_EXTENSIONS
+    }
+
+    /// Whether a file name has a supported image extension
+    ///
+    /// This is the check used for folders and archives. Only the last
+    /// extension counts and case is ignored:
+    ///
+    /// ```rust
+    /// use pdf_converter::PdfConverter;
+    ///
+    /// for name in ["a.jpg", "b.PNG", "c.JpEg", "d.tar.webp", "e.f.g.bmp"] {
+    ///     assert!(PdfConverter::is_supported_image(name), "{name}");
+    /// }
+    /// for name in ["photo.JPG.bak", "image.png.tmp", ".jpg", "jpg", "notes.txt", "archive.jpg.zip"] {
+    ///     assert!(!PdfConverter::is_supported_image(name), "{name}");
+    /// }
+    /// ```
+    pub fn is_supported_image<P: AsRef<Path>>(path: P) -> bool {
+        is_image_file(path.as_ref())
+    }
+
+    /// Get the current configuration
+    pub fn config(&self) -> &PdfConfig {
+        &self.config
+    }
+
+    /// Update the configuration
+    pub fn set_config(&mut self, config: PdfConfig) {
+        self.config = config;
+    }
+
+    /// Open and decode an image, telling unsupported formats apart from decode errors
+    ///
+    /// The format is detected from the file contents rather than the extension,
+    /// so a renamed or foreign file is reported as `UnsupportedFormat`.
+    fn open_image(&self, image_path: &Path) -> Result<::image::DynamicImage> {
+        self.decode_image(image_path, &std::fs::read(image_path)?)
+    }
+
+    /// Decode the contents of an image file
+    ///
+    /// `image_path` names the file in errors.
+    fn decode_image(&self, image_path: &Path, bytes: &[u8]) -> Result<::image::DynamicImage> {
+        let unsupported = |format: String| PdfError::UnsupportedFormat {
+            path: image_path.display().to_string(),
+            format,
+        };
+
+        let max_decoded_bytes = self.config.resource_limits.max_decoded_bytes;
+        let too_large = |limit: u64| PdfError::ImageTooLarge {
+            path: image_path.display().to_string(),
+            limit,
+        };
+
+        // PSD只读取合并后的图像
+        #[cfg(feature = "psd")]
+        if psd::is_psd(bytes) {
+            if let (Some(limit), Some(len)) = (max_decoded_bytes, psd::decoded_len(bytes)) {
+                if len > limit {
+                    return Err(too_large(limit));
+                }
+            }
+            let img = psd::decode(bytes).map_err(|reason| unsupported(format!("PSD, {}", reason)))?;
+            return Ok(img);
+        }
+
+        let truncated = || PdfError::TruncatedImage(image_path.display().to_string());
+        if bytes.is_empty() {
+            return Err(truncated());
+        }
+        let format = ::image::guess_format(bytes)
+            .map_err(|_| unsupported("unknown".to_string()))?;
+
+        // 解码前按图片头中的尺寸和颜色类型检查内存上限
+        let mut reader = ::image::io::Reader::with_format(std::io::Cursor::new(bytes), format);
+        let mut limits = ::image::io::Limits::default();
+        limits.max_alloc = max_decoded_bytes;
+        reader.limits(limits);
+        let img = reader.decode().map_err(|e| match e {
+            ::image::ImageError::Unsupported(_) => unsupported(format!("{:?}", format)),
+            ::image::ImageError::Limits(_) => match max_decoded_bytes {
+                Some(limit) => too_large(limit),
+                None => PdfError::Image(e),
+            },
+            _ if is_truncated(bytes) => truncated(),
+            other => PdfError::Image(other),
+        })?;
+
+        Ok(img)
+    }
+
+    /// Run `read` again after the `read_retry` backoff while it reports a truncated image
+    fn read_with_retry<T>(&self, mut read: impl FnMut() -> Result<T>) -> Result<T> {
+        let Some(retry) = self.config.read_retry else { return read() };
+        let mut backoff = retry.backoff;
+        let mut attempt = 1;
+        loop {
+            match read() {
+                Err(PdfError::TruncatedImage(path)) if attempt < retry.attempts => {
+                    log::warn!("⚠️ 图片文件不完整（第{}次，共{}次）: {}，{:?}后重试", attempt, retry.attempts, path, backoff);
+                    std::thread::sleep(backoff);
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Sampled frames of an animated GIF, `None` for still images or when animations are not expanded
+    fn animation_frames(&self, bytes: &[u8]) -> Result<Option<Vec<::image::DynamicImage>>> {
+        let Some(sampling) = self.config.animation_frames else { return Ok(None) };
+        if ::image::guess_format(bytes).ok() != Some(::image::ImageFormat::Gif) {
+            return Ok(None);
+        }
+
+        let decoder = ::image::codecs::gif::GifDecoder::new(std::io::Cursor::new(bytes))?;
+        let frames = ::image::AnimationDecoder::into_frames(decoder).collect_frames()?;
+        if frames.len() < 2 {
+            return Ok(None);
+        }
+
+        let delays: Vec<Duration> = frames
+            .iter()
+            .map(|frame| {
+                let (numer, denom) = frame.delay().numer_denom_ms();
+                Duration::from_micros(numer as u64 * 1000 / denom.max(1) as u64)
+            })
+            .collect();
+        let sampled = frames
+            .into_iter()
+            .zip(sampled_frames(&delays, sampling))
+            .filter(|(_, shown)| *shown)
+            .map(|(frame, _)| ::image::DynamicImage::ImageRgba8(frame.into_buffer()))
+            .collect();
+        Ok(Some(sampled))
+    }
+
+    /// Configured rotation of an image file, looked up by its file name
+    fn rotation_for(&self, image_path: &Path) -> Option<f32> {
+        let name = image_path.file_name()?.to_str()?;
+        self.config.rotations.get(name).copied()
+    }
+
+    /// Mask file of an image, its entry in `masks` or else its existing companion `<stem>.mask.png`
+    fn mask_path(&self, image_path: &Path) -> Option<PathBuf> {
+        let name = image_path.file_name()?.to_str()?;
+        if let Some(mask) = self.config.masks.get(name) {
+            return Some(mask.clone());
+        }
+        if !self.config.mask_files {
+            return None;
+        }
+        let companion = image_path.with_file_name(format!("{}.mask.png", image_path.file_stem()?.to_str()?));
+        companion.is_file().then_some(companion)
+    }
+
+    /// Whether a file found in a folder is the mask of another image
+    fn is_mask_file(&self, path: &Path) -> bool {
+        let is_companion = self.config.mask_files
+            && path.file_stem().and_then(|stem| stem.to_str()).is_some_and(|stem| stem.ends_with(".mask"));
+        is_companion || self.config.masks.values().any(|mask| mask == path)
+    }
+
+    /// Grayscale mask of an image file, cropped and rotated like the image
+    fn load_mask(&self, image_path: &Path) -> Result<Option<::image::DynamicImage>> {
+        let Some(mask_path) = self.mask_path(image_path) else { return Ok(None) };
+        log::debug!("  加载蒙版: {}", mask_path.display());
+        let mut mask = ::image::DynamicImage::ImageLuma8(::image::open(&mask_path)?.to_luma8());
+        if let Some(crop) = self.crop_for(Some(image_path)) {
+            let (width, height) = mask.dimensions();
+            let ((x, y, crop_width, crop_height), _) = crop.pixel_rect(width, height);
+            mask = mask.crop_imm(x, y, crop_width, crop_height);
+        }
+        if let Some(degrees) = self.rotation_for(image_path) {
+            mask = processing::rotate(mask, degrees);
+        }
+        Ok(Some(mask))
+    }
+
+    /// Mask stretched to the pixel size of the image object it belongs to
+    fn sized_mask(&self, mask: &::image::DynamicImage, object: &ImageXObject) -> SoftMask {
+        let (width, height) = (object.width.0 as u32, object.height.0 as u32);
+        let samples = if mask.dimensions() == (width, height) {
+            mask.to_luma8()
+        } else {
+            ::image::imageops::resize(&mask.to_luma8(), width, height, self.config.resize_filter.into())
+        };
+        SoftMask { width, height, samples: samples.into_raw() }
+    }
+
+    /// Configured crop of an image, its entry in `image_crops` or else `crop`
+    fn crop_for(&self, image_path: Option<&Path>) -> Option<ImageCrop> {
+        let name = image_path.and_then(Path::file_name).and_then(|name| name.to_str());
+        name.and_then(|name| self.config.image_crops.get(name)).or(self.config.crop.as_ref()).copied()
+    }
+
+    /// Write the configured header and footer onto every page
+    ///
+    /// Each line is centered vertically in its margin and aligned horizontally
+    /// between the left and right margins.
+    fn add_running_text(&self, doc: &PdfDocumentReference, font: &TextFont, pages: &[(PdfPageIndex, PageSize)]) {
+        let font_size = self.config.header_footer_font_size;
+        let margin_mm = self.config.effective_margin_mm();
+        // 以大写字母高度（约0.7倍字号）估算文字高度
+        let text_height_mm = 0.7 * font_size / text::PT_PER_MM;
+        let format = self.config.page_number_format;
+        let first_numbered = self.config.page_number_start.max(1) - 1;
+        let total = format.format(pages.len().saturating_sub(first_numbered));
+
+        for (index, (page_index, page)) in pages.iter().enumerate() {
+            let number = index.checked_sub(first_numbered).map(|number| format.format(number + 1));
+            // 编号开始之前的页面不显示含页码的行
+            let substitute = |template: &str| match &number {
+                Some(number) => Some(template.replace("{page}", number).replace("{total}", &total)),
+                None if template.contains("{page}") || template.contains("{total}") => None,
+                None => Some(template.to_string()),
+            };
+            let x_for = |line: &str, align: TextAlign| match align {
+                TextAlign::Left => margin_mm,
+                TextAlign::Center => (page.width_mm - font.text_width_mm(line, font_size)) / 2.0,
+                TextAlign::Right => page.width_mm - margin_mm - font.text_width_mm(line, font_size),
+            };
+
+            let layer = doc.get_page(*page_index).add_layer("Header and footer");
+            if let Some(line) = self.config.header_text.as_deref().and_then(substitute) {
+                let y_mm = page.height_mm - (margin_mm + text_height_mm) / 2.0;
+                text::write_line(&layer, font, &line, font_size, x_for(&line, self.config.header_align), y_mm);
+            }
+            if let Some(line) = self.config.footer_text.as_deref().and_then(substitute) {
+                let y_mm = (margin_mm - text_height_mm) / 2.0;
+                text::write_line(&layer, font, &line, font_size, x_for(&line, self.config.footer_align), y_mm);
+            }
+        }
+    }
+
+    /// Draw an overlay image on top of a page's content
+    fn add_overlay(&self, doc: &PdfDocumentReference, overlay: &PageOverlay, page_index: PdfPageIndex, page: PageSize) -> Result<()> {
+        let img = processing::flatten_alpha(self.open_image(&overlay.path)?);
+        let (img_width, img_height) = img.dimensions();
+        let height_mm = overlay.width_mm * img_height as f32 / img_width.max(1) as f32;
+        // 缩放以配置的DPI下的尺寸为基准
+        let scale = overlay.width_mm / (img_width.max(1) as f32 * 25.4 / self.config.dpi);
+
+        let layer = doc.get_page(page_index).add_layer("Overlay");
+        printpdf::Image::from(self.raw_image_object(&img)).add_to_layer(
+            layer,
+            ImageTransform {
+                translate_x: Some(printpdf::Mm(overlay.x_mm)),
+                translate_y: Some(printpdf::Mm(page.height_mm - overlay.top_mm - height_mm)),
+                scale_x: Some(scale),
+                scale_y: Some(scale),
+                rotate: None,
+                dpi: Some(self.config.dpi),
+            },
+        );
+        log::debug!("  添加首页叠加图片: {}", overlay.path.display());
+        Ok(())
+    }
+
+    /// Draw the margin box of every page as a dashed line
+    fn add_margin_guides(&self, doc: &PdfDocumentReference, pages: &[(PdfPageIndex, PageSize)]) {
+        for (page_index, page) in pages {
+            let layer = doc.get_page(*page_index).add_layer("Margin guides");
+            layer.set_outline_color(Color::Greyscale(Greyscale::new(0.5, None)));
+            layer.set_outline_thickness(0.25);
+            // 3pt线段与2pt间隔交替
+            layer.set_line_dash_pattern(LineDashPattern {
+                dash_1: Some(3),
+                gap_1: Some(2),
+                ..Default::default()
+            });
+            layer.add_polygon(margin_polygon(*page, self.config.effective_margin_mm(), path::PaintMode::Stroke));
+        }
+    }
+
+    /// Draw dashed cut lines through the gutters between the cells of every image sheet
+    fn add_cut_lines(&self, doc: &PdfDocumentReference, pages: &[(PdfPageIndex, PageSize)], image_pages: &[(PdfPageIndex, PathBuf)], grid: Grid) {
+        let gutter_mm = self.config.grid_gutter_mm.max(0.0);
+        let margin_mm = self.config.effective_margin_mm();
+        for (page_index, page) in pages.iter().filter(|(page_index, _)| image_pages.iter().any(|(index, _)| index == page_index)) {
+            let (cell_width_mm, cell_height_mm) = self.grid_cell_size(*page, grid);
+            let layer = doc.get_page(*page_index).add_layer("Cut lines");
+            layer.set_outline_color(Color::Greyscale(Greyscale::new(0.5, None)));
+            layer.set_outline_thickness(0.25);
+            layer.set_line_dash_pattern(LineDashPattern {
+                dash_1: Some(3),
+                gap_1: Some(2),
+                ..Default::default()
+            });
+            let line = |from: (f32, f32), to: (f32, f32)| Line {
+                points: vec![(Point::new(Mm(from.0), Mm(from.1)), false), (Point::new(Mm(to.0), Mm(to.1)), false)],
+                is_closed: false,
+            };
+            // 切割线位于格子间距的中线
+            for column in 1..grid.columns {
+                let x = margin_mm + column as f32 * (cell_width_mm + gutter_mm) - gutter_mm / 2.0;
+                layer.add_line(line((x, 0.0), (x, page.height_mm)));
+            }
+            for row in 1..grid.rows {
+                let y = page.height_mm - margin_mm - row as f32 * (cell_height_mm + gutter_mm) + gutter_mm / 2.0;
+                layer.add_line(line((0.0, y), (page.width_mm, y)));
+            }
+        }
+    }
+
+    /// Stamp a QR code onto every page
+    ///
+    /// `image_pages` pairs each image page with the files shown on it, in
+    /// placement order, for codes encoding the file name.
+    #[cfg(feature = "qr")]
+    fn add_qr_stamps(
+        &self,
+        doc: &PdfDocumentReference,
+        stamp: &crate::qr::QrStamp,
+        pages: &[(PdfPageIndex, PageSize)],
+        image_pages: &[(PdfPageIndex, PathBuf)],
+    ) -> Result<()> {
+        use crate::qr::{self, QrContent};
+
+        let total = pages.len();
+        for (number, (page_index, page)) in pages.iter().enumerate() {
+            let data = match &stamp.content {
+                QrContent::FileName => match image_pages.iter().find(|(index, _)| index == page_index) {
+                    Some((_, path)) => path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+                    None => continue,
+                },
+                QrContent::Text(template) => template
+                    .replace("{page}", &(number + 1).to_string())
+                    .replace("{total}", &total.to_string()),
+            };
+            let layer = doc.get_page(*page_index).add_layer("QR code");
+            qr::draw(&layer, *page, stamp, &data)?;
+        }
+        Ok(())
+    }
+
+    /// Write a finished document, applying post-processing that printpdf cannot do
+    ///
+    /// Post-processing covers the internal links of the table of contents,
+    /// PDF/X requirements, catalog entries, the encoding of non-ASCII titles
+    /// and encryption.
+    ///
+    /// With a `save_retry` policy the document is serialized in memory first,
+    /// so failed writes can be repeated without rebuilding it.
+    fn save_document(&self, doc: PdfDocumentReference, toc_links: &[TocLink], outline: &[OutlineNode], pdf_inserts: Vec<PdfInsert>, masks: Vec<SoftMask>, output: &Path) -> Result<()> {
+        self.write_output(output, |target| self.write_document(doc, toc_links, outline, pdf_inserts, masks, target))
+    }
+
+    /// Post-process a finished document as needed and write it to `output`
+    fn write_document(&self, doc: PdfDocumentReference, toc_links: &[TocLink], outline: &[OutlineNode], pdf_inserts: Vec<PdfInsert>, masks: Vec<SoftMask>, output: &Path) -> Result<()> {
+        let needs_post_processing = !toc_links.is_empty()
+            || !masks.is_empty()
+            || self.config.image_compression == ImageCompression::Lossless
+            || self.config.compress_streams
+            || !pdf_inserts.is_empty()
+            || !self.config.page_boxes.is_empty()
+            || self.config.pdf_x.is_some()
+            || self.config.xmp_metadata.is_some()
+            || self.config.language.is_some()
+            || self.config.encryption.is_some()
+            // printpdf写入的标题是未经编码的UTF-8
+            || !self.config.title.is_ascii()
+            || self.config.bookmarks
+            || self.config.target_pages.is_some();
+
+        let Some(retry) = self.config.save_retry else {
+            // 保存PDF - 使用BufWriter
+            let file = std::fs::File::create(output)?;
+            let mut buf_writer = BufWriter::new(file);
+            if needs_post_processing {
+                self.post_process(doc.save_to_bytes()?, toc_links, outline, pdf_inserts, masks)?.save_to(&mut buf_writer)?;
+            } else {
+                doc.save(&mut buf_writer)?;
+            }
+            buf_writer.flush()?;
+            if self.config.sync_output {
+                buf_writer.get_ref().sync_all()?;
+            }
+            return Ok(());
+        };
+
+        let mut bytes = doc.save_to_bytes()?;
+        if needs_post_processing {
+            let mut document = self.post_process(bytes, toc_links, outline, pdf_inserts, masks)?;
+            bytes = Vec::new();
+            document.save_to(&mut bytes)?;
+        }
+        write_with_retry(output, &bytes, retry, self.config.sync_output)
+    }
+
+    /// Write the output file with `write`, through a temporary file renamed into place with `atomic_output`
+    fn write_output(&self, output: &Path, write: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+        if !self.config.atomic_output {
+            return write(output);
+        }
+        static NEXT_TEMPORARY: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let temporary_id = NEXT_TEMPORARY.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        // 临时文件与输出位于同一目录，重命名才是原子操作
+        let name = output.file_name().unwrap_or_default().to_string_lossy();
+        let temporary = output.with_file_name(format!(".{}.{}-{}.tmp", name, std::process::id(), temporary_id));
+
+        let result = write(&temporary).and_then(|()| Ok(std::fs::rename(&temporary, output)?));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temporary);
+        }
+        result
+    }
+
+    /// Apply the table of contents links, inserted PDF pages, page boxes, PDF/X requirements, XMP metadata, language, title encoding and encryption to a serialized document
+    fn post_process(&self, bytes: Vec<u8>, toc_links: &[TocLink], outline: &[OutlineNode], pdf_inserts: Vec<PdfInsert>, masks: Vec<SoftMask>) -> Result<printpdf::lopdf::Document> {
+        let mut document = printpdf::lopdf::Document::load_mem(&bytes)?;
+        smask::apply(&mut document, masks)?;
+        // 书签按生成的页面位置查找，需在插入PDF页面之前添加
+        outline::write(&mut document, outline)?;
+        self.finish_document(&mut document, toc_links, pdf_inserts)?;
+        Ok(document)
+    }
+
+    /// Apply the post-processing steps of `post_process` to a loaded document
+    fn finish_document(&self, document: &mut printpdf::lopdf::Document, toc_links: &[TocLink], pdf_inserts: Vec<PdfInsert>) -> Result<()> {
+        // 目录链接按生成的页面位置查找，需在插入PDF页面之前添加
+        toc::add_links(document, toc_links)?;
+        merge::insert_pages(document, pdf_inserts)?;
+        if let Some(target) = self.config.target_pages {
+            pad_pages(document, target)?;
+        }
+        boxes::apply(document, &self.config.page_boxes)?;
+        if let Some(level) = self.config.pdf_x {
+            pdfx::finish(document, level)?;
+        }
+        if let Some(packet) = &self.config.xmp_metadata {
+            xmp::set_metadata(document, packet)?;
+        }
+        if let Some(language) = &self.config.language {
+            document.catalog_mut()?.set("Lang", text_string(language));
+        }
+        if self.config.image_compression == ImageCompression::Lossless {
+            lossless::compress_images(document)?;
+        }
+        if self.config.compress_streams {
+            compress_streams(document);
+        }
+        encode_text_strings(document);
+        if let Some(encryption) = &self.config.encryption {
+            encryption::encrypt_document(document, encryption);
+        }
+        Ok(())
+    }
+
+    /// Validate a folder and collect its images, and PDF files if `include_pdfs`, in page order
+    fn folder_images(&self, folder: &Path, include_pdfs: bool) -> Result<Vec<PathBuf>> {
+        let image_files = self.sorted_folder_images(folder, include_pdfs)?;
+        self.check_orientation(&image_files, |path| match is_pdf_file(path) {
+            true => Ok(None),
+            false => self.upright_dimensions(path).map(Some),
+        })?;
+        Ok(image_files)
+    }
+
+    /// Like `folder_images`, without checking that the images share an orientation
+    fn sorted_folder_images(&self, folder: &Path, include_pdfs: bool) -> Result<Vec<PathBuf>> {
+        if !folder.exists() || !folder.is_dir() {
+            return Err(PdfError::InvalidPath(folder.display().to_string()));
+        }
+
+        // 收集所有图片文件
+        let mut image_files = self.collect_image_files(folder, include_pdfs)?;
+        
+        if image_files.is_empty() {
+            return Err(PdfError::NoImagesFound(folder.display().to_string()));
+        }
+
+        self.sort_images(&mut image_files, |path, key| {
+            Ok(match key {
+                SortKey::FileSize => std::fs::metadata(path)?.len(),
+                SortKey::PixelArea if is_pdf_file(path) => 0,
+                SortKey::PixelArea => {
+                    let (width, height) = image_dimensions(path)?;
+                    width as u64 * height as u64
+                }
+            })
+        })?;
+        Ok(image_files)
+    }
+
+    /// Fail when `require_consistent_orientation` is set and the images mix portrait and landscape
+    ///
+    /// `dimensions` reads the pixel size of an image, `None` for files without one.
+    fn check_orientation<F>(&self, images: &[PathBuf], mut dimensions: F) -> Result<()>
+    where
+        F: FnMut(&Path) -> Result<Option<(u32, u32)>>,
+    {
+        if !self.config.require_consistent_orientation {
+            return Ok(());
+        }
+
+        let mut portrait = Vec::new();
+        let mut landscape = Vec::new();
+        for path in images {
+            let Some((mut width, mut height)) = dimensions(path)? else { continue };
+            // 按最接近的直角判断旋转后是否横竖互换
+            if let Some(degrees) = self.rotation_for(path) {
+                if (degrees / 90.0).round().rem_euclid(2.0) == 1.0 {
+                    std::mem::swap(&mut width, &mut height);
+                }
+            }
+            match width.cmp(&height) {
+                std::cmp::Ordering::Less => portrait.push(path.display().to_string()),
+                std::cmp::Ordering::Greater => landscape.push(path.display().to_string()),
+                std::cmp::Ordering::Equal => {}
+            }
+        }
+
+        if portrait.is_empty() || landscape.is_empty() {
+            return Ok(());
+        }
+        let minority = if portrait.len() < landscape.len() { &portrait } else { &landscape };
+        for path in minority {
+            log::warn!("⚠️ 图片方向与其他图片不一致: {}", path);
+        }
+        Err(PdfError::MixedOrientation { portrait, landscape })
+    }
+
+    /// Sort images into page order as configured by `page_order`
+    ///
+    /// `metadata` reads the file size or pixel area of an image for the
+    /// orders that need it.
+    fn sort_images<F>(&self, images: &mut [PathBuf], mut metadata: F) -> Result<()>
+    where
+        F: FnMut(&Path, SortKey) -> Result<u64>,
+    {
+        // 先按文件名排序，编号或大小相同的文件保持文件名顺序
+        images.sort();
+
+        let (key, direction) = match &self.config.page_order {
+            PageOrder::FileName => return Ok(()),
+            PageOrder::CapturedNumber { pattern, unmatched } => return Self::sort_by_captured_number(images, pattern, *unmatched),
+            PageOrder::FileSize(direction) => (SortKey::FileSize, *direction),
+            PageOrder::PixelArea(direction) => (SortKey::PixelArea, *direction),
+        };
+
+        let mut keyed = images
+            .iter()
+            .map(|path| Ok((metadata(path, key)?, path.clone())))
+            .collect::<Result<Vec<_>>>()?;
+        // 稳定排序，降序时也保持文件名顺序
+        keyed.sort_by(|(a, _), (b, _)| match direction {
+            SortDirection::Ascending => a.cmp(b),
+            SortDirection::Descending => b.cmp(a),
+        });
+        for (slot, (_, path)) in images.iter_mut().zip(keyed) {
+            *slot = path;
+        }
+        Ok(())
+    }
+
+    /// Sort images already in file name order by the number captured from their names
+    fn sort_by_captured_number(images: &mut [PathBuf], pattern: &Regex, unmatched: UnmatchedPages) -> Result<()> {
+        let page_number = |path: &Path| -> Option<u64> {
+            let name = path.file_name()?.to_string_lossy();
+            pattern.captures(&name)?.get(1)?.as_str().parse().ok()
+        };
+
+        if unmatched == UnmatchedPages::Error {
+            if let Some(path) = images.iter().find(|path| page_number(path).is_none()) {
+                return Err(PdfError::UnorderedFile(path.display().to_string()));
+            }
+        }
+        // 未匹配的文件排在最后
+        images.sort_by_cached_key(|path| page_number(path).map_or((1, 0), |number| (0, number)));
+        Ok(())
+    }
+
+    /// Collect all image files from folder, and the PDF files if `include_pdfs`
+    fn collect_image_files(&self, folder: &Path, include_pdfs: bool) -> Result<Vec<PathBuf>> {
+        let mut image_files = Vec::new();
+
+        for entry in std::fs::read_dir(normalize_path(folder))? {
+            let entry = entry?;
+            let path = entry.path();
+            if self.config.skip_hidden && entry.file_name().as_encoded_bytes().starts_with(b".") {
+                log::debug!("  跳过隐藏文件: {}", path.display());
+                continue;
+            }
+            if self.is_mask_file(&path) {
+                log::debug!("  跳过蒙版文件: {}", path.display());
+                continue;
+            }
+            if entry.file_type()?.is_file() && (is_image_file(&path) || (include_pdfs && is_pdf_file(&path))) {
+                image_files.push(path);
+            }
+        }
+
+        Ok(image_files)
+    }
+
+    /// Build the image object of an image file and fit it onto `page`
+    ///
+    /// `bytes` holds the contents of the image file; `image_path` names it in
+    /// logs and errors. `orientation` is the EXIF orientation to turn the
+    /// image upright with. The returned placement's page is the one to draw
+    /// on, which differs from `page` when `native_dpi` is set.
+    fn image_object(&self, image_path: &Path, bytes: &[u8], orientation: Option<u8>, page: PageSize) -> Result<(ImageXObject, Placement)> {
+        // 需要旋转或裁剪的JPEG必须解码
+        let passthrough = match (orientation, self.rotation_for(image_path), self.crop_for(Some(image_path))) {
+            (None, None, None) => self.jpeg_passthrough(image_path, bytes, page)?,
+            _ => None,
+        };
+        Ok(match passthrough {
+            Some(passthrough) => {
+                log::debug!("  直接嵌入JPEG数据: {}", image_path.display());
+                passthrough
+            }
+            None => {
+                let img = self.decode_image(image_path, bytes)?;
+                let (img, placement) = self.process_image(img, Some(image_path), orientation, page);
+                (self.raw_image_object(&img), placement)
+            }
+        })
+    }
+
+    /// Build the image object of a borrowed decoded image, copying it only when processing changes it
+    fn decoded_image_object(&self, img: &::image::DynamicImage, page: PageSize) -> (ImageXObject, Placement) {
+        let (img_width, img_height) = img.dimensions();
+        let placement = self.compute_placement(img_width, img_height, page);
+        let fits_max_dpi = self.image_max_dpi(img).is_none_or(|max_dpi| {
+            let (max_width, max_height) = max_pixel_size(placement.width_mm, placement.height_mm, max_dpi);
+            img_width <= max_width && img_height <= max_height
+        });
+
+        if self.config.deskew || self.config.auto_trim || self.config.tint.is_some() || self.config.chroma_key.is_some() || self.config.crop.is_some() || self.image_processor.is_some() || !fits_max_dpi {
+            let (img, placement) = self.process_image(img.clone(), None, None, page);
+            (self.raw_image_object(&img), placement)
+        } else {
+            (self.raw_image_object(img), placement)
+        }
+    }
+
+    /// Build an image object with the uncompressed pixels of a decoded image
+    ///
+    /// Pure black-and-white images, such as fax-style document scans, are
+    /// embedded with one bit per pixel, a 24th of their RGB size.
+    fn raw_image_object(&self, img: &::image::DynamicImage) -> ImageXObject {
+        let (img_width, img_height) = img.dimensions();
+        let (raw_data, bits_per_component, color_space) = match (processing::bilevel_samples(img), self.config.pdf_x) {
+            // 纯黑白图片每像素一位
+            (Some(samples), _) => (samples, ColorBits::Bit1, ColorSpace::Greyscale),
+            // 文字类图片用灰度嵌入，数据量为RGB的三分之一
+            _ if self.is_text_like(img) => (img.to_luma8().into_raw(), ColorBits::Bit8, ColorSpace::Greyscale),
+            (None, Some(_)) => (pdfx::cmyk_samples(img), ColorBits::Bit8, ColorSpace::Cmyk),
+            (None, None) => {
+                let (raw_data, bits_per_component) = self.pixel_data(img);
+                (raw_data, bits_per_component, ColorSpace::Rgb)
+            }
+        };
+
+        // 创建图片对象
+        ImageXObject {
+            width: Px(img_width as usize),
+            height: Px(img_height as usize),
+            color_space,
+            bits_per_component,
+            interpolate: true,
+            image_data: raw_data,
+            image_filter: None,
+            clipping_bbox: None,
+            smask: None,
+        }
+    }
+
+    /// Add an image to PDF page at its placement
+    ///
+    /// A caller-supplied `transform` replaces the one derived from the placement.
+    fn add_image_to_pdf_layer(&self, current_layer: &PdfLayerReference, image_object: ImageXObject, placement: &Placement, transform: Option<ImageTransform>) {
+        let (img_width, img_height) = (image_object.width.0, image_object.height.0);
+        let image_file = printpdf::Image::from(image_object);
+
+        // 混合模式、渲染意图和圆角裁剪只作用于当前图片
+        current_layer.save_graphics_state();
+        if self.config.clip_to_margins {
+            current_layer.add_polygon(margin_polygon(placement.page, self.config.effective_margin_mm(), path::PaintMode::Clip));
+        }
+        let rotated = transform.is_some_and(|transform| transform.rotate.is_some());
+        if self.config.corner_radius_mm > 0.0 && !rotated {
+            current_layer.add_polygon(rounded_rect_clip(placement, self.config.corner_radius_mm));
+        }
+        if let Some(blend_mode) = self.config.blend_mode {
+            current_layer.set_blend_mode(blend_mode);
+        }
+        if let Some(rendering_intent) = self.config.rendering_intent {
+            for operation in rendering_intent.into_stream_op() {
+                current_layer.add_operation(operation);
+            }
+        }
+
+        // 平移量是图片左下角的位置，缩放以配置的DPI下的尺寸为基准
+        image_file.add_to_layer(
+            current_layer.clone(),
+            transform.unwrap_or(ImageTransform {
+                translate_x: Some(printpdf::Mm(placement.x_mm)),
+                translate_y: Some(printpdf::Mm(placement.y_mm)),
+                scale_x: Some(placement.scale_x),
+                scale_y: Some(placement.scale_y),
+                rotate: None,
+                dpi: Some(self.config.dpi),
+            }),
+        );
+        current_layer.restore_graphics_state();
+
+        log::debug!("  图片添加成功: {}x{} -> {:.1}x{:.1}mm @ ({:.1}, {:.1})mm", 
+            img_width, img_height, placement.width_mm, placement.height_mm, placement.x_mm, placement.y_mm);
+    }
+
+    /// Embed a JPEG file's compressed data as-is when no processing needs its pixels
+    ///
+    /// Returns `None`, falling back to decoding, when the data is not a JPEG
+    /// readers can decode directly, when deskewing, trimming or an image
+    /// processor is enabled, for color JPEGs in PDF/X output, or when the image exceeds the resolution
+    /// limit and must be downscaled. Only the data up to the end marker is
+    /// embedded; a file that ends before it fails with `TruncatedImage`.
+    fn jpeg_passthrough(&self, image_path: &Path, bytes: &[u8], page: PageSize) -> Result<Option<(ImageXObject, Placement)>> {
+        // 重叠检测需要比较像素
+        let finds_overlap = self.config.strip_mode.is_some_and(|strip| strip.overlap.is_some());
+        if self.config.deskew || self.config.auto_trim || self.config.tint.is_some() || self.config.chroma_key.is_some() || self.image_processor.is_some() || finds_overlap {
+            return Ok(None);
+        }
+        let Some(info) = jpeg::read_header(bytes) else { return Ok(None) };
+        // PDF/X需要CMYK，彩色JPEG必须解码转换
+        if self.config.pdf_x.is_some() && info.components != 1 {
+            return Ok(None);
+        }
+        let placement = self.compute_placement(info.width, info.height, page);
+
+        if let Some(max_dpi) = self.lowest_max_dpi() {
+            let (max_width, max_height) = max_pixel_size(placement.width_mm, placement.height_mm, max_dpi);
+            if info.width > max_width || info.height > max_height {
+                return Ok(None);
+            }
+        }
+
+        // 不解码就无法发现数据缺失，需找到压缩数据后的结束标记
+        let end = jpeg::data_end(bytes).ok_or_else(|| PdfError::TruncatedImage(image_path.display().to_string()))?;
+
+        let image_object = ImageXObject {
+            width: Px(info.width as usize),
+            height: Px(info.height as usize),
+            color_space: if info.components == 1 { ColorSpace::Greyscale } else { ColorSpace::Rgb },
+            bits_per_component: ColorBits::Bit8,
+            interpolate: true,
+            image_data: bytes[..end].to_vec(),
+            image_filter: Some(ImageFilter::DCT),
+            clipping_bbox: None,
+            smask: None,
+        };
+        Ok(Some((image_object, placement)))
+    }
+
+    /// RGB sample data of an image and its bit depth
+    ///
+    /// Sources with 16 bits per channel keep their precision and are embedded
+    /// with 16-bit big-endian samples. Everything else, and every image when
+    /// downsampling is requested via `max_dpi`, `low_memory` or `content_dpi`,
+    /// is embedded with 8 bits.
+    fn pixel_data(&self, img: &::image::DynamicImage) -> (Vec<u8>, ColorBits) {
+        use ::image::ColorType;
+
+        let is_16_bit = matches!(img.color(), ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16);
+        if is_16_bit && self.lowest_max_dpi().is_none() {
+            // PDF要求16位采样按大端序存储
+            let data = img.to_rgb16().into_raw().into_iter().flat_map(u16::to_be_bytes).collect();
+            (data, ColorBits::Bit16)
+        } else {
+            (img.to_rgb8().into_raw(), ColorBits::Bit8)
+        }
+    }
+
+    /// Decode an image, apply the configured processing and compute its placement
+    ///
+    /// The returned placement's scale factors refer to the returned image, which
+    /// may be smaller than the source file after trimming or downscaling.
+    fn prepare_image(&self, image_path: &Path, page: PageSize) -> Result<(::image::DynamicImage, Placement)> {
+        let bytes = std::fs::read(image_path)?;
+        let img = self.decode_image(image_path, &bytes)?;
+        Ok(self.process_image(img, Some(image_path), self.orientation_of(&bytes), page))
+    }
+
+    /// EXIF orientation of an image file to turn it upright with; `None` when upright or not requested
+    fn orientation_of(&self, bytes: &[u8]) -> Option<u8> {
+        self.config.exif_orientation.then(|| jpeg::exif_orientation(bytes)).flatten().filter(|&orientation| orientation != 1)
+    }
+
+    /// Pixel size of an image file once turned upright by its EXIF orientation, read from its headers
+    fn upright_dimensions(&self, path: &Path) -> Result<(u32, u32)> {
+        let size = image_dimensions(path)?;
+        if !self.config.exif_orientation {
+            return Ok(size);
+        }
+        // EXIF数据位于文件开头的APP1段
+        let mut head = Vec::new();
+        std::io::Read::read_to_end(&mut std::io::Read::take(std::fs::File::open(path)?, EXIF_HEAD_LEN), &mut head)?;
+        Ok(upright_size(size, self.orientation_of(&head)))
+    }
+
+    /// Apply the configured processing to a decoded image and compute its placement
+    ///
+    /// Every conversion runs the same steps in this order:
+    ///
+    /// 1. turn upright by the EXIF `orientation`, see `exif_orientation`
+    /// 2. crop to the image's entry in `image_crops`, or to `crop`
+    /// 3. rotate by the image's entry in `rotations`
+    /// 4. replace the `chroma_key` background
+    /// 5. deskew
+    /// 6. trim borders
+    /// 7. run the image processor, see `with_image_processor`
+    /// 8. place by `scale_mode` into the page or grid cell, or at `native_dpi`
+    /// 9. downscale to the resolution limit of the placed size
+    /// 10. tint
+    ///
+    /// Images without a file (`image_path` is `None`) get the global `crop` only.
+    ///
+    /// Strip stacking and the page size limit are applied to the results
+    /// afterwards, when the pages are laid out.
+    fn process_image(&self, mut img: ::image::DynamicImage, image_path: Option<&Path>, orientation: Option<u8>, page: PageSize) -> (::image::DynamicImage, Placement) {
+        // 处理图片
+        if let Some(orientation) = orientation {
+            img = processing::apply_orientation(img, orientation);
+        }
+        if let Some(crop) = self.crop_for(image_path) {
+            let (width, height) = img.dimensions();
+            let ((x, y, crop_width, crop_height), clamped) = crop.pixel_rect(width, height);
+            if clamped {
+                log::warn!("⚠️ 裁剪区域超出图片范围，已限制在图片内: {}", image_path.map_or_else(String::new, |path| path.display().to_string()));
+            }
+            img = img.crop_imm(x, y, crop_width, crop_height);
+        }
+        if let Some(degrees) = image_path.and_then(|path| self.rotation_for(path)) {
+            img = processing::rotate(img, degrees);
+        }
+        if let Some(key) = self.config.chroma_key {
+            img = processing::chroma_key(&img, key.color, key.tolerance, key.replacement);
+        }
+        if self.config.deskew {
+            img = processing::deskew(img, self.config.deskew_max_angle);
+        }
+        if self.config.auto_trim {
+            img = processing::trim_borders(img, self.config.trim_tolerance);
+        }
+        if let Some(processor) = &self.image_processor {
+            img = processor(img);
+        }
+        let (img_width, img_height) = img.dimensions();
+        let placement = self.compute_placement(img_width, img_height, page);
+
+        // 超过最大DPI时降采样，缩放比例按新的像素尺寸重新计算
+        let img = match self.image_max_dpi(&img) {
+            Some(max_dpi) => self.downscale_to_dpi(img, placement.width_mm, placement.height_mm, max_dpi),
+            None => img,
+        };
+        // 降采样之后着色，处理的像素更少
+        let img = match self.config.tint {
+            Some(tint) => processing::duotone(&img, tint.shadow, tint.highlight),
+            None => img,
+        };
+        let (img_width, img_height) = img.dimensions();
+        let pixel_to_mm = 25.4 / self.config.dpi;
+        let placement = Placement {
+            scale_x: placement.width_mm / (img_width as f32 * pixel_to_mm),
+            scale_y: placement.height_mm / (img_height as f32 * pixel_to_mm),
+            ..placement
+        };
+
+        (img, placement)
+    }
+
+    /// Placement an image object ends up at when drawn with `transform`, ignoring rotation
+    fn transform_placement(&self, object: &ImageXObject, transform: &ImageTransform, page: PageSize) -> Placement {
+        // printpdf在未指定DPI时按300 DPI计算图片尺寸
+        let mm_per_pixel = 25.4 / transform.dpi.unwrap_or(300.0);
+        let width_mm = object.width.0 as f32 * mm_per_pixel * transform.scale_x.unwrap_or(1.0);
+        let height_mm = object.height.0 as f32 * mm_per_pixel * transform.scale_y.unwrap_or(1.0);
+        let pixel_to_mm = 25.4 / self.config.dpi;
+        Placement {
+            page,
+            x_mm: transform.translate_x.map_or(0.0, |x| x.0),
+            y_mm: transform.translate_y.map_or(0.0, |y| y.0),
+            width_mm,
+            height_mm,
+            scale_x: width_mm / (object.width.0 as f32 * pixel_to_mm),
+            scale_y: height_mm / (object.height.0 as f32 * pixel_to_mm),
+        }
+    }
+
+    /// Fit an image of the given pixel size into the page margins, centered
+    fn compute_placement(&self, img_width: u32, img_height: u32, page: PageSize) -> Placement {
+        // 原生DPI模式：页面尺寸由图片决定，不做适配缩放
+        if let Some(native_dpi) = self.config.native_dpi {
+            let width_mm = img_width as f32 * 25.4 / native_dpi;
+            let height_mm = img_height as f32 * 25.4 / native_dpi;
+            let margin_mm = self.config.effective_margin_mm();
+            let scale = self.config.dpi / native_dpi;
+            return Placement {
+                page: PageSize::new(width_mm + 2.0 * margin_mm, height_mm + 2.0 * margin_mm),
+                x_mm: margin_mm,
+                y_mm: margin_mm,
+                width_mm,
+                height_mm,
+                scale_x: scale,
+                scale_y: scale,
+            };
+        }
+
+        // 计算缩放和位置（居中显示，适配页面或网格单元格）
+        let (available_width_mm, available_height_mm) = match self.active_grid() {
+            Some(grid) => self.grid_cell_size(page, grid),
+            None => (page.width_mm - (2.0 * self.config.effective_margin_mm()), page.height_mm - (2.0 * self.config.effective_margin_mm())),
+        };
+        // 页边距超出页面时退化为最小可用空间，避免负缩放导致图片翻转
+        if available_width_mm < MIN_AVAILABLE_MM || available_height_mm < MIN_AVAILABLE_MM {
+            log::warn!("⚠️ 页边距 {:.1}mm 对于 {:.1}x{:.1}mm 的页面过大，可用空间按 {:.1}mm 计算",
+                self.config.effective_margin_mm(), page.width_mm, page.height_mm, MIN_AVAILABLE_MM);
+        }
+        let available_width_mm = available_width_mm.max(MIN_AVAILABLE_MM);
+        let available_height_mm = available_height_mm.max(MIN_AVAILABLE_MM);
+        
+        // 根据DPI进行转换
+        let pixel_to_mm = 25.4 / self.config.dpi;
+        let img_width_mm = img_width as f32 * pixel_to_mm;
+        let img_height_mm = img_height as f32 * pixel_to_mm;
+        
+        let scale = match self.config.scale_mode {
+            // 长条模式下页面高度随图片增长，只按宽度适配
+            ScaleMode::Fit if self.config.strip_mode.is_some() => available_width_mm / img_width_mm,
+            ScaleMode::Fit => (available_width_mm / img_width_mm).min(available_height_mm / img_height_mm),
+            ScaleMode::FixedPercent(percent) => percent / 100.0,
+            ScaleMode::FitWidth => available_width_mm / img_width_mm,
+        };
+        let display_width_mm = img_width_mm * scale;
+        let display_height_mm = img_height_mm * scale;
+        // 只按宽度适配时页面高度随图片变化，长条模式另行排版
+        let page = match self.config.scale_mode {
+            ScaleMode::FitWidth if self.config.strip_mode.is_none() => {
+                PageSize::new(page.width_mm, display_height_mm + 2.0 * self.config.effective_margin_mm())
+            }
+            _ => page,
+        };
+        let (x_mm, y_mm) = centered_origin(page, display_width_mm, display_height_mm);
+
+        // 调试信息
+        log::debug!("  📊 图片原始尺寸: {}x{} px", img_width, img_height);
+        log::debug!("  📏 转换为mm: {:.1}x{:.1} mm", img_width_mm, img_height_mm);
+        log::debug!("  📐 可用空间: {:.1}x{:.1} mm", available_width_mm, available_height_mm);
+        log::debug!("  🔍 缩放比例: {:.3}", scale);
+        log::debug!("  📍 显示尺寸: {:.1}x{:.1} mm", display_width_mm, display_height_mm);
+        log::debug!("  🎯 位置: ({:.1}, {:.1}) mm", x_mm, y_mm);
+
+        Placement {
+            page,
+            x_mm,
+            y_mm,
+            width_mm: display_width_mm,
+            height_mm: display_height_mm,
+            scale_x: scale,
+            scale_y: scale,
+        }
+    }
+
+    /// Resolution limit of placed images from `print_dpi`, or `max_dpi` and `low_memory`; none in native DPI mode
+    fn effective_max_dpi(&self) -> Option<f32> {
+        if self.config.native_dpi.is_some() {
+            return None;
+        }
+        if let Some(print_dpi) = self.config.print_dpi {
+            return Some(print_dpi);
+        }
+        // 低内存模式下图片分辨率不超过输出DPI
+        let low_memory_dpi = self.config.low_memory.then_some(self.config.dpi);
+        lower_limit(self.config.max_dpi, low_memory_dpi)
+    }
+
+    /// Content-based resolution limits; none in native DPI mode and with `print_dpi`
+    fn content_dpi(&self) -> Option<ContentDpi> {
+        self.config.content_dpi.filter(|_| self.config.native_dpi.is_none() && self.config.print_dpi.is_none())
+    }
+
+    /// Whether `content_dpi` classifies an image as text-like
+    fn is_text_like(&self, img: &::image::DynamicImage) -> bool {
+        self.content_dpi().is_some_and(|content| {
+            processing::is_text_like(img, content.min_ink_paper_ratio, content.max_saturation)
+        })
+    }
+
+    /// Resolution limit of one image, adding its content-based limit to `effective_max_dpi`
+    fn image_max_dpi(&self, img: &::image::DynamicImage) -> Option<f32> {
+        let content_dpi = self.content_dpi().map(|content| {
+            let text_like = self.is_text_like(img);
+            log::debug!("  内容分类: {}", if text_like { "文字" } else { "照片" });
+            if text_like { content.text_dpi } else { content.photo_dpi }
+        });
+        lower_limit(self.effective_max_dpi(), content_dpi)
+    }
+
+    /// Resolution limit every image stays below regardless of its content
+    fn lowest_max_dpi(&self) -> Option<f32> {
+        let content_dpi = self.content_dpi().map(|content| content.text_dpi.min(content.photo_dpi));
+        lower_limit(self.effective_max_dpi(), content_dpi)
+    }
+
+    /// Downscale an image whose effective resolution on the page exceeds `max_dpi`
+    fn downscale_to_dpi(
+        &self,
+        img: ::image::DynamicImage,
+        display_width_mm: f32,
+        display_height_mm: f32,
+        max_dpi: f32,
+    ) -> ::image::DynamicImage {
+        let (max_width, max_height) = max_pixel_size(display_width_mm, display_height_mm, max_dpi);
+        let (img_width, img_height) = img.dimensions();
+
+        if img_width <= max_width && img_height <= max_height {
+            return img;
+        }
+
+        log::debug!("  降采样: {}x{} -> {}x{} px ({:?})",
+            img_width, img_height, max_width, max_height, self.config.resize_filter);
+        img.resize_exact(max_width, max_height, self.config.resize_filter.into())
+    }
+}
+
+/// Bottom-left corner that centers a displayed image of the given size on the page
+///
+/// PDF pages have their origin in the bottom-left corner with the Y axis
+/// pointing up, and images are drawn up and to the right from the translated
+/// origin, so the gap below the image equals the gap above it when
+/// `y = (page height - image height) / 2`. Centering on the whole page is the
+/// same as centering within equal margins, and keeps the image on the page
+/// when the available space was clamped.
+fn centered_origin(page: PageSize, width_mm: f32, height_mm: f32) -> (f32, f32) {
+    ((page.width_mm - width_mm) / 2.0, (page.height_mm - height_mm) / 2.0)
+}
+
+/// Clipping path with the outline of a placed image with rounded corners
+fn rounded_rect_clip(placement: &Placement, radius_mm: f32) -> Polygon {
+    // 三次贝塞尔曲线近似四分之一圆的控制点系数
+    const KAPPA: f32 = 0.552_284_8;
+
+    let (x0, y0) = (placement.x_mm, placement.y_mm);
+    let (x1, y1) = (x0 + placement.width_mm, y0 + placement.height_mm);
+    let r = radius_mm.min(placement.width_mm / 2.0).min(placement.height_mm / 2.0);
+    let k = r * (1.0 - KAPPA);
+    let point = |x: f32, y: f32, next_is_curve: bool| (Point::new(Mm(x), Mm(y)), next_is_curve);
+
+    // 标记为true的点后面跟着曲线的两个控制点
+    let ring = vec![
+        point(x0 + r, y0, false),
+        point(x1 - r, y0, true),
+        point(x1 - k, y0, true),
+        point(x1, y0 + k, false),
+        point(x1, y0 + r, false),
+        point(x1, y1 - r, true),
+        point(x1, y1 - k, true),
+        point(x1 - k, y1, false),
+        point(x1 - r, y1, false),
+        point(x0 + r, y1, true),
+        point(x0 + k, y1, true),
+        point(x0, y1 - k, false),
+        point(x0, y1 - r, false),
+        point(x0, y0 + r, true),
+        point(x0, y0 + k, true),
+        point(x0 + k, y0, false),
+        point(x0 + r, y0, false),
+    ];
+
+    Polygon {
+        rings: vec![ring],
+        mode: path::PaintMode::Clip,
+        winding_order: path::WindingOrder::NonZero,
+    }
+}
+
+/// Shrink a page and everything on it uniformly until both sides are within `MAX_PAGE_SIDE_MM`
+fn fit_page_limit(placement: &mut Placement) {
+    let factor = (MAX_PAGE_SIDE_MM / placement.page.width_mm).min(MAX_PAGE_SIDE_MM / placement.page.height_mm);
+    if factor >= 1.0 {
+        return;
+    }
+    // 略小于上限，避免换算成点时的舍入误差越界
+    let factor = factor * 0.9999;
+
+    log::warn!("⚠️ 页面 {:.0}x{:.0}mm 超过PDF上限 {:.0}mm，按 {:.3} 等比缩小",
+        placement.page.width_mm, placement.page.height_mm, MAX_PAGE_SIDE_MM, factor);
+    *placement = Placement {
+        page: PageSize::new(placement.page.width_mm * factor, placement.page.height_mm * factor),
+        x_mm: placement.x_mm * factor,
+        y_mm: placement.y_mm * factor,
+        width_mm: placement.width_mm * factor,
+        height_mm: placement.height_mm * factor,
+        scale_x: placement.scale_x * factor,
+        scale_y: placement.scale_y * factor,
+    };
+}
+
+/// Enlarge every page to the largest width and height among them, keeping each image centered on its page
+fn pad_to_largest_page(images: &mut [PreparedImage]) {
+    let width_mm = images.iter().map(|image| image.placement.page.width_mm).fold(0.0, f32::max);
+    let height_mm = images.iter().map(|image| image.placement.page.height_mm).fold(0.0, f32::max);
+    for image in images {
+        let placement = &mut image.placement;
+        placement.x_mm += (width_mm - placement.page.width_mm) / 2.0;
+        placement.y_mm += (height_mm - placement.page.height_mm) / 2.0;
+        placement.page = PageSize::new(width_mm, height_mm);
+    }
+}
+
+/// Place images below each other on one page as wide as the widest of their pages
+///
+/// The page height is the sum of the image heights, the gaps between them
+/// and the top and bottom margins; images stay horizontally centered.
+fn stack_strip(images: &mut [PreparedImage], margin_mm: f32, gap_mm: f32) {
+    let width_mm = images.iter().map(|image| image.placement.page.width_mm).fold(0.0, f32::max);
+    let images_height_mm: f32 = images.iter().map(|image| image.placement.height_mm).sum();
+    let gaps_mm = gap_mm * images.len().saturating_sub(1) as f32;
+    let page = PageSize::new(width_mm, images_height_mm + gaps_mm + 2.0 * margin_mm);
+
+    // PDF坐标Y轴向上，从页面顶部依次向下排列
+    let mut top_mm = page.height_mm - margin_mm;
+    for image in images {
+        let placement = &mut image.placement;
+        placement.page = page;
+        placement.x_mm = (page.width_mm - placement.width_mm) / 2.0;
+        placement.y_mm = top_mm - placement.height_mm;
+        top_mm = placement.y_mm - gap_mm;
+    }
+}
+
+/// Crop from each image the rows it repeats from the bottom of the image before it
+fn remove_strip_overlaps(images: &mut [PreparedImage], overlap: StripOverlap) {
+    // 每个采样的通道数和字节数，压缩数据无法逐行比较
+    let layout = |object: &ImageXObject| {
+        let channels = match object.color_space {
+            ColorSpace::Greyscale => 1,
+            ColorSpace::Rgb => 3,
+            ColorSpace::Cmyk => 4,
+            _ => return None,
+        };
+        let sample_len = match object.bits_per_component {
+            ColorBits::Bit8 => 1,
+            ColorBits::Bit16 => 2,
+            ColorBits::Bit1 => return None,
+        };
+        object.image_filter.is_none().then_some((object.width.0, channels, sample_len))
+    };
+
+    for index in 1..images.len() {
+        let (before, after) = images.split_at_mut(index);
+        let (previous, next) = (&before[index - 1], &mut after[0]);
+        let Some((width, channels, sample_len)) = layout(&next.object) else { continue };
+        if layout(&previous.object) != Some((width, channels, sample_len)) {
+            continue;
+        }
+
+        // 至少保留一行
+        let row_len = width * channels * sample_len;
+        let height = next.object.height.0;
+        let max_rows = (overlap.max_overlap_px as usize).min(height.saturating_sub(1));
+        let rows = processing::vertical_overlap(&previous.object.image_data, &next.object.image_data, row_len, sample_len, overlap.similarity, max_rows);
+        if rows == 0 {
+            continue;
+        }
+
+        log::debug!("  去除重叠的 {} 行: {}", rows, next.path.display());
+        next.object.image_data.drain(..rows * row_len);
+        next.object.height = Px(height - rows);
+        next.placement.height_mm *= (height - rows) as f32 / height as f32;
+    }
+}
+
+/// Configuration for converting one part of a document that is merged later
+///
+/// Settings that apply to the finished document as a whole are left to the
+/// merge: the table of contents, notes and bookmarks would point into the
+/// part, and the cover, metadata and encryption are added once.
+fn part_config(config: &PdfConfig) -> PdfConfig {
+    let mut part = config.clone();
+    part.checkpoint = None;
+    part.generate_toc = false;
+    part.notes.clear();
+    part.bookmarks = false;
+    part.page_boxes.clear();
+    part.cover_pdf = None;
+    part.xmp_metadata = None;
+    part.language = None;
+    part.encryption = None;
+    part.debug_images = false;
+    part.target_pages = None;
+    #[cfg(feature = "open")]
+    {
+        part.open_after = false;
+    }
+    part
+}
+
+/// Save the pixels of an embedded image for `PdfConfig::debug_images`
+///
+/// `page` is the 1-based page number and `position` the place on the page
+/// when a page holds several images.
+fn save_debug_image(object: &ImageXObject, output: &Path, page: usize, position: Option<usize>) {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let dir = output.with_file_name(format!("{}-images", stem));
+    let name = match position {
+        Some(position) => format!("page-{:04}-{}", page, position),
+        None => format!("page-{:04}", page),
+    };
+
+    let (width, height) = (object.width.0 as u32, object.height.0 as u32);
+    let data = &object.image_data;
+    let result = std::fs::create_dir_all(&dir).map_err(PdfError::from).and_then(|()| {
+        if object.image_filter.is_some() {
+            // 直接嵌入的JPEG数据原样保存
+            std::fs::write(dir.join(format!("{}.jpg", name)), data)?;
+            return Ok(());
+        }
+        let img = match (object.color_space, object.bits_per_component) {
+            (ColorSpace::Greyscale, ColorBits::Bit1) => {
+                let row_len = (width as usize).div_ceil(8);
+                Some(::image::GrayImage::from_fn(width, height, |x, y| {
+                    let byte = data[y as usize * row_len + x as usize / 8];
+                    ::image::Luma([if byte & (0x80 >> (x % 8)) != 0 { 255 } else { 0 }])
+                })
+                .into())
+            }
+            (ColorSpace::Greyscale, _) => ::image::GrayImage::from_raw(width, height, data.clone()).map(Into::into),
+            (ColorSpace::Cmyk, _) => {
+                // 简单换算为RGB，仅供查看
+                let rgb = data
+                    .chunks_exact(4)
+                    .flat_map(|cmyk| [0, 1, 2].map(|c| (255 - cmyk[c] as u32) * (255 - cmyk[3] as u32) / 255).map(|v| v as u8))
+                    .collect();
+                ::image::RgbImage::from_raw(width, height, rgb).map(Into::into)
+            }
+            (_, ColorBits::Bit16) => {
+                let samples = data.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+                ::image::ImageBuffer::<::image::Rgb<u16>, _>::from_raw(width, height, samples).map(::image::DynamicImage::ImageRgb16)
+            }
+            _ => ::image::RgbImage::from_raw(width, height, data.clone()).map(Into::into),
+        };
+        let img: ::image::DynamicImage = img.ok_or_else(|| PdfError::Custom("image data does not match its size".to_string()))?;
+        img.save(dir.join(format!("{}.png", name)))?;
+        Ok(())
+    });
+    if let Err(e) = result {
+        log::warn!("⚠️ 无法保存调试图片 {}: {}", name, e);
+    }
+}
+
+/// Write `bytes` to `output`, repeating failed attempts as the policy allows
+///
+/// Only IO errors are retried; the error of the last attempt is returned.
+/// With `sync` the file is synced to disk as part of each attempt.
+fn write_with_retry(output: &Path, bytes: &[u8], retry: RetryPolicy, sync: bool) -> Result<()> {
+    let write = || -> std::io::Result<()> {
+        let mut file = std::fs::File::create(output)?;
+        file.write_all(bytes)?;
+        if sync {
+            file.sync_all()?;
+        }
+        Ok(())
+    };
+
+    let mut backoff = retry.backoff;
+    let mut attempt = 1;
+    loop {
+        match write() {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < retry.attempts => {
+                log::warn!("⚠️ 写入失败（第{}次，共{}次）: {}，{:?}后重试", attempt, retry.attempts, e, backoff);
+                std::thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Path around the area inside the margins of a page
+fn margin_polygon(page: PageSize, margin_mm: f32, mode: path::PaintMode) -> Polygon {
+    let (x0, y0) = (margin_mm.min(page.width_mm / 2.0), margin_mm.min(page.height_mm / 2.0));
+    let (x1, y1) = (page.width_mm - x0, page.height_mm - y0);
+    let point = |x: f32, y: f32| (Point::new(Mm(x), Mm(y)), false);
+
+    Polygon {
+        rings: vec![vec![point(x0, y0), point(x1, y0), point(x1, y1), point(x0, y1)]],
+        mode,
+        winding_order: path::WindingOrder::NonZero,
+    }
+}
+
+/// Add the crop boxes and source file records to the page dictionaries
+///
+/// printpdf replaces the extra entries of a page on every `extend_with`
+/// call, so all entries of a page are collected into one dictionary.
+fn extend_pages(
+    doc: &PdfDocumentReference,
+    pages: &[(PdfPageIndex, PageSize)],
+    crop_box: Option<CropBox>,
+    source_files: &[(PdfPageIndex, PathBuf)],
+    locations: &[(PdfPageIndex, (f64, f64))],
+) {
+    for (page_index, page) in pages {
+        let mut entries = lopdf::Dictionary::new();
+        if let Some(crop_box) = crop_box {
+            let rect = crop_rect(*page, crop_box);
+            entries.set("CropBox", rect.clone());
+            entries.set("TrimBox", rect);
+        }
+
+        let files: Vec<lopdf::Object> = source_files
+            .iter()
+            .filter(|(index, _)| index == page_index)
+            .map(|(_, path)| text_string(&path.to_string_lossy()))
+            .collect();
+        let page_locations: Vec<lopdf::Object> = locations
+            .iter()
+            .filter(|(index, _)| index == page_index)
+            .map(|(_, (latitude, longitude))| lopdf::Object::Array(vec![lopdf::Object::Real(*latitude as f32), lopdf::Object::Real(*longitude as f32)]))
+            .collect();
+        if !files.is_empty() || !page_locations.is_empty() {
+            let mut private = lopdf::Dictionary::new();
+            if !files.is_empty() {
+                private.set("SourceFiles", files);
+            }
+            if !page_locations.is_empty() {
+                private.set("Locations", page_locations);
+            }
+            // 页面片段字典要求数据和页面都带修改时间
+            let modified = lopdf::Object::from(printpdf::OffsetDateTime::now_utc());
+            let data = lopdf::Dictionary::from_iter(vec![
+                ("LastModified", modified.clone()),
+                ("Private", private.into()),
+            ]);
+            entries.set("PieceInfo", lopdf::Dictionary::from_iter(vec![("PdfConverter", lopdf::Object::from(data))]));
+            entries.set("LastModified", modified);
+        }
+
+        if !entries.is_empty() {
+            doc.get_page(*page_index).extend_with(entries);
+        }
+    }
+}
+
+/// Crop box of a page, leaving its media box at the full page size
+///
+/// printpdf writes the crop and trim boxes equal to the media box; both are
+/// replaced, so the trim box marks the finished page for printers too.
+fn crop_rect(page: PageSize, crop_box: CropBox) -> Vec<lopdf::Object> {
+    // 裁切框不能为空或超出页面
+    let left_mm = crop_box.left_mm.clamp(0.0, page.width_mm);
+    let bottom_mm = crop_box.bottom_mm.clamp(0.0, page.height_mm);
+    let right_mm = (page.width_mm - crop_box.right_mm).clamp(left_mm, page.width_mm);
+    let top_mm = (page.height_mm - crop_box.top_mm).clamp(bottom_mm, page.height_mm);
+    [left_mm, bottom_mm, right_mm, top_mm]
+        .iter()
+        .map(|mm| lopdf::Object::Real(mm * text::PT_PER_MM))
+        .collect()
+}
+
+/// PDF text string: plain bytes for ASCII, otherwise UTF-16BE with a byte order mark
+fn text_string(text: &str) -> lopdf::Object {
+    if text.is_ascii() {
+        return lopdf::Object::string_literal(text);
+    }
+    let bytes = [0xFE, 0xFF]
+        .into_iter()
+        .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+        .collect();
+    lopdf::Object::String(bytes, lopdf::StringFormat::Literal)
+}
+
+/// Re-encode the document information and bookmark titles printpdf writes as plain UTF-8
+///
+/// Readers decode text strings without a byte order mark as PDFDocEncoding,
+/// which garbles anything outside ASCII. Strings that already carry a byte
+/// order mark or are not UTF-8, e.g. from inserted PDF files, are kept.
+fn encode_text_strings(document: &mut lopdf::Document) {
+    let encode = |object: &mut lopdf::Object| {
+        if let lopdf::Object::String(bytes, _) = object {
+            if !bytes.is_ascii() && !bytes.starts_with(&[0xFE, 0xFF]) {
+                if let Ok(text) = std::str::from_utf8(bytes) {
+                    *object = text_string(text);
+                }
+            }
+        }
+    };
+
+    if let Ok(info) = document.trailer.get(b"Info").and_then(lopdf::Object::as_reference) {
+        if let Ok(info) = document.get_dictionary_mut(info) {
+            info.iter_mut().for_each(|(_, value)| encode(value));
+        }
+    }
+
+    // 沿 First/Next 遍历书签树，防止损坏的文件形成环
+    let mut pending: Vec<lopdf::ObjectId> = document
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Outlines"))
+        .and_then(lopdf::Object::as_reference)
+        .into_iter()
+        .collect();
+    let mut visited = std::collections::HashSet::new();
+    while let Some(id) = pending.pop() {
+        if !visited.insert(id) {
+            continue;
+        }
+        let Ok(item) = document.get_dictionary_mut(id) else { continue };
+        if let Ok(title) = item.get_mut(b"Title") {
+            encode(title);
+        }
+        pending.extend([b"First".as_slice(), b"Next"].into_iter().filter_map(|key| item.get(key).and_then(lopdf::Object::as_reference).ok()));
+    }
+}
+
+/// Which frames of an animation with the given frame delays become pages
+fn sampled_frames(delays: &[Duration], sampling: FrameSampling) -> Vec<bool> {
+    let mut frame_start = Duration::ZERO;
+    let mut next_sample = Duration::ZERO;
+    delays
+        .iter()
+        .map(|&delay| {
+            // 浏览器把过短的帧延迟按100毫秒播放
+            let delay = if delay <= Duration::from_millis(10) { Duration::from_millis(100) } else { delay };
+            let frame_end = frame_start + delay;
+            frame_start = frame_end;
+            match sampling {
+                FrameSampling::Interval(interval) if !interval.is_zero() => {
+                    // 同一帧覆盖多个采样时刻时只取一次
+                    let sampled_here = next_sample < frame_end;
+                    while next_sample < frame_end {
+                        next_sample += interval;
+                    }
+                    sampled_here
+                }
+                _ => true,
+            }
+        })
+        .collect()
+}
+
+/// Whether a file name has the `.pdf` extension, compared case-insensitively
+fn is_pdf_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"))
+}
+
+/// Whether a file name has one of the supported image extensions
+///
+/// Only the last extension counts, compared case-insensitively, so
+/// `scan.tar.JPG` matches while `photo.jpg.bak` and a hidden file named
+/// `.jpg` do not.
+fn is_image_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|ext_str| SUPPORTED_EXTENSIONS.iter().any(|supported| ext_str.eq_ignore_ascii_case(supported)))
+}
+
+/// Grid with at least `cells` cells, about as many rows as columns and more rows on portrait pages
+fn target_grid(cells: usize, page: PageSize) -> Grid {
+    let long = (cells as f64).sqrt().ceil() as usize;
+    let short = cells.div_ceil(long);
+    match page.width_mm <= page.height_mm {
+        true => Grid::new(short, long),
+        false => Grid::new(long, short),
+    }
+}
+
+/// Append blank pages the size of the last page until the document has `target` pages
+///
+/// Fails with `PdfError::Config` when the document is already longer.
+fn pad_pages(document: &mut printpdf::lopdf::Document, target: usize) -> Result<()> {
+    use printpdf::lopdf::{Dictionary, Object};
+
+    let pages = document.get_pages();
+    if pages.len() > target {
+        log::warn!("⚠️ 文档共 {} 页，超过目标页数 {}", pages.len(), target);
+        return Err(PdfError::Config { name: "target_pages".to_string(), value: target.to_string() });
+    }
+    if pages.len() == target {
+        return Ok(());
+    }
+    let Some(&last_id) = pages.values().last() else { return Ok(()) };
+    // 空白页沿用最后一页的页面框，不带内容
+    let mut blank = document.get_dictionary(last_id)?.clone();
+    for key in [&b"Contents"[..], b"Annots", b"PieceInfo", b"Group"] {
+        blank.remove(key);
+    }
+    blank.set("Resources", Dictionary::new());
+    let pages_id = blank.get(b"Parent")?.as_reference()?;
+
+    log::debug!("  补充 {} 个空白页", target - pages.len());
+    for _ in pages.len()..target {
+        let page_id = document.add_object(blank.clone());
+        let tree = document.get_object_mut(pages_id)?.as_dict_mut()?;
+        tree.get_mut(b"Kids")?.as_array_mut()?.push(Object::Reference(page_id));
+        let count = tree.get(b"Count")?.as_i64()?;
+        tree.set("Count", count + 1);
+    }
+    Ok(())
+}
+
+/// Flate-compress every unfiltered stream except XMP metadata
+///
+/// Metadata streams stay uncompressed so tools scanning files for XMP packets
+/// find them, as PDF/A requires.
+fn compress_streams(document: &mut printpdf::lopdf::Document) {
+    for object in document.objects.values_mut() {
+        let Ok(stream) = object.as_stream_mut() else { continue };
+        if stream.dict.get(b"Type").and_then(printpdf::lopdf::Object::as_name).ok() == Some(b"Metadata") {
+            continue;
+        }
+        // 压缩失败时保留未压缩的数据
+        let _ = stream.compress();
+    }
+}
+
+/// Whether image file data is empty or lacks the end marker of its format
+///
+/// Only JPEG, PNG and GIF files have an end marker; other formats are never
+/// reported as truncated.
+fn is_truncated(bytes: &[u8]) -> bool {
+    // 部分程序会在文件末尾补零
+    let end = bytes.iter().rposition(|&byte| byte != 0).map_or(0, |last| last + 1);
+    let trimmed = &bytes[..end];
+    match ::image::guess_format(bytes) {
+        _ if bytes.is_empty() => true,
+        Ok(::image::ImageFormat::Jpeg) => !trimmed.ends_with(&[0xFF, 0xD9]),
+        Ok(::image::ImageFormat::Png) => !trimmed.ends_with(b"IEND\xAE\x42\x60\x82"),
+        Ok(::image::ImageFormat::Gif) => !trimmed.ends_with(&[0x3B]),
+        _ => false,
+    }
+}
+
+/// Check that `bytes` start like an image file, describing what they are otherwise
+fn check_image_magic(bytes: &[u8]) -> std::result::Result<(), String> {
+    #[cfg(feature = "psd")]
+    if psd::is_psd(bytes) {
+        return Ok(());
+    }
+    if bytes.is_empty() {
+        return Err("empty file".to_string());
+    }
+    if ::image::guess_format(bytes).is_ok() {
+        return Ok(());
+    }
+
+    // 常见的非图片类型给出明确说明，其余列出开头字节
+    let known: &[(&[u8], &str)] = &[
+        (b"MZ", "Windows executable"),
+        (b"\x7FELF", "ELF executable"),
+        (b"\xCF\xFA\xED\xFE", "Mach-O executable"),
+        (b"\xCE\xFA\xED\xFE", "Mach-O executable"),
+        (b"\xCA\xFE\xBA\xBE", "Mach-O executable"),
+        (b"#!", "script"),
+        (b"%PDF", "PDF document"),
+        (b"PK\x03\x04", "ZIP archive"),
+    ];
+    match known.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        Some((_, kind)) => Err(kind.to_string()),
+        None => {
+            let start: Vec<String> = bytes.iter().take(8).map(|byte| format!("{:02X}", byte)).collect();
+            Err(format!("unrecognized content starting with {}", start.join(" ")))
+        }
+    }
+}
+
+/// Path with redundant separators, trailing separators and `.` components removed
+///
+/// Windows accepts both `/` and `\\` as separators; the components come out
+/// joined with the platform's own separator, so mixed input yields uniform paths.
+fn normalize_path(path: &Path) -> PathBuf {
+    path.components().collect()
+}
+
+/// Path of a ZIP entry, whose name may separate directories with `/` or `\\`
+///
+/// The ZIP format prescribes `/`, but archives written by some Windows tools
+/// use backslashes, which other platforms would take as part of the file name.
+fn archive_entry_path(name: &str) -> PathBuf {
+    name.split(['/', '\\']).filter(|part| !part.is_empty()).collect()
+}
+
+/// Read the next length-prefixed blob, `None` at end of input between blobs
+fn read_blob<R: Read>(reader: &mut R) -> Result<Option<Vec<u8>>> {
+    let mut prefix = [0u8; 4];
+    let mut filled = 0;
+    while filled < prefix.len() {
+        match reader.read(&mut prefix[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    // 长度来自输入，逐步读取以免按错误的长度预先分配
+    let len = u32::from_be_bytes(prefix) as u64;
+    let mut bytes = Vec::new();
+    reader.by_ref().take(len).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < len {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(Some(bytes))
+}
+
+/// Value of an environment variable, `None` when unset
+fn env_value(name: &str) -> Result<Option<String>> {
+    match std::env::var(name) {
+        Ok(value) => Ok(Some(value)),
+        Err(std::env::VarError::NotPresent) => Ok(None),
+        Err(std::env::VarError::NotUnicode(value)) => Err(PdfError::Config {
+            name: name.to_string(),
+            value: value.to_string_lossy().into_owned(),
+        }),
+    }
+}
+
+/// Finite number from an environment variable, `None` when unset
+fn env_number(name: &str) -> Result<Option<f32>> {
+    let Some(value) = env_value(name)? else { return Ok(None) };
+    match value.trim().parse::<f32>() {
+        Ok(number) if number.is_finite() => Ok(Some(number)),
+        _ => Err(PdfError::Config { name: name.to_string(), value }),
+    }
+}
+
+/// The lower of two optional resolution limits
+fn lower_limit(a: Option<f32>, b: Option<f32>) -> Option<f32> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Pixel size of an image file, read from its header
+fn image_dimensions(path: &Path) -> Result<(u32, u32)> {
+    #[cfg(feature = "psd")]
+    {
+        let mut header = Vec::new();
+        std::io::Read::read_to_end(&mut std::io::Read::take(std::fs::File::open(path)?, psd::HEADER_LEN as u64), &mut header)?;
+        if let Some(size) = psd::dimensions(&header) {
+            return Ok(size);
+        }
+    }
+    Ok(::image::image_dimensions(path)?)
+}
+
+/// Pixel size after turning an image upright, with width and height swapped for the orientations that turn it by 90 degrees
+fn upright_size((width, height): (u32, u32), orientation: Option<u8>) -> (u32, u32) {
+    match orientation {
+        Some(5..=8) => (height, width),
+        _ => (width, height),
+    }
+}
+
+/// Pixel size of an image held in memory, read from its header
+fn image_dimensions_in(bytes: Vec<u8>) -> Result<(u32, u32)> {
+    #[cfg(feature = "psd")]
+    if let Some(size) = psd::dimensions(&bytes) {
+        return Ok(size);
+    }
+    Ok(::image::io::Reader::new(std::io::Cursor::new(bytes)).with_guessed_format()?.into_dimensions()?)
+}
+
+/// Largest pixel size of an image displayed at the given size without exceeding `max_dpi`
+fn max_pixel_size(display_width_mm: f32, display_height_mm: f32, max_dpi: f32) -> (u32, u32) {
+    (
+        (display_width_mm / 25.4 * max_dpi).round().max(1.0) as u32,
+        (display_height_mm / 25.4 * max_dpi).round().max(1.0) as u32,
+    )
 }
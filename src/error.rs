@@ -14,18 +14,131 @@ pub enum PdfError {
     #[error("Image error: {0}")]
     Image(#[from] image::ImageError),
 
+    /// Image file could not be decoded because its format is not supported
+    #[error("Unsupported image format ({format}): {path}")]
+    UnsupportedFormat {
+        /// Path of the offending image
+        path: String,
+        /// Format detected from the file contents, or `unknown`
+        format: String,
+    },
+
+    /// File whose contents are not an image, found before decoding by `PdfConfig::validate_image_magic`
+    #[error("Not an image file ({reason}): {path}")]
+    NotAnImage {
+        /// Path of the offending file
+        path: String,
+        /// What the file is instead, e.g. `empty file` or `Windows executable`
+        reason: String,
+    },
+
+    /// Image whose decoded pixels would exceed `ResourceLimits::max_decoded_bytes`
+    #[error("Image exceeds the decoded size limit of {limit} bytes: {path}")]
+    ImageTooLarge {
+        /// Path of the offending image
+        path: String,
+        /// Limit in effect, in bytes
+        limit: u64,
+    },
+
+    /// Image file that ends before its data does, e.g. because it is still being written
+    #[error("Image file appears truncated or incomplete (still being written?): {0}")]
+    TruncatedImage(String),
+
+    /// Image of a conversion that could not be read, decoded or processed
+    #[error("Failed to convert image {} ({}): {source}", .index + 1, .path.display())]
+    ImageAt {
+        /// Path of the image, or its `Image N` title for images without a file
+        path: std::path::PathBuf,
+        /// Position of the image in page order, starting at 0
+        index: usize,
+        /// What went wrong
+        source: Box<PdfError>,
+    },
+
     /// PDF generation error
     #[error("PDF error: {0}")]
     Pdf(#[from] printpdf::Error),
 
+    /// Error reading or writing the low-level PDF structure
+    #[error("PDF structure error: {0}")]
+    PdfStructure(#[from] printpdf::lopdf::Error),
+
+    /// ZIP archive could not be read
+    #[error("ZIP error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+
     /// No images found in the specified folder
     #[error("No images found in folder: {0}")]
     NoImagesFound(String),
 
+    /// File name has no page number for the configured page order
+    #[error("No page number in file name: {0}")]
+    UnorderedFile(String),
+
     /// Invalid folder path
     #[error("Invalid folder path: {0}")]
     InvalidPath(String),
 
+    /// Font file could not be parsed
+    #[error("Invalid font file: {0}")]
+    InvalidFont(String),
+
+    /// Data too long or otherwise not encodable as a QR code
+    #[cfg(feature = "qr")]
+    #[error("QR code error: {0}")]
+    Qr(#[from] qrcode::types::QrError),
+
+    /// Rotation sidecar file could not be parsed
+    #[error("Invalid sidecar file {path}: {reason}")]
+    InvalidSidecar {
+        /// Path of the sidecar file
+        path: String,
+        /// What is wrong with its contents
+        reason: String,
+    },
+
+    /// Project file could not be parsed
+    #[cfg(feature = "serde")]
+    #[error("Invalid project file {path}: {reason}")]
+    InvalidProject {
+        /// Path of the project file
+        path: String,
+        /// What is wrong with its contents
+        reason: String,
+    },
+
+    /// Configuration value that could not be parsed
+    #[error("Invalid configuration value for {name}: {value:?}")]
+    Config {
+        /// Name of the setting, e.g. the environment variable
+        name: String,
+        /// Value as given
+        value: String,
+    },
+
+    /// Settings or content that violate the requested PDF/X conformance
+    #[error("PDF/X conformance error: {0}")]
+    Conformance(String),
+
+    /// Some folders of a batch conversion failed
+    #[error("{} of {} folders failed to convert", .failures.len(), .failures.len() + .reports.len())]
+    Batch {
+        /// Reports of the folders that were converted
+        reports: Vec<crate::report::ConversionReport>,
+        /// Every folder that failed, with its error
+        failures: Vec<(std::path::PathBuf, PdfError)>,
+    },
+
+    /// Images of one conversion mix portrait and landscape orientations
+    #[error("Mixed image orientations: {} portrait and {} landscape images", .portrait.len(), .landscape.len())]
+    MixedOrientation {
+        /// Images taller than wide, in page order
+        portrait: Vec<String>,
+        /// Images wider than tall, in page order
+        landscape: Vec<String>,
+    },
+
     /// Custom error with message
     #[error("{0}")]
     Custom(String),
@@ -36,4 +149,13 @@ impl PdfError {
     pub fn custom<S: Into<String>>(message: S) -> Self {
         Self::Custom(message.into())
     }
+
+    /// Attach the path and page order position of the image the error occurred with
+    pub(crate) fn at_image(self, path: &std::path::Path, index: usize) -> Self {
+        Self::ImageAt {
+            path: path.to_path_buf(),
+            index,
+            source: Box::new(self),
+        }
+    }
 }
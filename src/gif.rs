@@ -0,0 +1,86 @@
+//! Frame timing of GIF files, read without decoding any pixels
+//!
+//! Counting the pages an animation contributes only needs the delay of every
+//! frame. These are read by walking the blocks of the file and skipping the
+//! compressed image data, which is much faster than decoding the frames.
+
+use std::time::Duration;
+
+/// Signatures of the two GIF versions
+const SIGNATURES: [&[u8]; 2] = [b"GIF87a", b"GIF89a"];
+
+/// Delay of every frame in order, `None` when `bytes` is not a readable GIF file
+///
+/// Frames without a graphic control extension have no delay, as with the
+/// `image` crate's decoder.
+pub(crate) fn frame_delays(bytes: &[u8]) -> Option<Vec<Duration>> {
+    if !SIGNATURES.iter().any(|signature| bytes.starts_with(signature)) {
+        return None;
+    }
+    let mut reader = Reader { bytes, position: 6 };
+    // 逻辑屏幕描述符之后可能跟着全局颜色表
+    let screen = reader.take(7)?;
+    reader.skip_color_table(screen[4])?;
+
+    let mut delays = Vec::new();
+    let mut delay = Duration::ZERO;
+    loop {
+        // 缺少结尾标记的文件在此结束，与常见解码器一致
+        let Some(&[introducer]) = reader.take(1) else { return Some(delays) };
+        match introducer {
+            // 扩展块：图形控制扩展记录下一帧的延迟，单位为百分之一秒
+            0x21 => {
+                let label = reader.take(1)?[0];
+                let first = reader.sub_blocks()?;
+                if let (0xF9, Some(&[_, low, high, ..])) = (label, first) {
+                    delay = Duration::from_millis(u16::from_le_bytes([low, high]) as u64 * 10);
+                }
+            }
+            // 图像描述符：跳过局部颜色表和压缩数据
+            0x2C => {
+                let descriptor = reader.take(9)?;
+                reader.skip_color_table(descriptor[8])?;
+                reader.take(1)?;
+                reader.sub_blocks()?;
+                delays.push(std::mem::take(&mut delay));
+            }
+            0x3B => return Some(delays),
+            _ => return None,
+        }
+    }
+}
+
+/// Reader over the blocks of a GIF file
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.position..self.position.checked_add(len)?)?;
+        self.position += len;
+        Some(slice)
+    }
+
+    /// Skip the color table announced by the packed field of a descriptor
+    fn skip_color_table(&mut self, packed: u8) -> Option<()> {
+        if packed & 0x80 != 0 {
+            self.take(3 << ((packed & 0x07) + 1))?;
+        }
+        Some(())
+    }
+
+    /// Skip a chain of data sub-blocks up to its terminator, returning the first one
+    fn sub_blocks(&mut self) -> Option<Option<&'a [u8]>> {
+        let mut first = None;
+        loop {
+            let len = self.take(1)?[0] as usize;
+            if len == 0 {
+                return Some(first);
+            }
+            let block = self.take(len)?;
+            first.get_or_insert(block);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! JPEG header inspection for embedding files without re-encoding
+
+/// Frame parameters of a JPEG file that PDF readers can decode directly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct JpegInfo {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Number of color components: 1 for grayscale, 3 for YCbCr/RGB
+    pub components: u8,
+}
+
+/// Read the frame header of a JPEG that a `DCTDecode` filter can embed as-is
+///
+/// Returns `None` for anything else: non-JPEG data, lossless or arithmetic
+/// coded frames, 12-bit precision and CMYK files, whose Adobe inversion
+/// conventions readers disagree on.
+pub(crate) fn read_header(bytes: &[u8]) -> Option<JpegInfo> {
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut pos = 2;
+    loop {
+        // 跳过标记前的填充字节
+        while *bytes.get(pos)? == 0xFF && *bytes.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        if *bytes.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *bytes.get(pos + 1)?;
+        let length = u16::from_be_bytes([*bytes.get(pos + 2)?, *bytes.get(pos + 3)?]) as usize;
+        let segment = bytes.get(pos + 4..pos + 2 + length)?;
+
+        match marker {
+            // 基线、扩展和渐进式DCT帧
+            0xC0..=0xC2 => {
+                let precision = *segment.first()?;
+                let height = u16::from_be_bytes([*segment.get(1)?, *segment.get(2)?]) as u32;
+                let width = u16::from_be_bytes([*segment.get(3)?, *segment.get(4)?]) as u32;
+                let components = *segment.get(5)?;
+                let supported = precision == 8 && width > 0 && height > 0 && matches!(components, 1 | 3);
+                return supported.then_some(JpegInfo { width, height, components });
+            }
+            // 其他帧类型或扫描开始前未找到帧头
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF | 0xDA | 0xD9 => return None,
+            _ => pos += 2 + length,
+        }
+    }
+}
+
+/// Length of a JPEG file up to and including its end-of-image marker
+///
+/// Follows the marker segments and the entropy-coded data of every scan, so
+/// data appended after the image, such as maker trailers or the video of a
+/// motion photo, is not taken for its end. Returns `None` when the data ends
+/// before the end marker, e.g. while the file is still being written.
+pub(crate) fn data_end(bytes: &[u8]) -> Option<usize> {
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut pos = 2;
+    loop {
+        // 跳过标记前的填充字节
+        while *bytes.get(pos)? == 0xFF && *bytes.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        if *bytes.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *bytes.get(pos + 1)?;
+        match marker {
+            0xD9 => return Some(pos + 2),
+            // 不带长度的标记
+            0x01 | 0xD0..=0xD7 => pos += 2,
+            _ => {
+                let length = u16::from_be_bytes([*bytes.get(pos + 2)?, *bytes.get(pos + 3)?]) as usize;
+                if length < 2 {
+                    return None;
+                }
+                bytes.get(pos + 2..pos + 2 + length)?;
+                pos += 2 + length;
+                if marker == 0xDA {
+                    pos = scan_end(bytes, pos)?;
+                }
+            }
+        }
+    }
+}
+
+/// Position of the first marker after the entropy-coded data starting at `pos`
+fn scan_end(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let ff = pos + bytes.get(pos..)?.iter().position(|&byte| byte == 0xFF)?;
+        match *bytes.get(ff + 1)? {
+            // 填充的0x00和复位标记属于熵编码数据
+            0x00 | 0xD0..=0xD7 => pos = ff + 2,
+            0xFF => pos = ff + 1,
+            _ => return Some(ff),
+        }
+    }
+}
+
+/// JPEG thumbnail stored in the EXIF data of a JPEG file, if any
+///
+/// Cameras and phones store a small preview, typically 160x120 pixels, in the
+/// second image file directory of the EXIF segment.
+pub(crate) fn exif_thumbnail(bytes: &[u8]) -> Option<&[u8]> {
+    let exif = Exif::find(bytes)?;
+    // 第一个目录描述主图，其后的第二个目录描述缩略图
+    let second = exif.next_directory(exif.u32_at(4)?)?;
+    let (mut offset, mut length) = (None, None);
+    for (tag, entry) in exif.entries(second)? {
+        match tag {
+            0x0201 => offset = Some(exif.u32_at(entry + 8)?),
+            0x0202 => length = Some(exif.u32_at(entry + 8)?),
+            _ => {}
+        }
+    }
+    let (offset, length) = (offset?, length?);
+    let thumbnail = exif.tiff.get(offset..offset.checked_add(length)?)?;
+    thumbnail.starts_with(&[0xFF, 0xD8]).then_some(thumbnail)
+}
+
+/// Orientation tag (1-8) in the first image file directory of the EXIF data of a JPEG file
+///
+/// 1 is upright; the others give the mirroring and rotation that turn the
+/// stored pixels upright, as listed in the EXIF specification.
+pub(crate) fn exif_orientation(bytes: &[u8]) -> Option<u8> {
+    let exif = Exif::find(bytes)?;
+    let (_, entry) = exif.entries(exif.u32_at(4)?)?.find(|&(tag, _)| tag == 0x0112)?;
+    // 方向值是内联存放的SHORT
+    let orientation = u8::try_from(exif.u16_at(entry + 8)?).ok()?;
+    (1..=8).contains(&orientation).then_some(orientation)
+}
+
+/// Capture location in the EXIF data of a JPEG file as `(latitude, longitude)` in degrees
+///
+/// Southern latitudes and western longitudes are negative.
+pub(crate) fn exif_location(bytes: &[u8]) -> Option<(f64, f64)> {
+    let exif = Exif::find(bytes)?;
+    let (_, pointer) = exif.entries(exif.u32_at(4)?)?.find(|&(tag, _)| tag == 0x8825)?;
+    let gps = exif.u32_at(pointer + 8)?;
+
+    let (mut latitude, mut longitude) = (None, None);
+    let (mut south, mut west) = (false, false);
+    for (tag, entry) in exif.entries(gps)? {
+        match tag {
+            // 方向参考是内联存放的ASCII字符
+            0x0001 => south = exif.tiff.get(entry + 8) == Some(&b'S'),
+            0x0002 => latitude = exif.degrees(exif.u32_at(entry + 8)?),
+            0x0003 => west = exif.tiff.get(entry + 8) == Some(&b'W'),
+            0x0004 => longitude = exif.degrees(exif.u32_at(entry + 8)?),
+            _ => {}
+        }
+    }
+    let sign = |negative: bool| if negative { -1.0 } else { 1.0 };
+    Some((sign(south) * latitude?, sign(west) * longitude?))
+}
+
+/// TIFF structure in the EXIF segment of a JPEG file, with offsets relative to its start
+struct Exif<'a> {
+    tiff: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Exif<'a> {
+    /// EXIF data of a JPEG file, `None` if it has none
+    fn find(bytes: &'a [u8]) -> Option<Self> {
+        if !bytes.starts_with(&[0xFF, 0xD8]) {
+            return None;
+        }
+
+        let mut pos = 2;
+        let tiff = loop {
+            if *bytes.get(pos)? != 0xFF {
+                return None;
+            }
+            let marker = *bytes.get(pos + 1)?;
+            let length = u16::from_be_bytes([*bytes.get(pos + 2)?, *bytes.get(pos + 3)?]) as usize;
+            let segment = bytes.get(pos + 4..pos + 2 + length)?;
+            match marker {
+                0xE1 if segment.starts_with(b"Exif\0\0") => break &segment[6..],
+                // EXIF数据位于图像数据之前
+                0xDA | 0xD9 => return None,
+                _ => pos += 2 + length,
+            }
+        };
+
+        let little_endian = match tiff.get(..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        Some(Self { tiff, little_endian })
+    }
+
+    fn u16_at(&self, offset: usize) -> Option<usize> {
+        let raw = [*self.tiff.get(offset)?, *self.tiff.get(offset + 1)?];
+        Some(if self.little_endian { u16::from_le_bytes(raw) } else { u16::from_be_bytes(raw) } as usize)
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<usize> {
+        let raw: [u8; 4] = self.tiff.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian { u32::from_le_bytes(raw) } else { u32::from_be_bytes(raw) } as usize)
+    }
+
+    /// Tags of the entries of the image file directory at `directory`, each with the entry's offset
+    fn entries(&self, directory: usize) -> Option<impl Iterator<Item = (u16, usize)> + '_> {
+        let count = self.u16_at(directory)?;
+        let entries = (0..count).map(move |index| directory + 2 + 12 * index);
+        Some(entries.map_while(|entry| Some((self.u16_at(entry)? as u16, entry))))
+    }
+
+    /// Offset of the directory following the one at `directory`, `None` after the last
+    fn next_directory(&self, directory: usize) -> Option<usize> {
+        let next = self.u32_at(directory + 2 + 12 * self.u16_at(directory)?)?;
+        (next != 0).then_some(next)
+    }
+
+    /// Degrees, minutes and seconds stored as three rationals at `offset`, in degrees
+    fn degrees(&self, offset: usize) -> Option<f64> {
+        let rational = |index: usize| {
+            let (numerator, denominator) = (self.u32_at(offset + 8 * index)?, self.u32_at(offset + 8 * index + 4)?);
+            (denominator != 0).then(|| numerator as f64 / denominator as f64)
+        };
+        Some(rational(0)? + rational(1)? / 60.0 + rational(2)? / 3600.0)
+    }
+}
@@ -12,18 +12,50 @@
 //! 
 //! ## Example
 //! 
-//! ```rust
+//! ```rust,no_run
 //! use pdf_converter::PdfConverter;
 //! 
 //! let converter = PdfConverter::new();
 //! converter.convert_folder_to_pdf("images/", "output.pdf")?;
+//! # Ok::<(), pdf_converter::PdfError>(())
 //! ```
 
 pub mod converter;
+pub mod encryption;
 pub mod error;
+pub mod pdfx;
+#[cfg(feature = "qr")]
+pub mod qr;
+pub mod report;
+mod boxes;
+mod gif;
+mod jpeg;
+mod lossless;
+mod merge;
+mod outline;
+#[cfg(feature = "serde")]
+mod preset;
+mod preview;
+mod processing;
+#[cfg(feature = "serde")]
+mod project;
+#[cfg(feature = "psd")]
+mod psd;
+mod sidecar;
+mod smask;
+mod text;
+mod toc;
+mod xmp;
 
-pub use converter::{PdfConverter, PdfConfig};
+pub use converter::{Checkpoint, ChromaKey, ContentDpi, CropBox, DocumentBuilder, FrameSampling, Grid, ImageCompression, ImageCrop, MarginMode, PageBoxes, PageNumberFormat, PageOrder, PageOverlay, PageSize, PdfConverter, PdfConfig, Placement, ResizeFilter, ResourceLimits, RetryPolicy, ScaleMode, SortDirection, StripMode, StripOverlap, TextAlign, Tint, UnmatchedPages};
+pub use encryption::{Encryption, Permissions};
 pub use error::{PdfError, Result};
+pub use pdfx::PdfX;
+#[cfg(feature = "qr")]
+pub use qr::{Corner, QrContent, QrStamp};
+pub use report::{ConversionReport, ImageReport, Progress};
+pub use printpdf::{BlendMode, ImageRotation, ImageTransform, Mm, NonSeperableBlendMode, RenderingIntent, SeperableBlendMode};
+pub use regex::Regex;
 
 /// Default A4 page width in millimeters
 pub const A4_WIDTH_MM: f32 = 210.0;
@@ -36,3 +68,22 @@ pub const DEFAULT_MARGIN_MM: f32 = 20.0;
 
 /// Default DPI for image conversion
 pub const DEFAULT_DPI: f32 = 300.0;
+
+/// Default luma tolerance for detecting uniform borders when auto-trimming
+pub const DEFAULT_TRIM_TOLERANCE: u8 = 24;
+
+/// Default largest tilt in degrees corrected by deskewing
+pub const DEFAULT_DESKEW_MAX_ANGLE: f32 = 5.0;
+
+/// Default font size of notes pages in points
+pub const DEFAULT_NOTES_FONT_SIZE: f32 = 11.0;
+
+/// Default font size of header and footer text in points
+pub const DEFAULT_HEADER_FOOTER_FONT_SIZE: f32 = 9.0;
+
+/// Longest page side in millimeters that PDF viewers reliably accept
+///
+/// This is 14,400 PDF units (200 inches), the page size limit of the PDF
+/// specification's implementation limits. Image pages that would be larger,
+/// e.g. in native DPI or strip mode, are scaled down uniformly to fit.
+pub const MAX_PAGE_SIDE_MM: f32 = 5080.0;
@@ -0,0 +1,115 @@
+//! Pages of existing PDF files inserted between the image pages
+//!
+//! The pages are copied with lopdf into the finished document, keeping their
+//! vector content, fonts and images as they are. Attributes a page inherits
+//! from its page tree (resources, media box, crop box, rotation) are copied
+//! onto the page itself, since it is moved into the flat page tree printpdf
+//! writes.
+
+use std::path::{Path, PathBuf};
+use printpdf::lopdf::{Dictionary, Document, Object, ObjectId};
+use crate::error::{PdfError, Result};
+
+/// Page attributes a page may inherit from its parents (PDF 1.7, table 30)
+const INHERITABLE: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// Pages of a PDF file to insert into the generated document
+#[derive(Debug)]
+pub(crate) struct PdfInsert {
+    /// Position (0-based) among the generated pages before which the pages are inserted
+    pub position: usize,
+    /// PDF file whose pages are inserted
+    pub path: PathBuf,
+    /// Contents of the file
+    source: Document,
+}
+
+impl PdfInsert {
+    /// Read a PDF file to insert before the generated page at `position`
+    pub(crate) fn load(position: usize, path: &Path) -> Result<Self> {
+        let source = Document::load(path)?;
+        // 加密文件的内容流无法直接复制
+        if source.is_encrypted() {
+            return Err(PdfError::UnsupportedFormat {
+                path: path.display().to_string(),
+                format: "encrypted PDF".to_string(),
+            });
+        }
+        Ok(Self { position, path: path.to_path_buf(), source })
+    }
+
+    /// Number of pages the file adds
+    pub(crate) fn page_count(&self) -> usize {
+        self.source.get_pages().len()
+    }
+}
+
+/// Insert the pages of every PDF file at its position
+///
+/// Positions refer to the generated pages, so earlier inserts do not shift
+/// later ones; inserts at the same position keep their order.
+pub(crate) fn insert_pages(document: &mut Document, inserts: Vec<PdfInsert>) -> Result<()> {
+    let pages_id = document.catalog()?.get(b"Pages")?.as_reference()?;
+    let mut inserted = 0;
+
+    for insert in inserts {
+        log::debug!("  插入PDF页面: {} ({} 页)", insert.path.display(), insert.page_count());
+        let page_ids = import_pages(document, insert.source, pages_id)?;
+
+        let pages = document.get_object_mut(pages_id)?.as_dict_mut()?;
+        let kids = pages.get_mut(b"Kids")?.as_array_mut()?;
+        let position = (insert.position + inserted).min(kids.len());
+        let count = page_ids.len();
+        kids.splice(position..position, page_ids.into_iter().map(Object::Reference));
+        let total = kids.len() as i64;
+        pages.set("Count", total);
+        inserted += count;
+    }
+
+    // 源文件的目录和页面树不再被引用
+    document.prune_objects();
+    Ok(())
+}
+
+/// Move the objects of a PDF file into `document` and return its pages, now children of `parent`
+fn import_pages(document: &mut Document, mut source: Document, parent: ObjectId) -> Result<Vec<ObjectId>> {
+    source.renumber_objects_with(document.max_id + 1);
+
+    let page_ids: Vec<ObjectId> = source.get_pages().into_values().collect();
+    for &page_id in &page_ids {
+        let inherited = inherited_attributes(&source, page_id)?;
+        let page = source.get_object_mut(page_id)?.as_dict_mut()?;
+        for (key, value) in inherited.iter() {
+            page.set(key.clone(), value.clone());
+        }
+        page.set("Parent", parent);
+    }
+
+    document.max_id = document.max_id.max(source.max_id);
+    document.objects.extend(source.objects);
+    Ok(page_ids)
+}
+
+/// Inheritable attributes a page takes from its parents because it does not set them
+fn inherited_attributes(source: &Document, page_id: ObjectId) -> Result<Dictionary> {
+    let page = source.get_dictionary(page_id)?;
+    let mut inherited = Dictionary::new();
+    let mut parent = page.get(b"Parent").and_then(Object::as_reference).ok();
+    let mut visited = Vec::new();
+
+    // 防止损坏的页面树形成环
+    while let Some(node_id) = parent.filter(|id| !visited.contains(id)) {
+        visited.push(node_id);
+        let node = source.get_dictionary(node_id)?;
+        for key in INHERITABLE {
+            if !page.has(key) && !inherited.has(key) {
+                if let Ok(value) = node.get(key) {
+                    inherited.set(key, value.clone());
+                }
+            }
+        }
+        parent = node.get(b"Parent").and_then(Object::as_reference).ok();
+    }
+
+    Ok(inherited)
+}
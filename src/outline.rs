@@ -0,0 +1,124 @@
+//! Bookmark outlines nested by the folders of the images
+//!
+//! printpdf only writes a flat list of bookmarks, one per page. Nested
+//! outlines are written into the saved document instead, replacing the
+//! empty outline printpdf leaves when no bookmarks were added.
+
+use std::path::{Path, PathBuf};
+use printpdf::lopdf::{dictionary, Document, Object, ObjectId};
+use crate::error::Result;
+
+/// Bookmark pointing at a generated page, with the entries nested below it
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct OutlineNode {
+    pub title: String,
+    /// Position (0-based) of the page among the generated pages
+    pub page: usize,
+    pub children: Vec<OutlineNode>,
+    /// Folder entries are created for directories and hold the entries of their images
+    folder: bool,
+}
+
+/// Entries for images given as `(page, path, title)` in page order, grouped into folder entries
+///
+/// Folders are taken relative to `root`, or without one to the deepest
+/// folder containing all images; the root gets no entry itself. A folder
+/// entry points at the page of its first image. Consecutive pages of the same
+/// file, such as animation frames, get a single entry.
+pub(crate) fn folder_tree<'a>(root: Option<&Path>, images: impl IntoIterator<Item = (usize, &'a Path, String)>) -> Vec<OutlineNode> {
+    let images: Vec<_> = images.into_iter().collect();
+    let root = match root {
+        Some(root) => root.to_path_buf(),
+        None => common_folder(images.iter().map(|(_, path, _)| *path)),
+    };
+
+    let mut nodes: Vec<OutlineNode> = Vec::new();
+    let mut previous: Option<&Path> = None;
+    for (page, path, title) in &images {
+        if previous == Some(*path) {
+            continue;
+        }
+        previous = Some(path);
+
+        let folders = path.parent().and_then(|parent| parent.strip_prefix(&root).ok()).into_iter().flat_map(Path::components);
+        let mut level = &mut nodes;
+        for folder in folders {
+            let name = folder.as_os_str().to_string_lossy();
+            // 只与最后一个条目合并，保持页面顺序
+            let continues = matches!(level.last(), Some(last) if last.folder && last.title == name);
+            if !continues {
+                level.push(OutlineNode { title: name.into_owned(), page: *page, children: Vec::new(), folder: true });
+            }
+            level = &mut level.last_mut().expect("folder entry was just ensured").children;
+        }
+        level.push(OutlineNode { title: title.clone(), page: *page, children: Vec::new(), folder: false });
+    }
+    nodes
+}
+
+/// Deepest folder containing all of `paths`
+fn common_folder<'a>(paths: impl Iterator<Item = &'a Path>) -> PathBuf {
+    let mut root: Option<PathBuf> = None;
+    for path in paths {
+        let parent = path.parent().unwrap_or(Path::new(""));
+        root = Some(match root {
+            None => parent.to_path_buf(),
+            Some(root) => root.ancestors().find(|ancestor| parent.starts_with(ancestor)).unwrap_or(Path::new("")).to_path_buf(),
+        });
+    }
+    root.unwrap_or_default()
+}
+
+/// Replace the document outline with `nodes`, folder entries collapsed
+pub(crate) fn write(document: &mut Document, nodes: &[OutlineNode]) -> Result<()> {
+    if nodes.is_empty() {
+        return Ok(());
+    }
+    let page_ids: Vec<_> = document.get_pages().into_values().collect();
+    let outlines_id = document.new_object_id();
+    let (first, last) = add_level(document, &page_ids, nodes, outlines_id);
+    document.objects.insert(outlines_id, Object::Dictionary(dictionary! {
+        "Type" => "Outlines",
+        "First" => first,
+        "Last" => last,
+        "Count" => nodes.len() as i64,
+    }));
+
+    let catalog = document.catalog_mut()?;
+    let previous = catalog.get(b"Outlines").and_then(Object::as_reference).ok();
+    catalog.set("Outlines", outlines_id);
+    catalog.set("PageMode", "UseOutlines");
+    if let Some(previous) = previous {
+        document.objects.remove(&previous);
+    }
+    Ok(())
+}
+
+/// Add the entries of one level below `parent`, returning the first and last
+fn add_level(document: &mut Document, page_ids: &[ObjectId], nodes: &[OutlineNode], parent: ObjectId) -> (ObjectId, ObjectId) {
+    let ids: Vec<ObjectId> = nodes.iter().map(|_| document.new_object_id()).collect();
+    for (index, node) in nodes.iter().enumerate() {
+        let mut item = dictionary! {
+            "Title" => Object::string_literal(node.title.as_bytes()),
+            "Parent" => parent,
+        };
+        if let Some(&page_id) = page_ids.get(node.page) {
+            item.set("Dest", vec![page_id.into(), "Fit".into()]);
+        }
+        if index > 0 {
+            item.set("Prev", ids[index - 1]);
+        }
+        if let Some(&next) = ids.get(index + 1) {
+            item.set("Next", next);
+        }
+        if !node.children.is_empty() {
+            let (first, last) = add_level(document, page_ids, &node.children, ids[index]);
+            item.set("First", first);
+            item.set("Last", last);
+            // 负数表示条目默认折叠
+            item.set("Count", -(node.children.len() as i64));
+        }
+        document.objects.insert(ids[index], Object::Dictionary(item));
+    }
+    (ids[0], ids[ids.len() - 1])
+}
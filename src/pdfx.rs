@@ -0,0 +1,102 @@
+//! PDF/X output for commercial printing
+//!
+//! printpdf writes the PDF/X metadata (XMP packet, `GTS_PDFXVersion`, trapping
+//! flag) and an output intent with the Coated FOGRA39 CMYK profile. This
+//! module covers what printpdf leaves to the caller: images are embedded as
+//! DeviceCMYK, features the standard forbids are rejected before converting,
+//! and the finished document is checked for untagged RGB color.
+//!
+//! The RGB to CMYK conversion is a plain device conversion without color
+//! management; run the output through a preflight tool when exact print
+//! colors matter.
+
+use printpdf::lopdf::{Document, Object};
+use printpdf::PdfConformance;
+use crate::converter::PdfConfig;
+use crate::error::{PdfError, Result};
+
+/// PDF/X conformance level of the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PdfX {
+    /// PDF/X-1a:2003: CMYK and gray only, no transparency, no layers
+    X1a,
+    /// PDF/X-4: color-managed output that allows transparency and layers
+    X4,
+}
+
+impl PdfX {
+    /// Conformance passed to printpdf, which writes the matching metadata
+    pub(crate) fn conformance(self) -> PdfConformance {
+        match self {
+            Self::X1a => PdfConformance::X1A_2003_PDF_1_4,
+            Self::X4 => PdfConformance::X4_2010_PDF_1_4,
+        }
+    }
+}
+
+/// Reject configurations that cannot produce a conforming document
+pub(crate) fn check_config(config: &PdfConfig, level: PdfX) -> Result<()> {
+    if config.encryption.is_some() {
+        return Err(PdfError::Conformance("PDF/X documents must not be encrypted".to_string()));
+    }
+    if level == PdfX::X1a && config.blend_mode.is_some() {
+        return Err(PdfError::Conformance("PDF/X-1a does not allow blend modes".to_string()));
+    }
+    if level == PdfX::X1a && (config.mask_files || !config.masks.is_empty()) {
+        return Err(PdfError::Conformance("PDF/X-1a does not allow image masks".to_string()));
+    }
+
+    // 内置Helvetica字体不会嵌入，而PDF/X要求嵌入所有字体
+    let uses_text = config.generate_toc
+        || !config.notes.is_empty()
+        || config.header_text.is_some()
+        || config.footer_text.is_some();
+    if uses_text && config.font.is_none() {
+        return Err(PdfError::Conformance(
+            "PDF/X requires embedded fonts; set `font` for contents, notes, header or footer".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// CMYK samples (8 bits per component) of an image
+pub(crate) fn cmyk_samples(img: &::image::DynamicImage) -> Vec<u8> {
+    img.to_rgb8()
+        .pixels()
+        .flat_map(|pixel| {
+            let max = pixel.0.into_iter().max().unwrap_or(0);
+            if max == 0 {
+                return [0, 0, 0, 255];
+            }
+            // 黑色由最亮通道决定，其余油墨按剩余亮度比例计算
+            let ink = |channel: u8| ((max - channel) as u32 * 255 / max as u32) as u8;
+            [ink(pixel[0]), ink(pixel[1]), ink(pixel[2]), 255 - max]
+        })
+        .collect()
+}
+
+/// Apply the structural requirements printpdf misses and check the image color spaces
+pub(crate) fn finish(document: &mut Document, level: PdfX) -> Result<()> {
+    for (id, object) in &document.objects {
+        let Object::Stream(stream) = object else { continue };
+        let is_image = matches!(stream.dict.get(b"Subtype"), Ok(Object::Name(name)) if name == b"Image");
+        let is_rgb = matches!(stream.dict.get(b"ColorSpace"), Ok(Object::Name(name)) if name == b"DeviceRGB");
+        if is_image && is_rgb {
+            return Err(PdfError::Conformance(format!("image {} {} uses DeviceRGB without an ICC profile", id.0, id.1)));
+        }
+    }
+
+    match level {
+        // 可选内容（图层）在PDF/X-1a中不允许
+        PdfX::X1a => {
+            let catalog = document.catalog_mut()?;
+            catalog.remove(b"OCProperties");
+        }
+        // printpdf的图层需要PDF 1.5，PDF/X-4允许到1.6
+        PdfX::X4 => document.version = "1.6".to_string(),
+    }
+
+    Ok(())
+}
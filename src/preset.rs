@@ -0,0 +1,100 @@
+//! Serde representations of configuration values from other crates
+//!
+//! Blend modes and rendering intents are written as their PDF names, e.g.
+//! `"Multiply"` or `"Perceptual"`, and page order patterns as regex source
+//! strings, so presets stay readable in JSON or TOML.
+
+use printpdf::{BlendMode, NonSeperableBlendMode, RenderingIntent, SeperableBlendMode};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Every blend mode, for looking modes up by name
+const BLEND_MODES: [BlendMode; 16] = {
+    use NonSeperableBlendMode::*;
+    use SeperableBlendMode::*;
+    [
+        BlendMode::Seperable(Normal),
+        BlendMode::Seperable(Multiply),
+        BlendMode::Seperable(Screen),
+        BlendMode::Seperable(Overlay),
+        BlendMode::Seperable(Darken),
+        BlendMode::Seperable(Lighten),
+        BlendMode::Seperable(ColorDodge),
+        BlendMode::Seperable(ColorBurn),
+        BlendMode::Seperable(HardLight),
+        BlendMode::Seperable(SoftLight),
+        BlendMode::Seperable(Difference),
+        BlendMode::Seperable(Exclusion),
+        BlendMode::NonSeperable(Hue),
+        BlendMode::NonSeperable(Saturation),
+        BlendMode::NonSeperable(Color),
+        BlendMode::NonSeperable(Luminosity),
+    ]
+};
+
+/// Every rendering intent with its PDF name
+const RENDERING_INTENTS: [(RenderingIntent, &str); 4] = [
+    (RenderingIntent::AbsoluteColorimetric, "AbsoluteColorimetric"),
+    (RenderingIntent::RelativeColorimetric, "RelativeColorimetric"),
+    (RenderingIntent::Saturation, "Saturation"),
+    (RenderingIntent::Perceptual, "Perceptual"),
+];
+
+/// PDF name of a blend mode
+fn blend_mode_name(mode: BlendMode) -> String {
+    match printpdf::lopdf::Object::from(mode) {
+        printpdf::lopdf::Object::Name(name) => String::from_utf8_lossy(&name).into_owned(),
+        _ => unreachable!("blend modes are written as names"),
+    }
+}
+
+/// `#[serde(with)]` module for `Option<BlendMode>`
+pub(crate) mod blend_mode {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(mode: &Option<BlendMode>, serializer: S) -> Result<S::Ok, S::Error> {
+        mode.map(blend_mode_name).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<BlendMode>, D::Error> {
+        let Some(name) = Option::<String>::deserialize(deserializer)? else { return Ok(None) };
+        BLEND_MODES
+            .into_iter()
+            .find(|mode| blend_mode_name(*mode) == name)
+            .map(Some)
+            .ok_or_else(|| D::Error::custom(format!("unknown blend mode `{name}`")))
+    }
+}
+
+/// `#[serde(with)]` module for `Option<RenderingIntent>`
+pub(crate) mod rendering_intent {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(intent: &Option<RenderingIntent>, serializer: S) -> Result<S::Ok, S::Error> {
+        let name = intent.and_then(|intent| RENDERING_INTENTS.iter().find(|(candidate, _)| *candidate == intent));
+        name.map(|(_, name)| *name).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<RenderingIntent>, D::Error> {
+        let Some(name) = Option::<String>::deserialize(deserializer)? else { return Ok(None) };
+        RENDERING_INTENTS
+            .into_iter()
+            .find(|(_, candidate)| *candidate == name)
+            .map(|(intent, _)| Some(intent))
+            .ok_or_else(|| D::Error::custom(format!("unknown rendering intent `{name}`")))
+    }
+}
+
+/// `#[serde(with)]` module for `Regex`, written as its source pattern
+pub(crate) mod regex {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(pattern: &::regex::Regex, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(pattern.as_str())
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<::regex::Regex, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        ::regex::Regex::new(&pattern).map_err(D::Error::custom)
+    }
+}
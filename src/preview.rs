@@ -0,0 +1,67 @@
+//! SVG diagrams of image placement for debugging layouts
+
+use std::fmt::Write;
+use crate::converter::Placement;
+
+/// Horizontal gap between pages in millimeters
+const PAGE_GAP_MM: f32 = 10.0;
+
+/// Height reserved above each page for its label in millimeters
+const LABEL_HEIGHT_MM: f32 = 8.0;
+
+/// Draw pages side by side, each with its margins and placed image
+///
+/// Units are millimeters. Placements use PDF coordinates (origin bottom-left,
+/// Y up) and are flipped here, so the diagram looks like the rendered page.
+pub(crate) fn layout_svg(pages: &[(String, Placement)], margin_mm: f32) -> String {
+    let width_mm: f32 = pages.iter().map(|(_, p)| p.page.width_mm + PAGE_GAP_MM).sum::<f32>() + PAGE_GAP_MM;
+    let height_mm = pages.iter().map(|(_, p)| p.page.height_mm).fold(0.0, f32::max) + LABEL_HEIGHT_MM + 2.0 * PAGE_GAP_MM;
+
+    let mut svg = String::new();
+    // 写入String不会失败
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.1}mm" height="{h:.1}mm" viewBox="0 0 {w:.1} {h:.1}" font-family="sans-serif" font-size="5">"#,
+        w = width_mm,
+        h = height_mm,
+    );
+
+    let mut left_mm = PAGE_GAP_MM;
+    let top_mm = PAGE_GAP_MM + LABEL_HEIGHT_MM;
+    for (index, (name, placement)) in pages.iter().enumerate() {
+        let page = placement.page;
+        let image_top_mm = top_mm + page.height_mm - placement.y_mm - placement.height_mm;
+
+        let _ = writeln!(svg, r#"  <g transform="translate({:.1} 0)">"#, left_mm);
+        let _ = writeln!(svg, r#"    <text x="0" y="{:.1}">{}: {}</text>"#, top_mm - 2.0, index + 1, escape(name));
+        let _ = writeln!(
+            svg,
+            r#"    <rect x="0" y="{:.1}" width="{:.1}" height="{:.1}" fill="white" stroke="black" stroke-width="0.5"/>"#,
+            top_mm, page.width_mm, page.height_mm,
+        );
+        let _ = writeln!(
+            svg,
+            r#"    <rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="none" stroke="gray" stroke-width="0.3" stroke-dasharray="2 2"/>"#,
+            margin_mm,
+            top_mm + margin_mm,
+            (page.width_mm - 2.0 * margin_mm).max(0.0),
+            (page.height_mm - 2.0 * margin_mm).max(0.0),
+        );
+        let _ = writeln!(
+            svg,
+            r#"    <rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="lightsteelblue" fill-opacity="0.6" stroke="steelblue" stroke-width="0.5"/>"#,
+            placement.x_mm, image_top_mm, placement.width_mm, placement.height_mm,
+        );
+        let _ = writeln!(svg, "  </g>");
+
+        left_mm += page.width_mm + PAGE_GAP_MM;
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Escape text for use in SVG character data
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
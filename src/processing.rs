@@ -0,0 +1,406 @@
+//! Image buffer processing applied before placement
+
+use ::image::DynamicImage;
+
+/// Turn an image upright according to its EXIF orientation tag (1-8)
+pub(crate) fn apply_orientation(img: DynamicImage, orientation: u8) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+/// Crop near-uniform borders from an image
+///
+/// Each edge takes the colour of its outermost line as reference and is
+/// scanned inwards while every pixel of a row or column stays within
+/// `tolerance` (in 8-bit luma) of that reference, so white and black borders
+/// on different sides are both removed. Images that are uniform throughout are
+/// returned unchanged.
+pub(crate) fn trim_borders(img: DynamicImage, tolerance: u8) -> DynamicImage {
+    let luma = img.to_luma8();
+    let (width, height) = luma.dimensions();
+    if width == 0 || height == 0 {
+        return img;
+    }
+
+    let row_matches = |y: u32, reference: u8| {
+        (0..width).all(|x| luma.get_pixel(x, y)[0].abs_diff(reference) <= tolerance)
+    };
+    let col_matches = |x: u32, top: u32, bottom: u32, reference: u8| {
+        (top..bottom).all(|y| luma.get_pixel(x, y)[0].abs_diff(reference) <= tolerance)
+    };
+
+    let reference = luma.get_pixel(0, 0)[0];
+    let mut top = 0;
+    while top < height && row_matches(top, reference) {
+        top += 1;
+    }
+    if top == height {
+        return img;
+    }
+
+    let reference = luma.get_pixel(0, height - 1)[0];
+    let mut bottom = height;
+    while bottom > top && row_matches(bottom - 1, reference) {
+        bottom -= 1;
+    }
+
+    let reference = luma.get_pixel(0, top)[0];
+    let mut left = 0;
+    while left < width && col_matches(left, top, bottom, reference) {
+        left += 1;
+    }
+
+    let reference = luma.get_pixel(width - 1, top)[0];
+    let mut right = width;
+    while right > left && col_matches(right - 1, top, bottom, reference) {
+        right -= 1;
+    }
+
+    if (left, top, right, bottom) == (0, 0, width, height) {
+        return img;
+    }
+
+    log::debug!("  裁剪边框: {}x{} -> {}x{} px @ ({}, {})",
+        width, height, right - left, bottom - top, left, top);
+    img.crop_imm(left, top, right - left, bottom - top)
+}
+
+/// Pack a black-and-white image into rows of 1-bit samples, `None` if any pixel is not pure black or white
+///
+/// Gray and RGB images qualify; each row starts on a new byte, the first
+/// pixel in the highest bit, with 1 for white as in `DeviceGray`.
+pub(crate) fn bilevel_samples(img: &DynamicImage) -> Option<Vec<u8>> {
+    // 只接受无透明通道的灰度和RGB图片，RGB要求三个通道相同
+    let level = |samples: &[u8]| match samples {
+        [0] | [0, 0, 0] => Some(false),
+        [255] | [255, 255, 255] => Some(true),
+        _ => None,
+    };
+    let (width, channels, pixels): (usize, usize, &[u8]) = match img {
+        DynamicImage::ImageLuma8(gray) => (gray.width() as usize, 1, gray.as_raw()),
+        DynamicImage::ImageRgb8(rgb) => (rgb.width() as usize, 3, rgb.as_raw()),
+        _ => return None,
+    };
+    if width == 0 {
+        return None;
+    }
+
+    let row_len = width.div_ceil(8);
+    let mut packed = Vec::with_capacity(row_len * pixels.len() / (width * channels));
+    for row in pixels.chunks(width * channels) {
+        let mut bytes = vec![0u8; row_len];
+        for (x, pixel) in row.chunks(channels).enumerate() {
+            if level(pixel)? {
+                bytes[x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        packed.extend(bytes);
+    }
+    Some(packed)
+}
+
+/// Samples along the longer side of an image when classifying its content
+const CONTENT_SAMPLES: u32 = 200;
+
+/// Whether an image looks like a scanned document rather than a photo
+///
+/// Pixels are sampled on a regular grid. The image is text-like when at least
+/// `min_ink_paper_ratio` of the samples are within 64 levels of black or
+/// white in luma and their mean saturation is at most `max_saturation`.
+pub(crate) fn is_text_like(img: &DynamicImage, min_ink_paper_ratio: f32, max_saturation: f32) -> bool {
+    use ::image::GenericImageView;
+
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return false;
+    }
+    let step = (width.max(height) / CONTENT_SAMPLES).max(1);
+
+    let (mut samples, mut ink_or_paper, mut saturation) = (0u32, 0u32, 0.0f32);
+    for y in (0..height).step_by(step as usize) {
+        for x in (0..width).step_by(step as usize) {
+            let [r, g, b, _] = img.get_pixel(x, y).0;
+            let luma = (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000;
+            if !(64..192).contains(&luma) {
+                ink_or_paper += 1;
+            }
+            // HSV饱和度
+            let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+            if max > 0 {
+                saturation += (max - min) as f32 / max as f32;
+            }
+            samples += 1;
+        }
+    }
+
+    ink_or_paper as f32 / samples as f32 >= min_ink_paper_ratio
+        && saturation / samples as f32 <= max_saturation
+}
+
+/// Map the luminance of every pixel onto the gradient from `shadow` to `highlight`
+///
+/// Black becomes `shadow`, white becomes `highlight`, and the grays in
+/// between are interpolated per channel.
+pub(crate) fn duotone(img: &DynamicImage, shadow: [u8; 3], highlight: [u8; 3]) -> DynamicImage {
+    // 256级亮度预先计算对应颜色
+    let palette: Vec<[u8; 3]> = (0..=255u32)
+        .map(|luma| [0, 1, 2].map(|c| ((shadow[c] as u32 * (255 - luma) + highlight[c] as u32 * luma) / 255) as u8))
+        .collect();
+    let gray = img.to_luma8();
+    let tinted = ::image::RgbImage::from_fn(gray.width(), gray.height(), |x, y| {
+        ::image::Rgb(palette[gray.get_pixel(x, y)[0] as usize])
+    });
+    DynamicImage::ImageRgb8(tinted)
+}
+
+/// Replace the pixels close to `key` with `background`, blending the edges
+///
+/// A pixel's distance is the largest difference of its channels from the
+/// key color. Pixels within `tolerance` become `background`, pixels twice as
+/// far or more are kept, and those in between are mixed in proportion, so
+/// anti-aliased outlines do not keep a fringe of the old background. Existing
+/// transparency is blended onto `background` as well.
+pub(crate) fn chroma_key(img: &DynamicImage, key: [u8; 3], tolerance: u8, background: [u8; 3]) -> DynamicImage {
+    let tolerance = tolerance as u32;
+    let mut rgba = img.to_rgba8();
+    for pixel in rgba.pixels_mut() {
+        let distance = (0..3).map(|c| (pixel[c] as i32 - key[c] as i32).unsigned_abs()).max().unwrap_or(0);
+        // 容差到两倍容差之间线性过渡，容差为0时只去除完全相同的颜色
+        let keep = match distance {
+            d if d <= tolerance => 0,
+            d if d >= 2 * tolerance.max(1) => 255,
+            d => 255 * (d - tolerance) / tolerance.max(1),
+        };
+        let alpha = keep * pixel[3] as u32 / 255;
+        for c in 0..3 {
+            pixel[c] = ((pixel[c] as u32 * alpha + background[c] as u32 * (255 - alpha)) / 255) as u8;
+        }
+    }
+    DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8())
+}
+
+/// Blend the transparent parts of an image onto white
+///
+/// Images without an alpha channel are returned unchanged.
+pub(crate) fn flatten_alpha(img: DynamicImage) -> DynamicImage {
+    if !img.color().has_alpha() {
+        return img;
+    }
+    let mut rgba = img.to_rgba8();
+    for pixel in rgba.pixels_mut() {
+        let alpha = pixel[3] as u32;
+        for channel in &mut pixel.0[..3] {
+            *channel = ((*channel as u32 * alpha + 255 * (255 - alpha)) / 255) as u8;
+        }
+    }
+    DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8())
+}
+
+/// Samples compared per row when looking for the overlap of two images
+const OVERLAP_SAMPLES: usize = 256;
+
+/// Largest difference of a sample (in 8-bit levels) that still counts as a match
+const OVERLAP_TOLERANCE: u8 = 16;
+
+/// Number of rows at the top of `next` that repeat the bottom of `previous`
+///
+/// Both buffers hold raw rows of `row_len` bytes; `sample_len` is the size of
+/// one sample (2 for 16-bit images, whose high bytes are compared). The
+/// tallest band of at most `max_rows` rows wins in which at least
+/// `similarity` of the compared samples match, so a uniform background
+/// repeated at both edges counts as overlap too. Returns 0 when no band
+/// matches.
+pub(crate) fn vertical_overlap(previous: &[u8], next: &[u8], row_len: usize, sample_len: usize, similarity: f32, max_rows: usize) -> usize {
+    if row_len == 0 {
+        return 0;
+    }
+    let previous_rows = previous.len() / row_len;
+    let step = (row_len / OVERLAP_SAMPLES).max(1).next_multiple_of(sample_len);
+    let samples_per_row = row_len.div_ceil(step);
+    let max_rows = max_rows.min(previous_rows).min(next.len() / row_len);
+
+    (1..=max_rows)
+        .rev()
+        .find(|&rows| {
+            let allowed = ((1.0 - similarity.clamp(0.0, 1.0)) * (rows * samples_per_row) as f32) as usize;
+            let mut mismatches = 0;
+            for row in 0..rows {
+                let above = &previous[(previous_rows - rows + row) * row_len..][..row_len];
+                let below = &next[row * row_len..][..row_len];
+                for offset in (0..row_len).step_by(step) {
+                    if above[offset].abs_diff(below[offset]) > OVERLAP_TOLERANCE {
+                        mismatches += 1;
+                        // 超出允许的不匹配数后不必继续比较
+                        if mismatches > allowed {
+                            return false;
+                        }
+                    }
+                }
+            }
+            true
+        })
+        .unwrap_or(0)
+}
+
+/// Longest side of the working copy used for skew detection
+const SKEW_DETECTION_SIZE: u32 = 1000;
+
+/// Angular resolution of skew detection in degrees
+const SKEW_STEP_DEG: f32 = 0.1;
+
+/// Straighten a scan whose text lines are tilted by at most `max_angle_deg`
+///
+/// Returns the image unchanged when no skew is found within the bound, so
+/// photos that are deliberately tilted further are left alone.
+pub(crate) fn deskew(img: DynamicImage, max_angle_deg: f32) -> DynamicImage {
+    match detect_skew(&img, max_angle_deg) {
+        Some(angle) => {
+            log::debug!("  纠正倾斜: {:.1}°", angle);
+            rotate_about_center(&img, angle)
+        }
+        None => img,
+    }
+}
+
+/// Estimate the tilt of text lines in degrees (positive is clockwise on screen)
+///
+/// Dark pixels of a downscaled copy vote in a Hough accumulator restricted to
+/// near-horizontal lines. The angle whose accumulator is most sharply peaked,
+/// measured as the sum of squared bin counts, wins.
+fn detect_skew(img: &DynamicImage, max_angle_deg: f32) -> Option<f32> {
+    if max_angle_deg <= 0.0 {
+        return None;
+    }
+
+    let luma = img.thumbnail(SKEW_DETECTION_SIZE, SKEW_DETECTION_SIZE).to_luma8();
+    let (width, height) = luma.dimensions();
+    let mean = luma.pixels().map(|p| p[0] as u64).sum::<u64>() / (width as u64 * height as u64).max(1);
+    let threshold = (mean as u8).saturating_sub(48).min(160);
+
+    let ink: Vec<(f32, f32)> = luma
+        .enumerate_pixels()
+        .filter(|(_, _, p)| p[0] < threshold)
+        .map(|(x, y, _)| (x as f32, y as f32))
+        .collect();
+
+    // 空白页和很暗的照片没有可用的边缘信息
+    let ink_ratio = ink.len() as f32 / (width * height) as f32;
+    if !(0.001..=0.4).contains(&ink_ratio) {
+        return None;
+    }
+
+    let diagonal = ((width * width + height * height) as f32).sqrt().ceil() as usize;
+    let steps = (max_angle_deg / SKEW_STEP_DEG).round() as i32;
+    let mut accumulator = vec![0u32; 2 * diagonal + 1];
+
+    let mut best = (0.0f32, 0u64);
+    for step in -steps..=steps {
+        let angle = step as f32 * SKEW_STEP_DEG;
+        let (sin, cos) = angle.to_radians().sin_cos();
+
+        accumulator.iter_mut().for_each(|bin| *bin = 0);
+        for &(x, y) in &ink {
+            let rho = (y * cos - x * sin).round() as isize + diagonal as isize;
+            accumulator[rho as usize] += 1;
+        }
+
+        let score = accumulator.iter().map(|&n| n as u64 * n as u64).sum::<u64>();
+        if score > best.1 {
+            best = (angle, score);
+        }
+    }
+
+    // 小于检测步长的角度差不值得重新采样
+    (best.0.abs() >= SKEW_STEP_DEG).then_some(best.0)
+}
+
+/// Rotate an image about its center, keeping its size
+///
+/// Text lines tilted clockwise by `angle_deg` come out horizontal. Uncovered
+/// corners are filled with the colour of the top-left pixel, which for scans
+/// is the paper colour.
+fn rotate_about_center(img: &DynamicImage, angle_deg: f32) -> DynamicImage {
+    let (width, height) = (img.width(), img.height());
+    rotate_onto(img, angle_deg, width, height)
+}
+
+/// Rotate an image clockwise by `degrees`, enlarging the canvas to keep every pixel
+///
+/// Multiples of 90 degrees are exact pixel moves; other angles are resampled
+/// with the uncovered corners filled like `rotate_about_center` does.
+pub(crate) fn rotate(img: DynamicImage, degrees: f32) -> DynamicImage {
+    let degrees = degrees.rem_euclid(360.0);
+    if degrees == 0.0 {
+        img
+    } else if degrees == 90.0 {
+        img.rotate90()
+    } else if degrees == 180.0 {
+        img.rotate180()
+    } else if degrees == 270.0 {
+        img.rotate270()
+    } else {
+        let (out_width, out_height) = rotated_size(img.width(), img.height(), degrees);
+        rotate_onto(&img, -degrees, out_width, out_height)
+    }
+}
+
+/// Pixel size of an image of the given size after `rotate`
+pub(crate) fn rotated_size(width: u32, height: u32, degrees: f32) -> (u32, u32) {
+    let degrees = degrees.rem_euclid(360.0);
+    if degrees == 90.0 || degrees == 270.0 {
+        return (height, width);
+    }
+    if degrees == 0.0 || degrees == 180.0 {
+        return (width, height);
+    }
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (width, height) = (width as f32, height as f32);
+    (
+        (width * cos.abs() + height * sin.abs()).ceil() as u32,
+        (width * sin.abs() + height * cos.abs()).ceil() as u32,
+    )
+}
+
+/// Rotate an image counter-clockwise by `angle_deg` onto a canvas of the given size, centers aligned
+fn rotate_onto(img: &DynamicImage, angle_deg: f32, out_width: u32, out_height: u32) -> DynamicImage {
+    let src = img.to_rgba8();
+    let (width, height) = src.dimensions();
+    let background = *src.get_pixel(0, 0);
+    let (sin, cos) = angle_deg.to_radians().sin_cos();
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    let (ox, oy) = (out_width as f32 / 2.0, out_height as f32 / 2.0);
+
+    let rotated = ::image::RgbaImage::from_fn(out_width, out_height, |x, y| {
+        let (u, v) = (x as f32 + 0.5 - ox, y as f32 + 0.5 - oy);
+        let sx = u * cos - v * sin + cx - 0.5;
+        let sy = u * sin + v * cos + cy - 0.5;
+
+        if sx < 0.0 || sy < 0.0 || sx > (width - 1) as f32 || sy > (height - 1) as f32 {
+            return background;
+        }
+
+        // 双线性插值
+        let (x0, y0) = (sx.floor() as u32, sy.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+        let (fx, fy) = (sx - x0 as f32, sy - y0 as f32);
+        let corners = [src.get_pixel(x0, y0), src.get_pixel(x1, y0), src.get_pixel(x0, y1), src.get_pixel(x1, y1)];
+        let weights = [(1.0 - fx) * (1.0 - fy), fx * (1.0 - fy), (1.0 - fx) * fy, fx * fy];
+
+        let mut pixel = [0u8; 4];
+        for (channel, value) in pixel.iter_mut().enumerate() {
+            let sum: f32 = corners.iter().zip(weights).map(|(p, w)| p[channel] as f32 * w).sum();
+            *value = sum.round() as u8;
+        }
+        ::image::Rgba(pixel)
+    });
+
+    DynamicImage::ImageRgba8(rotated)
+}
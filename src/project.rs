@@ -0,0 +1,145 @@
+//! Project files describing a document built from several sections
+//!
+//! A project names the output file, an optional cover PDF, configuration
+//! overrides for the whole document and a list of sections, each with its
+//! own images and overrides. Files ending in `.toml` are read as TOML, all
+//! others as JSON:
+//!
+//! ```toml
+//! output = "book.pdf"
+//! cover = "cover.pdf"
+//!
+//! [config]
+//! title = "Holiday 2024"
+//!
+//! [[sections]]
+//! folder = "day1"
+//!
+//! [[sections]]
+//! manifest = "day2.txt"
+//! config = { margin_mm = 0.0 }
+//!
+//! [[sections]]
+//! images = ["map.png", "tickets.jpg"]
+//! ```
+//!
+//! Each section takes its images from exactly one of `folder`, `manifest` (a
+//! text file listing one image per line) or `images`. Relative paths are
+//! resolved against the directory of the project file, and paths in a
+//! manifest against the directory of the manifest.
+//!
+//! Overrides replace whole `PdfConfig` fields by name, so an override of
+//! `encryption` replaces the entire setting.
+
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use crate::converter::PdfConfig;
+use crate::error::{PdfError, Result};
+
+/// Parsed project file with paths resolved
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Project {
+    /// PDF file to write
+    pub output: PathBuf,
+    /// PDF file whose pages open the document
+    #[serde(default)]
+    pub cover: Option<PathBuf>,
+    /// Overrides applied to the converter's configuration for the whole document
+    #[serde(default)]
+    pub config: Map<String, Value>,
+    /// Parts of the document in order
+    pub sections: Vec<Section>,
+}
+
+/// One part of a project, converted with its own configuration
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Section {
+    /// Folder whose images are converted as by `convert_folder_to_pdf`
+    #[serde(default)]
+    pub folder: Option<PathBuf>,
+    /// Text file listing the images of the section
+    #[serde(default)]
+    manifest: Option<PathBuf>,
+    /// Images of the section in page order
+    #[serde(default)]
+    images: Vec<PathBuf>,
+    /// Overrides applied on top of the document configuration
+    #[serde(default)]
+    pub config: Map<String, Value>,
+}
+
+impl Section {
+    /// Images listed by `images` or the manifest; empty for folder sections
+    pub(crate) fn listed_images(&self) -> Result<Vec<PathBuf>> {
+        let Some(manifest) = &self.manifest else { return Ok(self.images.clone()) };
+        let text = std::fs::read_to_string(manifest)?;
+        let dir = manifest.parent().unwrap_or(Path::new(""));
+        // 跳过空行和以 # 开头的注释行
+        Ok(text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| dir.join(line))
+            .collect())
+    }
+}
+
+/// Read a project file, resolving its paths against the file's directory
+pub(crate) fn load(path: &Path) -> Result<Project> {
+    let text = std::fs::read_to_string(path)?;
+    let invalid = |reason: String| PdfError::InvalidProject {
+        path: path.display().to_string(),
+        reason,
+    };
+
+    let is_toml = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+    let mut project: Project = if is_toml {
+        toml::from_str(&text).map_err(|e| invalid(e.to_string()))?
+    } else {
+        serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?
+    };
+
+    for (index, section) in project.sections.iter().enumerate() {
+        let sources = section.folder.is_some() as usize + section.manifest.is_some() as usize + !section.images.is_empty() as usize;
+        if sources != 1 {
+            return Err(invalid(format!("section {} needs exactly one of `folder`, `manifest` or `images`", index + 1)));
+        }
+    }
+    if project.sections.is_empty() {
+        return Err(invalid("no sections".to_string()));
+    }
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    project.output = dir.join(&project.output);
+    project.cover = project.cover.map(|cover| dir.join(cover));
+    for section in &mut project.sections {
+        section.folder = section.folder.take().map(|folder| dir.join(folder));
+        section.manifest = section.manifest.take().map(|manifest| dir.join(manifest));
+        section.images = section.images.iter().map(|image| dir.join(image)).collect();
+    }
+    Ok(project)
+}
+
+/// `base` with the fields named in `overrides` replaced
+pub(crate) fn apply_overrides(base: &PdfConfig, overrides: &Map<String, Value>, project_path: &Path) -> Result<PdfConfig> {
+    if overrides.is_empty() {
+        return Ok(base.clone());
+    }
+    let invalid = |reason: String| PdfError::InvalidProject {
+        path: project_path.display().to_string(),
+        reason,
+    };
+
+    let mut config = serde_json::to_value(base).map_err(|e| invalid(e.to_string()))?;
+    let fields = config.as_object_mut().expect("PdfConfig serializes to an object");
+    for (name, value) in overrides {
+        if !fields.contains_key(name) {
+            return Err(invalid(format!("unknown configuration field `{name}`")));
+        }
+        fields.insert(name.clone(), value.clone());
+    }
+    serde_json::from_value(config).map_err(|e| invalid(e.to_string()))
+}
@@ -0,0 +1,194 @@
+//! Photoshop (PSD) files, read through their flattened composite image
+//!
+//! Every PSD file stores the visible layers merged into one image after the
+//! layer data, which is what viewers without layer support show. Only this
+//! composite is decoded, so layer effects and adjustment layers appear as
+//! Photoshop last saved them. Files saved without "Maximize Compatibility"
+//! may carry a blank composite.
+//!
+//! Grayscale, RGB and CMYK files with 8 or 16 bits per channel are supported,
+//! uncompressed or RLE-compressed. Large documents (PSB), other color modes and
+//! ZIP-compressed image data are reported as unsupported.
+
+use ::image::{DynamicImage, ImageBuffer};
+
+/// Signature and version 1 at the start of every PSD file
+const SIGNATURE: &[u8] = b"8BPS\x00\x01";
+
+/// Length of the file header
+pub(crate) const HEADER_LEN: usize = 26;
+
+/// Whether a file starts with the PSD signature
+pub(crate) fn is_psd(bytes: &[u8]) -> bool {
+    bytes.starts_with(SIGNATURE)
+}
+
+/// Pixel size from the file header, `None` when `bytes` is not a PSD header
+pub(crate) fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    if !is_psd(bytes) || bytes.len() < HEADER_LEN {
+        return None;
+    }
+    let mut reader = Reader { bytes, position: 14 };
+    let height = reader.u32().ok()?;
+    let width = reader.u32().ok()?;
+    Some((width, height))
+}
+
+/// Size in bytes of the decoded composite image, `None` when `bytes` is not a PSD header
+pub(crate) fn decoded_len(bytes: &[u8]) -> Option<u64> {
+    let (width, height) = dimensions(bytes)?;
+    let mut reader = Reader { bytes, position: 22 };
+    let depth = reader.u16().ok()?;
+    let mode = reader.u16().ok()?;
+    // 灰度保持单通道，RGB和CMYK都解码为RGB
+    let channels = if mode == 1 { 1 } else { 3 };
+    Some(width as u64 * height as u64 * channels * (depth as u64).div_ceil(8))
+}
+
+/// Decode the composite image, describing the problem when it cannot be read
+pub(crate) fn decode(bytes: &[u8]) -> Result<DynamicImage, String> {
+    let mut reader = Reader { bytes, position: SIGNATURE.len() + 6 };
+    let channels = reader.u16()? as usize;
+    let height = reader.u32()?;
+    let width = reader.u32()?;
+    let depth = reader.u16()?;
+    let mode = reader.u16()?;
+
+    let color_channels = match mode {
+        1 => 1,
+        3 => 3,
+        4 => 4,
+        _ => return Err(format!("color mode {mode}")),
+    };
+    if depth != 8 && depth != 16 {
+        return Err(format!("{depth} bits per channel"));
+    }
+    if channels < color_channels {
+        return Err(format!("{channels} channels in color mode {mode}"));
+    }
+
+    // 跳过色彩模式数据、图像资源以及图层和蒙版信息
+    for _ in 0..3 {
+        let len = reader.u32()? as usize;
+        reader.take(len)?;
+    }
+
+    let row_len = width as usize * depth as usize / 8;
+    let plane_len = row_len * height as usize;
+    let planes: Vec<Vec<u8>> = match reader.u16()? {
+        0 => (0..color_channels).map(|_| reader.take(plane_len).map(<[u8]>::to_vec)).collect::<Result<_, _>>()?,
+        1 => {
+            // 先是所有通道每行压缩后的字节数，再是按通道排列的压缩行
+            let counts = (0..channels * height as usize).map(|_| reader.u16()).collect::<Result<Vec<_>, _>>()?;
+            let mut planes = Vec::with_capacity(color_channels);
+            for channel_counts in counts.chunks(height.max(1) as usize).take(color_channels) {
+                let mut plane = Vec::with_capacity(plane_len);
+                for &count in channel_counts {
+                    unpack_bits(reader.take(count as usize)?, row_len, &mut plane)?;
+                }
+                planes.push(plane);
+            }
+            planes
+        }
+        compression => return Err(format!("compression method {compression}")),
+    };
+
+    Ok(match depth {
+        8 => composite_8(&planes, width, height),
+        _ => composite_16(&planes, width, height),
+    })
+}
+
+/// Interleave 8-bit planes into a grayscale or RGB image
+fn composite_8(planes: &[Vec<u8>], width: u32, height: u32) -> DynamicImage {
+    let pixels = width as usize * height as usize;
+    match planes {
+        [gray] => DynamicImage::ImageLuma8(ImageBuffer::from_raw(width, height, gray.clone()).expect("plane size")),
+        _ => {
+            let data = (0..pixels).flat_map(|i| rgb(planes, i, |plane, i| plane[i] as u32, 255)).map(|v| v as u8).collect();
+            DynamicImage::ImageRgb8(ImageBuffer::from_raw(width, height, data).expect("plane size"))
+        }
+    }
+}
+
+/// Interleave 16-bit big-endian planes into a grayscale or RGB image
+fn composite_16(planes: &[Vec<u8>], width: u32, height: u32) -> DynamicImage {
+    let pixels = width as usize * height as usize;
+    let sample = |plane: &[u8], i: usize| u16::from_be_bytes([plane[2 * i], plane[2 * i + 1]]) as u32;
+    match planes {
+        [gray] => {
+            let data = (0..pixels).map(|i| sample(gray, i) as u16).collect();
+            DynamicImage::ImageLuma16(ImageBuffer::from_raw(width, height, data).expect("plane size"))
+        }
+        _ => {
+            let data = (0..pixels).flat_map(|i| rgb(planes, i, sample, 65535)).map(|v| v as u16).collect();
+            DynamicImage::ImageRgb16(ImageBuffer::from_raw(width, height, data).expect("plane size"))
+        }
+    }
+}
+
+/// RGB samples of pixel `i` from RGB or CMYK planes
+fn rgb<F: Fn(&[u8], usize) -> u32>(planes: &[Vec<u8>], i: usize, sample: F, max: u32) -> [u32; 3] {
+    let [r, g, b] = [0, 1, 2].map(|channel| sample(&planes[channel], i));
+    match planes.get(3) {
+        // PSD按反相存储CMYK，数值越大油墨越少
+        Some(black) => {
+            let k = sample(black, i);
+            [r * k / max, g * k / max, b * k / max]
+        }
+        None => [r, g, b],
+    }
+}
+
+/// Decode one PackBits-compressed row of `row_len` bytes
+fn unpack_bits(mut packed: &[u8], row_len: usize, out: &mut Vec<u8>) -> Result<(), String> {
+    let end = out.len() + row_len;
+    while let [header, rest @ ..] = packed {
+        let header = *header as i8;
+        packed = rest;
+        match header {
+            // 之后的 header+1 个字节原样复制
+            0.. => {
+                let len = header as usize + 1;
+                let literal = packed.get(..len).ok_or("truncated RLE row")?;
+                out.extend_from_slice(literal);
+                packed = &packed[len..];
+            }
+            // -128 不表示任何数据
+            -128 => {}
+            // 下一个字节重复 1-header 次
+            _ => {
+                let (&value, rest) = packed.split_first().ok_or("truncated RLE row")?;
+                out.resize(out.len() + (1 - header as isize) as usize, value);
+                packed = rest;
+            }
+        }
+    }
+    if out.len() != end {
+        return Err("RLE row has the wrong length".to_string());
+    }
+    Ok(())
+}
+
+/// Big-endian reader over the file contents
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.position.checked_add(len).filter(|&end| end <= self.bytes.len()).ok_or("file is truncated")?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().expect("two bytes")))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().expect("four bytes")))
+    }
+}
@@ -0,0 +1,114 @@
+//! QR code stamps for tracking printed pages
+//!
+//! The code is drawn as vector squares in DeviceGray, so it stays sharp at
+//! any size and is allowed in PDF/X output.
+
+use printpdf::{path, Color, Greyscale, Mm, PdfLayerReference, Point, Polygon};
+use crate::converter::PageSize;
+use crate::error::Result;
+
+/// Light modules around the code required by the QR specification
+const QUIET_ZONE_MODULES: usize = 4;
+
+/// QR code stamped onto every page
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QrStamp {
+    /// What the code encodes
+    pub content: QrContent,
+    /// Page corner holding the code
+    pub corner: Corner,
+    /// Side length of the code including its quiet zone in millimeters
+    pub size_mm: f32,
+    /// Distance from the two page edges of the corner in millimeters
+    pub offset_mm: f32,
+}
+
+impl QrStamp {
+    /// Stamp `content` as a 20 mm code in the bottom-right corner, 5 mm from the edges
+    pub fn new(content: QrContent) -> Self {
+        Self {
+            content,
+            corner: Corner::default(),
+            size_mm: 20.0,
+            offset_mm: 5.0,
+        }
+    }
+}
+
+/// Data encoded in a QR stamp
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QrContent {
+    /// File name of the image shown on the page; pages without an image get no code
+    FileName,
+    /// Fixed text where `{page}` and `{total}` are replaced with the page number and page count
+    Text(String),
+}
+
+/// Corner of a page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Corner {
+    /// Top-left corner
+    TopLeft,
+    /// Top-right corner
+    TopRight,
+    /// Bottom-left corner
+    BottomLeft,
+    /// Bottom-right corner
+    #[default]
+    BottomRight,
+}
+
+/// Draw a QR code encoding `data` onto a page
+pub(crate) fn draw(layer: &PdfLayerReference, page: PageSize, stamp: &QrStamp, data: &str) -> Result<()> {
+    let code = qrcode::QrCode::new(data)?;
+    let modules = code.width();
+    let module_mm = stamp.size_mm / (modules + 2 * QUIET_ZONE_MODULES) as f32;
+
+    // PDF坐标原点在左下角
+    let x0 = match stamp.corner {
+        Corner::TopLeft | Corner::BottomLeft => stamp.offset_mm,
+        Corner::TopRight | Corner::BottomRight => page.width_mm - stamp.offset_mm - stamp.size_mm,
+    };
+    let y0 = match stamp.corner {
+        Corner::BottomLeft | Corner::BottomRight => stamp.offset_mm,
+        Corner::TopLeft | Corner::TopRight => page.height_mm - stamp.offset_mm - stamp.size_mm,
+    };
+    let square = |x: f32, y: f32, side: f32| {
+        vec![
+            (Point::new(Mm(x), Mm(y)), false),
+            (Point::new(Mm(x + side), Mm(y)), false),
+            (Point::new(Mm(x + side), Mm(y + side)), false),
+            (Point::new(Mm(x), Mm(y + side)), false),
+        ]
+    };
+    let fill = |rings| Polygon {
+        rings,
+        mode: path::PaintMode::Fill,
+        winding_order: path::WindingOrder::NonZero,
+    };
+
+    // 白色底板兼作静区，二维码第一行在顶部
+    let dark: Vec<_> = code
+        .to_colors()
+        .into_iter()
+        .enumerate()
+        .filter(|(_, color)| *color == qrcode::Color::Dark)
+        .map(|(index, _)| {
+            let (column, row) = (index % modules, index / modules);
+            let x = x0 + (QUIET_ZONE_MODULES + column) as f32 * module_mm;
+            let y = y0 + stamp.size_mm - (QUIET_ZONE_MODULES + row + 1) as f32 * module_mm;
+            square(x, y, module_mm)
+        })
+        .collect();
+
+    layer.save_graphics_state();
+    layer.set_fill_color(Color::Greyscale(Greyscale::new(1.0, None)));
+    layer.add_polygon(fill(vec![square(x0, y0, stamp.size_mm)]));
+    layer.set_fill_color(Color::Greyscale(Greyscale::new(0.0, None)));
+    layer.add_polygon(fill(dark));
+    layer.restore_graphics_state();
+    Ok(())
+}
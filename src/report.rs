@@ -0,0 +1,91 @@
+//! Summaries of finished conversions
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use crate::converter::PageSize;
+
+/// Summary of a finished conversion
+#[derive(Debug, Clone)]
+pub struct ConversionReport {
+    /// Path of the written PDF
+    pub output: PathBuf,
+    /// Size of the written PDF in bytes
+    pub output_bytes: u64,
+    /// Number of pages in the document, including contents and notes pages
+    pub page_count: usize,
+    /// The converted images in page order
+    pub images: Vec<ImageReport>,
+    /// Images below `PdfConfig::min_effective_dpi`, in page order
+    pub low_resolution: Vec<PathBuf>,
+    /// Files that were found but not converted because they are not images
+    pub skipped: Vec<PathBuf>,
+    /// Wall-clock time of the whole conversion
+    pub elapsed: Duration,
+}
+
+/// Summary of a single converted image
+#[derive(Debug, Clone)]
+pub struct ImageReport {
+    /// Path of the source image
+    pub path: PathBuf,
+    /// Embedded pixel size as width and height, after trimming or downscaling
+    pub pixels: (u32, u32),
+    /// Pixels per inch at the displayed size, the lower of both directions
+    pub effective_dpi: f32,
+    /// Size of the page showing the image
+    pub page: PageSize,
+    /// Time spent decoding, processing and embedding the image
+    pub elapsed: Duration,
+}
+
+/// Progress of a running conversion, see `PdfConverter::with_progress`
+#[derive(Debug, Clone, Copy)]
+pub struct Progress<'a> {
+    /// Position of the image just converted in page order, starting at 0
+    pub index: usize,
+    /// Number of images in the conversion, `None` when not known in advance
+    pub total: Option<usize>,
+    /// The image just converted
+    pub image: &'a ImageReport,
+    /// Wall-clock time since the conversion started
+    pub elapsed: Duration,
+}
+
+/// Format a report the way the command-line tools print their summaries
+pub(crate) fn write_report<W: Write>(report: &ConversionReport, mut w: W) -> std::io::Result<()> {
+    writeln!(w, "✅ PDF generated: {}", report.output.display())?;
+    writeln!(w, "📄 PDF size: {} KB", report.output_bytes / 1024)?;
+    writeln!(w, "📑 Pages: {} ({} images)", report.page_count, report.images.len())?;
+    writeln!(w, "⏱️  Time: {:?}", report.elapsed)?;
+
+    for (index, image) in report.images.iter().enumerate() {
+        writeln!(
+            w,
+            "  {:>3}. {} - {}x{} px on {:.1}x{:.1} mm ({:?})",
+            index + 1,
+            image.path.display(),
+            image.pixels.0,
+            image.pixels.1,
+            image.page.width_mm,
+            image.page.height_mm,
+            image.elapsed,
+        )?;
+    }
+
+    if !report.low_resolution.is_empty() {
+        writeln!(w, "⚠️  Low resolution images: {}", report.low_resolution.len())?;
+        for path in &report.low_resolution {
+            writeln!(w, "     - {}", path.display())?;
+        }
+    }
+
+    if !report.skipped.is_empty() {
+        writeln!(w, "⏭️  Skipped {} files:", report.skipped.len())?;
+        for path in &report.skipped {
+            writeln!(w, "     - {}", path.display())?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! Per-image rotations read from sidecar files written by other tools
+//!
+//! Two formats are accepted, both mapping file names to clockwise rotations
+//! in degrees:
+//!
+//! - JSON: an object such as `{"scan_001.jpg": 90, "scan_002.jpg": -90}`
+//! - CSV: one `file name,degrees` pair per line; empty lines, lines starting
+//!   with `#` and a header line whose rotation is not a number are skipped
+//!
+//! Entries are keyed by file name without directories, matching the images
+//! of a folder or archive regardless of where the sidecar lives.
+
+use std::collections::HashMap;
+use std::path::Path;
+use crate::error::{PdfError, Result};
+
+/// Read the rotations of a sidecar file, detecting JSON by its leading `{`
+pub(crate) fn load_rotations(path: &Path) -> Result<HashMap<String, f32>> {
+    let text = std::fs::read_to_string(path)?;
+    let invalid = |reason: String| PdfError::InvalidSidecar {
+        path: path.display().to_string(),
+        reason,
+    };
+
+    if text.trim_start().starts_with('{') {
+        parse_json(&text).map_err(invalid)
+    } else {
+        parse_csv(&text).map_err(invalid)
+    }
+}
+
+/// Parse a JSON object of file names and numeric rotations
+fn parse_json(text: &str) -> std::result::Result<HashMap<String, f32>, String> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let object = value.as_object().ok_or("expected a JSON object")?;
+
+    object
+        .iter()
+        .map(|(name, degrees)| match degrees.as_f64() {
+            Some(degrees) => Ok((name.clone(), degrees as f32)),
+            None => Err(format!("rotation of {name} is not a number")),
+        })
+        .collect()
+}
+
+/// Parse `file name,degrees` lines
+fn parse_csv(text: &str) -> std::result::Result<HashMap<String, f32>, String> {
+    let mut rotations = HashMap::new();
+    let mut first_record = true;
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let is_first = std::mem::replace(&mut first_record, false);
+        // 文件名中可能含逗号，取最后一个逗号分隔
+        let (name, degrees) = line
+            .rsplit_once(',')
+            .ok_or_else(|| format!("line {}: expected `file name,degrees`", index + 1))?;
+        let name = name.trim().trim_matches('"');
+        match degrees.trim().parse::<f32>() {
+            Ok(degrees) => {
+                rotations.insert(name.to_string(), degrees);
+            }
+            // 首行可能是表头
+            Err(_) if is_first => continue,
+            Err(_) => return Err(format!("line {}: rotation is not a number", index + 1)),
+        }
+    }
+
+    Ok(rotations)
+}
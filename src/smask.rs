@@ -0,0 +1,69 @@
+//! Soft masks attached to embedded images
+//!
+//! printpdf writes an image's soft mask as a direct stream inside the image
+//! dictionary, which readers reject, so masks are added to the saved
+//! document instead. Masked images are tagged with a placeholder `BBox`
+//! holding the mask's number; the tag is replaced by a reference to the mask
+//! image when the document is post-processed.
+
+use printpdf::lopdf::{dictionary, Document, Object, Stream};
+use printpdf::CurTransMat;
+use crate::error::Result;
+
+/// Grayscale mask of one image, 255 where the image is opaque
+#[derive(Debug, Clone)]
+pub(crate) struct SoftMask {
+    pub width: u32,
+    pub height: u32,
+    /// One 8-bit sample per pixel, rows top to bottom
+    pub samples: Vec<u8>,
+}
+
+/// Placeholder `BBox` marking an image for the mask with number `index`
+///
+/// A matrix with a zero scale never occurs as a real bounding box.
+pub(crate) fn tag(index: usize) -> CurTransMat {
+    CurTransMat::Raw([0.0, 0.0, 0.0, 0.0, index as f32, 0.0])
+}
+
+/// Replace the tags of masked images with references to their masks
+pub(crate) fn apply(document: &mut Document, masks: Vec<SoftMask>) -> Result<()> {
+    if masks.is_empty() {
+        return Ok(());
+    }
+    let tagged: Vec<_> = document
+        .objects
+        .iter()
+        .filter_map(|(&id, object)| {
+            let bbox = object.as_stream().ok()?.dict.get(b"BBox").ok()?.as_array().ok()?;
+            let numbers: Vec<f32> = bbox.iter().map(|value| value.as_float().unwrap_or(f32::NAN)).collect();
+            match numbers[..] {
+                [0.0, 0.0, 0.0, 0.0, index, 0.0] => Some((id, index as usize)),
+                _ => None,
+            }
+        })
+        .collect();
+
+    for (image_id, index) in tagged {
+        let Some(mask) = masks.get(index) else { continue };
+        let mut stream = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => mask.width as i64,
+                "Height" => mask.height as i64,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            mask.samples.clone(),
+        );
+        // 压缩失败时保留未压缩的数据
+        let _ = stream.compress();
+        let mask_id = document.add_object(stream);
+
+        let image = document.get_object_mut(image_id)?.as_stream_mut()?;
+        image.dict.remove(b"BBox");
+        image.dict.set("SMask", Object::Reference(mask_id));
+    }
+    Ok(())
+}
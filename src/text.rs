@@ -0,0 +1,198 @@
+//! Text layout for rendered text, using a custom font or built-in Helvetica
+
+use std::path::Path;
+use printpdf::{BuiltinFont, IndirectFontRef, Mm, PdfDocumentReference, PdfLayerReference, PdfPageIndex};
+use crate::converter::PageSize;
+use crate::error::{PdfError, Result};
+
+/// Line height as a multiple of the font size
+const LINE_SPACING: f32 = 1.2;
+
+/// Points per millimeter
+pub(crate) const PT_PER_MM: f32 = 72.0 / 25.4;
+
+/// Helvetica advance widths for ASCII 32..=126 in 1/1000 em (Adobe AFM)
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Width of a character outside the table (the width of a digit)
+const DEFAULT_WIDTH: u16 = 556;
+
+/// Font used for all text rendered into a document, with its glyph metrics
+pub(crate) struct TextFont {
+    font: IndirectFontRef,
+    metrics: Metrics,
+}
+
+/// Source of glyph advance widths
+enum Metrics {
+    /// Built-in Helvetica, measured with the AFM table
+    Helvetica,
+    /// Embedded TrueType/OpenType font, measured from its own tables
+    External(Vec<u8>),
+}
+
+impl TextFont {
+    /// Add the font at `path` to the document, or built-in Helvetica when `None`
+    pub(crate) fn load(doc: &PdfDocumentReference, path: Option<&Path>) -> Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self {
+                font: doc.add_builtin_font(BuiltinFont::Helvetica)?,
+                metrics: Metrics::Helvetica,
+            });
+        };
+
+        let data = std::fs::read(path)?;
+        if let Err(e) = ttf_parser::Face::parse(&data, 0) {
+            return Err(PdfError::InvalidFont(format!("{}: {}", path.display(), e)));
+        }
+        let font = doc.add_external_font(data.as_slice())?;
+        Ok(Self {
+            font,
+            metrics: Metrics::External(data),
+        })
+    }
+
+    /// Width of a string in millimeters at the given size in points
+    pub(crate) fn text_width_mm(&self, text: &str, font_size: f32) -> f32 {
+        let em = match &self.metrics {
+            Metrics::Helvetica => helvetica_width_em(text),
+            // 字体在加载时已校验，这里不会解析失败
+            Metrics::External(data) => match ttf_parser::Face::parse(data, 0) {
+                Ok(face) => external_width_em(&face, text),
+                Err(_) => helvetica_width_em(text),
+            },
+        };
+        em * font_size / PT_PER_MM
+    }
+}
+
+/// Width of a string in em when set in Helvetica
+fn helvetica_width_em(text: &str) -> f32 {
+    let units: u32 = text
+        .chars()
+        .map(|c| {
+            (c as usize)
+                .checked_sub(32)
+                .and_then(|i| HELVETICA_WIDTHS.get(i))
+                .copied()
+                .unwrap_or(DEFAULT_WIDTH) as u32
+        })
+        .sum();
+    units as f32 / 1000.0
+}
+
+/// Width of a string in em when set in an embedded font; missing glyphs are skipped like printpdf does
+fn external_width_em(face: &ttf_parser::Face, text: &str) -> f32 {
+    let units: u32 = text
+        .chars()
+        .filter_map(|c| face.glyph_index(c))
+        .filter_map(|glyph| face.glyph_hor_advance(glyph))
+        .map(u32::from)
+        .sum();
+    units as f32 / face.units_per_em() as f32
+}
+
+/// Break text into lines no wider than `max_width_mm`
+///
+/// Line breaks in the input start a new line and blank lines are kept. Words
+/// longer than a whole line are split between characters.
+pub(crate) fn wrap_text(font: &TextFont, text: &str, font_size: f32, max_width_mm: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+            if font.text_width_mm(&candidate, font_size) <= max_width_mm {
+                line = candidate;
+                continue;
+            }
+
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            // 单词本身超过行宽时按字符拆分
+            for c in word.chars() {
+                line.push(c);
+                if font.text_width_mm(&line, font_size) > max_width_mm && line.chars().count() > 1 {
+                    line.pop();
+                    lines.push(std::mem::replace(&mut line, c.to_string()));
+                }
+            }
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Append pages showing `text` word-wrapped within the margins
+///
+/// Text that does not fit on one page continues on further pages. Only
+/// characters of the Windows-1252 character set can be shown by the built-in
+/// font; others are dropped, as are characters missing from a custom font.
+/// Returns the added pages in order.
+pub(crate) fn add_text_pages(
+    doc: &PdfDocumentReference,
+    font: &TextFont,
+    text: &str,
+    page: PageSize,
+    margin_mm: f32,
+    font_size: f32,
+) -> Result<Vec<PdfPageIndex>> {
+    let lines_per_page = lines_per_page(page, margin_mm, font_size);
+    let lines = wrap_text(font, text, font_size, page.width_mm - 2.0 * margin_mm);
+
+    let mut pages = Vec::new();
+    for chunk in lines.chunks(lines_per_page) {
+        let (page_index, layer_index) = doc.add_page(Mm(page.width_mm), Mm(page.height_mm), "Layer 1");
+        let layer = doc.get_page(page_index).get_layer(layer_index);
+        write_lines(&layer, chunk, &font.font, font_size, margin_mm, page.height_mm - margin_mm - font_size / PT_PER_MM);
+        pages.push(page_index);
+    }
+
+    Ok(pages)
+}
+
+/// Number of pages `add_text_pages` adds for `text`
+pub(crate) fn text_pages_needed(font: &TextFont, text: &str, page: PageSize, margin_mm: f32, font_size: f32) -> usize {
+    let lines = wrap_text(font, text, font_size, page.width_mm - 2.0 * margin_mm);
+    lines.len().div_ceil(lines_per_page(page, margin_mm, font_size))
+}
+
+/// Distance between baselines in millimeters
+pub(crate) fn line_height_mm(font_size: f32) -> f32 {
+    font_size * LINE_SPACING / PT_PER_MM
+}
+
+/// Number of lines fitting between the top and bottom margins, at least one
+pub(crate) fn lines_per_page(page: PageSize, margin_mm: f32, font_size: f32) -> usize {
+    let line_height_mm = line_height_mm(font_size);
+    let usable_height_mm = (page.height_mm - 2.0 * margin_mm).max(line_height_mm);
+    ((usable_height_mm / line_height_mm).floor() as usize).max(1)
+}
+
+/// Write a single line with its baseline starting at (`x_mm`, `y_mm`)
+pub(crate) fn write_line(layer: &PdfLayerReference, font: &TextFont, text: &str, font_size: f32, x_mm: f32, y_mm: f32) {
+    layer.use_text(text, font_size, Mm(x_mm), Mm(y_mm), &font.font);
+}
+
+/// Write lines top to bottom, the first baseline at `top_mm`
+fn write_lines(layer: &PdfLayerReference, lines: &[String], font: &IndirectFontRef, font_size: f32, x_mm: f32, top_mm: f32) {
+    layer.begin_text_section();
+    layer.set_font(font, font_size);
+    layer.set_line_height(font_size * LINE_SPACING);
+    layer.set_text_cursor(Mm(x_mm), Mm(top_mm));
+    for line in lines {
+        layer.write_text(line.as_str(), font);
+        layer.add_line_break();
+    }
+    layer.end_text_section();
+}
@@ -0,0 +1,131 @@
+//! Table of contents pages linking to the image pages
+//!
+//! printpdf can only create links to URIs, so the entries are laid out while
+//! building the document and their internal links are added to the finished
+//! document with lopdf.
+
+use printpdf::lopdf::{Dictionary, Document, Object};
+use printpdf::PdfLayerReference;
+use crate::converter::PageSize;
+use crate::error::Result;
+use crate::text::{self, TextFont, PT_PER_MM};
+
+/// Heading shown at the top of the first contents page
+const HEADING: &str = "Contents";
+
+/// Heading size as a multiple of the entry font size
+const HEADING_SCALE: f32 = 1.5;
+
+/// Lines taken by the heading on the first contents page
+const HEADING_LINES: usize = 2;
+
+/// Gap between an entry's title and its page number in millimeters
+const NUMBER_GAP_MM: f32 = 5.0;
+
+/// Clickable area of a contents entry
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TocLink {
+    /// Position (0-based) of the contents page holding the entry
+    pub toc_page: usize,
+    /// Position (0-based) of the page the entry points to
+    pub target_page: usize,
+    /// Left, bottom, right and top edge of the entry in millimeters
+    pub rect_mm: [f32; 4],
+}
+
+/// Number of contents pages needed for `entries` entries
+pub(crate) fn pages_needed(entries: usize, page: PageSize, margin_mm: f32, font_size: f32) -> usize {
+    (entries + HEADING_LINES).div_ceil(text::lines_per_page(page, margin_mm, font_size))
+}
+
+/// Write the heading and entries onto the contents pages, one layer per page
+///
+/// `entries` holds each entry's title and the position (0-based) of its target
+/// page; the printed page numbers are 1-based. Titles too long for a line are
+/// shortened.
+pub(crate) fn write_toc(
+    layers: &[PdfLayerReference],
+    font: &TextFont,
+    entries: &[(String, usize)],
+    page: PageSize,
+    margin_mm: f32,
+    font_size: f32,
+) -> Vec<TocLink> {
+    let lines_per_page = text::lines_per_page(page, margin_mm, font_size);
+    let line_height_mm = text::line_height_mm(font_size);
+    let first_baseline_mm = page.height_mm - margin_mm - font_size / PT_PER_MM;
+
+    if let Some(layer) = layers.first() {
+        text::write_line(layer, font, HEADING, font_size * HEADING_SCALE, margin_mm, first_baseline_mm);
+    }
+
+    let mut links = Vec::new();
+    for (index, (title, target_page)) in entries.iter().enumerate() {
+        let slot = index + HEADING_LINES;
+        let (toc_page, row) = (slot / lines_per_page, slot % lines_per_page);
+        let Some(layer) = layers.get(toc_page) else { break };
+        let baseline_mm = first_baseline_mm - row as f32 * line_height_mm;
+
+        let number = (target_page + 1).to_string();
+        let number_width_mm = font.text_width_mm(&number, font_size);
+        let title_width_mm = page.width_mm - 2.0 * margin_mm - number_width_mm - NUMBER_GAP_MM;
+        let title = shorten(font, title, font_size, title_width_mm);
+
+        text::write_line(layer, font, &title, font_size, margin_mm, baseline_mm);
+        text::write_line(layer, font, &number, font_size, page.width_mm - margin_mm - number_width_mm, baseline_mm);
+
+        // 链接区域覆盖整行
+        let descent_mm = 0.25 * line_height_mm;
+        links.push(TocLink {
+            toc_page,
+            target_page: *target_page,
+            rect_mm: [margin_mm, baseline_mm - descent_mm, page.width_mm - margin_mm, baseline_mm - descent_mm + line_height_mm],
+        });
+    }
+
+    links
+}
+
+/// Add the links of the contents entries to a finished document
+pub(crate) fn add_links(document: &mut Document, links: &[TocLink]) -> Result<()> {
+    let page_ids: Vec<_> = document.get_pages().into_values().collect();
+
+    for link in links {
+        let (Some(&page_id), Some(&target_id)) = (page_ids.get(link.toc_page), page_ids.get(link.target_page)) else {
+            continue;
+        };
+        let rect = link.rect_mm.iter().map(|mm| Object::Real(mm * PT_PER_MM)).collect();
+        let annotation_id = document.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Annot".to_vec())),
+            ("Subtype", Object::Name(b"Link".to_vec())),
+            ("Rect", Object::Array(rect)),
+            ("Border", Object::Array(vec![0.into(), 0.into(), 0.into()])),
+            ("Dest", Object::Array(vec![Object::Reference(target_id), Object::Name(b"Fit".to_vec())])),
+        ]));
+
+        let page = document.get_object_mut(page_id)?.as_dict_mut()?;
+        match page.get_mut(b"Annots") {
+            Ok(Object::Array(annotations)) => annotations.push(Object::Reference(annotation_id)),
+            _ => page.set("Annots", Object::Array(vec![Object::Reference(annotation_id)])),
+        }
+    }
+
+    Ok(())
+}
+
+/// Cut a title down with a trailing "..." until it fits `max_width_mm`
+fn shorten(font: &TextFont, title: &str, font_size: f32, max_width_mm: f32) -> String {
+    if font.text_width_mm(title, font_size) <= max_width_mm {
+        return title.to_string();
+    }
+
+    let mut chars: Vec<char> = title.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let candidate = format!("{}...", chars.iter().collect::<String>().trim_end());
+        if font.text_width_mm(&candidate, font_size) <= max_width_mm {
+            return candidate;
+        }
+    }
+    "...".to_string()
+}
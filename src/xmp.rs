@@ -0,0 +1,26 @@
+//! Document-level XMP metadata supplied by the caller
+//!
+//! The packet is stored as the catalog's `/Metadata` stream, uncompressed so
+//! that tools scanning files for XMP packets find it.
+
+use printpdf::lopdf::{Dictionary, Document, Object, Stream};
+use crate::error::Result;
+
+/// Replace the document's XMP metadata stream with `packet`
+pub(crate) fn set_metadata(document: &mut Document, packet: &str) -> Result<()> {
+    let dictionary = Dictionary::from_iter(vec![
+        ("Type", Object::Name(b"Metadata".to_vec())),
+        ("Subtype", Object::Name(b"XML".to_vec())),
+    ]);
+    let stream = Stream::new(dictionary, packet.as_bytes().to_vec()).with_compression(false);
+    let metadata_id = document.add_object(stream);
+    let catalog = document.catalog_mut()?;
+    let previous = catalog.get(b"Metadata").and_then(Object::as_reference).ok();
+    catalog.set("Metadata", metadata_id);
+
+    // printpdf写入的元数据不再被引用
+    if let Some(previous) = previous {
+        document.objects.remove(&previous);
+    }
+    Ok(())
+}